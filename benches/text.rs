@@ -0,0 +1,174 @@
+//! Compares the rope-backed [`Text`] with the `Vec<String>` storage
+//! buffers used previously.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+
+#[allow(dead_code, unused_imports)]
+#[path = "../src/edit/text.rs"]
+mod text;
+use text::Text;
+
+const LINE: &str = "The quick brown fox jumps over the lazy dog, again and again and again.";
+const SIZES: [usize; 3] = [ 1_000, 100_000, 1_000_000 ];
+
+
+
+/// The previous storage of `Buffer`, reduced to the operations that are benchmarked.
+#[derive(Clone)]
+struct VecText {
+    lines: Vec<String>,
+}
+
+impl VecText {
+
+    fn from_str(text: &str) -> Self {
+        Self { lines: text.lines().map(str::to_string).collect() }
+    }
+
+    fn insert(&mut self, (line, col): (usize, usize), text: &str) {
+        let tail = self.lines[line].split_off(col);
+
+        let mut segments = text.split('\n');
+        self.lines[line].push_str(segments.next().unwrap());
+
+        let mut idx = line;
+        for segment in segments {
+            idx += 1;
+            self.lines.insert(idx, segment.to_string());
+        }
+
+        self.lines[idx].push_str(&tail);
+    }
+
+    fn remove(&mut self, (sy, sx): (usize, usize), (ey, ex): (usize, usize)) -> String {
+        if sy == ey {
+            return self.lines[sy].drain(sx..ex).collect();
+        }
+
+        let tail = self.lines[ey].split_off(ex);
+        let mut removed: Vec<String> = self.lines.drain(sy + 1..=ey).collect();
+        removed.insert(0, self.lines[sy].split_off(sx));
+        self.lines[sy].push_str(&tail);
+
+        removed.join("\n")
+    }
+
+}
+
+fn document(lines: usize) -> String {
+    vec![ LINE; lines ].join("\n")
+}
+
+fn bench_load(c: &mut Criterion) {
+    let mut group = c.benchmark_group("load");
+    group.sample_size(10);
+
+    for size in SIZES {
+        let doc = document(size);
+        group.bench_with_input(BenchmarkId::new("vec", size), &doc, |b, doc| {
+            b.iter(|| VecText::from_str(black_box(doc)))
+        });
+        group.bench_with_input(BenchmarkId::new("rope", size), &doc, |b, doc| {
+            b.iter(|| Text::from(black_box(doc.as_str())))
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_insert_line(c: &mut Criterion) {
+    let mut group = c.benchmark_group("insert_line");
+
+    for size in SIZES {
+        let doc = document(size);
+        let pos = (size / 2, 0);
+
+        let vec = VecText::from_str(&doc);
+        group.bench_function(BenchmarkId::new("vec", size), |b| {
+            b.iter_batched_ref(|| vec.clone(), |t| t.insert(pos, "\n"), BatchSize::LargeInput)
+        });
+
+        let rope = Text::from(doc.as_str());
+        group.bench_function(BenchmarkId::new("rope", size), |b| {
+            b.iter_batched_ref(|| rope.clone(), |t| t.insert(pos, "\n"), BatchSize::LargeInput)
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_delete_line(c: &mut Criterion) {
+    let mut group = c.benchmark_group("delete_line");
+
+    for size in SIZES {
+        let doc = document(size);
+        let (start, end) = ((1, 0), (2, 0));
+
+        let vec = VecText::from_str(&doc);
+        group.bench_function(BenchmarkId::new("vec", size), |b| {
+            b.iter_batched_ref(|| vec.clone(), |t| t.remove(start, end), BatchSize::LargeInput)
+        });
+
+        let rope = Text::from(doc.as_str());
+        group.bench_function(BenchmarkId::new("rope", size), |b| {
+            b.iter_batched_ref(|| rope.clone(), |t| t.remove(start, end), BatchSize::LargeInput)
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_insert_char(c: &mut Criterion) {
+    let mut group = c.benchmark_group("insert_char");
+
+    for size in SIZES {
+        let doc = document(size);
+        let pos = (size / 2, LINE.len() / 2);
+
+        let vec = VecText::from_str(&doc);
+        group.bench_function(BenchmarkId::new("vec", size), |b| {
+            b.iter_batched_ref(|| vec.clone(), |t| t.insert(pos, "x"), BatchSize::LargeInput)
+        });
+
+        let rope = Text::from(doc.as_str());
+        group.bench_function(BenchmarkId::new("rope", size), |b| {
+            b.iter_batched_ref(|| rope.clone(), |t| t.insert(pos, "x"), BatchSize::LargeInput)
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_read_lines(c: &mut Criterion) {
+    let mut group = c.benchmark_group("read_visible_lines");
+
+    for size in SIZES {
+        let doc = document(size);
+        // roughly what the renderer reads each frame
+        let (start, count) = (size / 2, 50);
+
+        let vec = VecText::from_str(&doc);
+        group.bench_function(BenchmarkId::new("vec", size), |b| {
+            b.iter(|| vec.lines[start..start + count].iter().map(String::len).sum::<usize>())
+        });
+
+        let rope = Text::from(doc.as_str());
+        group.bench_function(BenchmarkId::new("rope", size), |b| {
+            b.iter(|| rope.lines().skip(start).take(count).map(|l| l.len()).sum::<usize>())
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_load,
+    bench_insert_line,
+    bench_delete_line,
+    bench_insert_char,
+    bench_read_lines,
+);
+criterion_main!(benches);
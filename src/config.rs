@@ -1,60 +1,241 @@
 use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::rc::Rc;
 use crate::edit::Statusline;
 
 use macroquad::prelude::*;
 
 use crate::edit::Mode;
+use crate::edit::motion::{Motion, Operator, Scope, TextObject};
+use crate::edit::search::Search;
 use crate::edit::event::{EventData, Event};
 use crate::{Application, keybind};
 
-mod keys;
+pub mod keys;
+#[macro_use]
+pub mod actions;
+pub mod registry;
+pub mod file;
+pub mod commands;
 use keys::{Keybind, Key, Modifiers};
+use registry::Registry;
 
 
 
-pub type StatuslineCallback = fn(&Application) -> Statusline;
-pub type Action = fn(&mut Application);
-pub type Autocmd = fn(&mut Application, &EventData);
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum KeymapError {
+    #[error("unknown action `{0}`")]
+    UnknownAction(String),
+    #[error("`{0}` cannot be used in {1} mode")]
+    UnsupportedMode(String, Mode),
+}
+
+// callbacks are closures, so that they can capture data, eg. the file to open or the snippet to insert
+pub type StatuslineCallback = Rc<dyn Fn(&Application) -> Statusline>;
+/// Receives the count typed before the key, if any (eg. `5` in `5j`).
+pub type Action = Rc<dyn Fn(&mut Application, Option<usize>)>;
+pub type AutocmdCallback = Rc<dyn Fn(&mut Application, &EventData)>;
+
+/// A callback subscribed to an event, see [`Config::add_autocmd`].
+#[derive(Clone)]
+pub struct Autocmd {
+    pub event: Event,
+    /// only runs for buffers whose file matches this glob, eg. `*.rs`
+    pub pattern: Option<String>,
+    /// allows clearing related autocmds at once, see [`Config::clear_group`]
+    pub group: Option<String>,
+    /// removed after it has run for the first time
+    pub once: bool,
+    pub callback: AutocmdCallback,
+}
+
+impl Autocmd {
+
+    pub fn new(event: Event, callback: impl Fn(&mut Application, &EventData) + 'static) -> Self {
+        Self { event, pattern: None, group: None, once: false, callback: Rc::new(callback) }
+    }
+
+    #[must_use]
+    pub fn pattern(self, pattern: impl Into<String>) -> Self {
+        Self { pattern: Some(pattern.into()), ..self }
+    }
+
+    #[must_use]
+    pub fn group(self, group: impl Into<String>) -> Self {
+        Self { group: Some(group.into()), ..self }
+    }
+
+    #[must_use]
+    pub fn once(self) -> Self {
+        Self { once: true, ..self }
+    }
+
+    /// Patterns without a slash match the file name, others the whole path.
+    /// Buffers without a file never match a pattern.
+    #[must_use]
+    pub fn matches(&self, file: Option<&Path>) -> bool {
+        let Some(pattern) = &self.pattern else { return true };
+        let Some(file) = file else { return false };
+
+        let name = if pattern.contains('/') {
+            file.to_str()
+        } else {
+            file.file_name().and_then(|name| name.to_str())
+        };
+        name.is_some_and(|name| glob_match(pattern, name))
+    }
+
+}
+
+impl fmt::Debug for Autocmd {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Autocmd")
+            .field("event", &self.event)
+            .field("pattern", &self.pattern)
+            .field("group", &self.group)
+            .field("once", &self.once)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Matches `*` against any characters, and `?` against exactly one.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+
+    // position of the last `*`, and of the text it has been matched up to
+    let mut star = None;
+    let (mut p, mut t) = (0, 0);
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => {
+                // let the last `*` match one more character
+                let Some((star_p, star_t)) = star else { return false };
+                star = Some((star_p, star_t + 1));
+                p = star_p + 1;
+                t = star_t + 1;
+            }
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
 
-#[derive(Debug, Clone)]
+/// Result of looking up the keys typed so far.
+#[derive(Clone, Default)]
+pub struct Lookup {
+    /// action bound to exactly the typed keys
+    pub action: Option<Action>,
+    /// whether the typed keys are the beginning of a longer binding
+    pub is_prefix: bool,
+}
+
+#[derive(Clone)]
 pub struct Config {
     keybinds: HashMap<Keybind, Action>,
-    autocmds: HashMap<Event, Autocmd>,
+    /// in the order they have been added
+    autocmds: Vec<Autocmd>,
     statusline: StatuslineCallback,
+    registry: Registry,
+    leader: Key,
+    /// seconds to wait for the next key of an ambiguous sequence (`g` vs `gg`)
+    timeout: f64,
+    /// seconds a key is held down before it repeats
+    repeat_delay: f64,
+    /// repeats per second of a key held down
+    repeat_rate: f64,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
-            autocmds: HashMap::default(),
+            autocmds: Vec::new(),
             keybinds: HashMap::default(),
-            statusline: |_| Statusline::default(),
+            statusline: Rc::new(|_| Statusline::default()),
+            registry: Registry::default(),
+            leader: Key::new(KeyCode::Space, Modifiers::NoMod),
+            timeout: 1.,
+            repeat_delay: 0.25,
+            repeat_rate: 20.,
         }
     }
 }
 
+impl fmt::Debug for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Config")
+            .field("keybinds", &self.keybinds.keys())
+            .field("autocmds", &self.autocmds)
+            .field("registry", &self.registry)
+            .field("leader", &self.leader)
+            .field("timeout", &self.timeout)
+            .field("repeat_delay", &self.repeat_delay)
+            .field("repeat_rate", &self.repeat_rate)
+            .finish_non_exhaustive()
+    }
+}
+
 impl Config {
 
-    pub fn set_status(&mut self, action: StatuslineCallback) {
-        self.statusline = action;
+    pub fn set_status(&mut self, callback: impl Fn(&Application) -> Statusline + 'static) {
+        self.statusline = Rc::new(callback);
+    }
+
+    pub fn keymap(&mut self, keybind: Keybind, action: impl Fn(&mut Application, Option<usize>) + 'static) {
+        self.bind(keybind, Rc::new(action));
     }
 
-    pub fn keymap(&mut self, keybind: Keybind, action: Action) {
+    /// Binds an action that is already shared, eg. one of the [`Registry`].
+    pub fn bind(&mut self, keybind: Keybind, action: Action) {
         self.keybinds.insert(keybind, action);
     }
 
-    pub fn autocmd(&mut self, ev: Event, action: Autocmd) {
-        self.autocmds.insert(ev, action);
+    /// Subscribes to `ev`, in addition to the autocmds subscribed to it already.
+    pub fn autocmd(&mut self, ev: Event, callback: impl Fn(&mut Application, &EventData) + 'static) {
+        self.add_autocmd(Autocmd::new(ev, callback));
+    }
+
+    pub fn add_autocmd(&mut self, autocmd: Autocmd) {
+        self.autocmds.push(autocmd);
+    }
+
+    /// Removes all autocmds of `group`, so that they can be added anew.
+    pub fn clear_group(&mut self, group: &str) {
+        self.autocmds.retain(|autocmd| autocmd.group.as_deref() != Some(group));
     }
 
     #[must_use]
-    pub fn autocmds(&self) -> &HashMap<Event, Autocmd> {
+    pub fn autocmds(&self) -> &[Autocmd] {
         &self.autocmds
     }
 
+    /// Returns the callbacks of the autocmds that `ev` triggers for a buffer of `file`,
+    /// in the order they have been added. Autocmds that only run once are removed.
+    pub fn triggered(&mut self, ev: &EventData, file: Option<&Path>) -> Vec<AutocmdCallback> {
+        let is_triggered = |autocmd: &Autocmd| autocmd.event == ev.base() && autocmd.matches(file);
+
+        let callbacks = self.autocmds
+            .iter()
+            .filter(|autocmd| is_triggered(autocmd))
+            .map(|autocmd| autocmd.callback.clone())
+            .collect();
+        self.autocmds.retain(|autocmd| !(autocmd.once && is_triggered(autocmd)));
+        callbacks
+    }
+
     #[must_use]
-    pub fn statusline(&self) -> fn(&Application) -> Statusline {
-        self.statusline
+    pub fn statusline(&self) -> StatuslineCallback {
+        self.statusline.clone()
     }
 
     #[must_use]
@@ -62,14 +243,97 @@ impl Config {
         &self.keybinds
     }
 
-}
+    #[must_use]
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
 
+    #[must_use]
+    pub fn registry_mut(&mut self) -> &mut Registry {
+        &mut self.registry
+    }
 
-// TODO: refactor into function
-macro_rules! buf {
-    ($app:expr) => {
-        $app.ed.buf_mut().unwrap()
+    /// Binds the action registered as `name`, if it is meant for the mode of `keybind`.
+    pub fn keymap_named(&mut self, keybind: Keybind, name: &str) -> Result<(), KeymapError> {
+        let info = self.registry.get(name).ok_or_else(|| KeymapError::UnknownAction(name.to_string()))?;
+        if !info.supports(keybind.mode) {
+            return Err(KeymapError::UnsupportedMode(name.to_string(), keybind.mode));
+        }
+
+        let action = info.action.clone();
+        self.bind(keybind, action);
+        Ok(())
+    }
+
+    /// Sets the key that [`Key::LEADER`] stands for.
+    pub fn set_leader(&mut self, key: Key) {
+        self.leader = key;
     }
+
+    #[must_use]
+    pub fn leader(&self) -> Key {
+        self.leader
+    }
+
+    pub fn set_timeout(&mut self, timeout: f64) {
+        self.timeout = timeout;
+    }
+
+    #[must_use]
+    pub fn timeout(&self) -> f64 {
+        self.timeout
+    }
+
+    pub fn set_repeat_delay(&mut self, delay: f64) {
+        self.repeat_delay = delay;
+    }
+
+    #[must_use]
+    pub fn repeat_delay(&self) -> f64 {
+        self.repeat_delay
+    }
+
+    pub fn set_repeat_rate(&mut self, rate: f64) {
+        self.repeat_rate = rate;
+    }
+
+    #[must_use]
+    pub fn repeat_rate(&self) -> f64 {
+        self.repeat_rate
+    }
+
+    fn resolve(&self, key: Key) -> Key {
+        if key == Key::LEADER { self.leader } else { key }
+    }
+
+    /// Returns the bindings of `mode` starting with `keys`, with the leader resolved.
+    fn bindings(&self, mode: Mode, keys: &[Key]) -> impl Iterator<Item = (Vec<Key>, Action)> {
+        self.keybinds
+            .iter()
+            .filter(move |(bind, _)| bind.mode == mode)
+            .map(|(bind, action)| (bind.keys.iter().map(|&k| self.resolve(k)).collect::<Vec<_>>(), action.clone()))
+            .filter(move |(bound, _)| bound.starts_with(keys))
+    }
+
+    /// Returns the keys that continue a binding after `keys` have been typed.
+    #[must_use]
+    pub fn next_keys(&self, mode: Mode, keys: &[Key]) -> Vec<Key> {
+        self.bindings(mode, keys)
+            .filter_map(|(bound, _)| bound.get(keys.len()).copied())
+            .collect()
+    }
+
+    #[must_use]
+    pub fn lookup(&self, mode: Mode, keys: &[Key]) -> Lookup {
+        self.bindings(mode, keys).fold(Lookup::default(), |lookup, (bound, action)| {
+            if bound.len() == keys.len() {
+                Lookup { action: Some(action), ..lookup }
+            } else {
+                Lookup { is_prefix: true, ..lookup }
+            }
+        })
+    }
+
 }
 
 
@@ -78,7 +342,14 @@ pub fn configure(app: &mut Application) {
     app.config.set_status(|app| {
         let ed = &app.ed;
 
-        let mode = ed.buf().unwrap().mode().to_string();
+        let count = app.count().map(|c| c.to_string()).unwrap_or_default();
+        let pending = app.pending_keys().iter().map(Key::to_string).collect::<String>();
+        let register = match app.register() {
+            Some(register)                          => format!("\"{register}"),
+            None if app.is_selecting_register() => "\"".to_string(),
+            None                                    => String::new(),
+        };
+        let mode = format!("{} {register}{count}{pending}", ed.buf().unwrap().mode()).trim_end().to_string();
         let buf_count = ed.buffers().count();
         let win = ed.windows().idcount;
         let win_count = ed.windows().count();
@@ -96,11 +367,13 @@ pub fn configure(app: &mut Application) {
             let char         = buf.cursor().x + 1;
             let append       = if buf.append { "[A]" } else { "[_]" };
             let linecount    = buf.getlines().len();
-            let search_query = &buf.search_query;
-            let search_count = buf.search().len();
-            let clipboard    = buf.clipboard.len();
+            let search       = ed.search().zip(ed.bufid()).map(|(search, id)| {
+                let (index, count) = Search::position(&ed.matches(search, id), buf.cursor());
+                format!(" | /{} [{index}/{count}]", search.pattern())
+            }).unwrap_or_default();
+            let kills        = ed.registers().kills();
             Statusline::new(
-                format!("{mode} {append} | {filename} | {search_query} ({search_count}) | Clipboard: {clipboard}"),
+                format!("{mode} {append} | {filename}{search} | Kills: {kills}"),
                 format!("{linecount} Lines | {line}:{char}"),
                 format!("{buf_count} Buffers | {win}/{win_count} Windows")
             )
@@ -113,101 +386,202 @@ pub fn configure(app: &mut Application) {
         }
     });
 
-    app.config.autocmd(Event::BufDel, |_app, data| {
-        dbg!(data);
+    app.config.autocmd(Event::BufDel, |app, data| {
+        app.ed.add_message(format!("{data:?}"));
     });
-    app.config.autocmd(Event::BufNew, |_app, data| {
-        dbg!(data);
+    app.config.autocmd(Event::BufNew, |app, data| {
+        app.ed.add_message(format!("{data:?}"));
     });
 
-    app.config.keymap(keybind!(Normal, P, Shift), |app| app.ed.buf_mut().unwrap().paste());
-    app.config.keymap(keybind!(Normal, P, NoMod), |app| app.ed.buf_mut().unwrap().paste_pop());
-    app.config.keymap(keybind!(Normal, Y, NoMod), |app| app.ed.buf_mut().unwrap().yank_line());
-    app.config.keymap(keybind!(Normal, J, NoMod), |app| app.ed.buf_mut().unwrap().move_down());
-    app.config.keymap(keybind!(Normal, K, NoMod), |app| app.ed.buf_mut().unwrap().move_up());
-    app.config.keymap(keybind!(Normal, L, NoMod), |app| app.ed.buf_mut().unwrap().move_right());
-    app.config.keymap(keybind!(Normal, H, NoMod), |app| app.ed.buf_mut().unwrap().move_left());
-
-    app.config.keymap(keybind!(Normal, D, Ctrl),  |app| app.ed.buf_mut().unwrap().move_down_many(10));
-    app.config.keymap(keybind!(Normal, U, Ctrl),  |app| app.ed.buf_mut().unwrap().move_up_many(10));
-    app.config.keymap(keybind!(Normal, D, NoMod), |app| app.ed.buf_mut().unwrap().delete_line());
-    app.config.keymap(keybind!(Normal, M, NoMod), |app| app.ed.show_messages());
-    app.config.keymap(keybind!(Normal, G, Shift), |app| app.ed.buf_mut().unwrap().move_bottom());
-    app.config.keymap(keybind!(Normal, G, NoMod), |app| app.ed.buf_mut().unwrap().move_top());
-    app.config.keymap(keybind!(Normal, I, NoMod), |app| app.ed.buf_mut().unwrap().set_mode(Mode::Insert));
-    app.config.keymap(keybind!(Normal, I, Shift), |app| {
-        app.ed.buf_mut().unwrap().move_start_line();
-        app.ed.buf_mut().unwrap().set_mode(Mode::Insert);
-    });
+    app.config.keymap(keybind!(Normal, P, NoMod), actions::paste);
+    app.config.keymap(keybind!(Normal, P, Shift), actions::paste_before);
+    app.config.keymap(keybind!(Normal, Y, Meta),  actions::paste_cycle);
+
+    // motions and operators are shared between normal, visual and operator-pending mode.
+    // operators apply to the selection in visual mode, and motions to the pending operator
+    for mode in [Mode::Normal, Mode::Visual, Mode::VisualLine, Mode::VisualBlock, Mode::OperatorPending] {
+        let bind = |key_code, mods| Keybind::new(Key::new(key_code, mods), mode);
+
+        app.config.keymap(bind(KeyCode::J,    Modifiers::NoMod), actions::move_down);
+        app.config.keymap(bind(KeyCode::K,    Modifiers::NoMod), actions::move_up);
+        app.config.keymap(bind(KeyCode::L,    Modifiers::NoMod), actions::move_right);
+        app.config.keymap(bind(KeyCode::H,    Modifiers::NoMod), actions::move_left);
+        app.config.keymap(bind(KeyCode::G,    Modifiers::Shift), actions::goto_bottom);
+        app.config.keymap(Keybind::sequence([ Key::new(KeyCode::G, Modifiers::NoMod); 2 ], mode), actions::goto_top);
+        app.config.keymap(bind(KeyCode::Key0, Modifiers::NoMod), actions::line_start);
+        app.config.keymap(bind(KeyCode::Key4, Modifiers::Shift), actions::line_end);
+        app.config.keymap(bind(KeyCode::W,    Modifiers::NoMod), actions::word_forward);
+        app.config.keymap(bind(KeyCode::E,    Modifiers::NoMod), actions::word_end);
+        app.config.keymap(bind(KeyCode::B,    Modifiers::NoMod), actions::word_backward);
+        app.config.keymap(bind(KeyCode::RightBracket, Modifiers::Shift), actions::paragraph_forward);
+        app.config.keymap(bind(KeyCode::LeftBracket,  Modifiers::Shift), actions::paragraph_backward);
+
+        app.config.keymap(bind(KeyCode::D,      Modifiers::NoMod), actions::delete);
+        app.config.keymap(bind(KeyCode::C,      Modifiers::NoMod), actions::change);
+        app.config.keymap(bind(KeyCode::Y,      Modifiers::NoMod), actions::yank);
+        app.config.keymap(bind(KeyCode::Period, Modifiers::Shift), actions::indent);
+        app.config.keymap(bind(KeyCode::Comma,  Modifiers::Shift), actions::dedent);
+    }
 
-    app.config.keymap(keybind!(Normal, Q, NoMod), |app| app.quit());
+    for mode in [Mode::Normal, Mode::Visual, Mode::VisualLine, Mode::VisualBlock] {
+        let bind = |key_code, mods| Keybind::new(Key::new(key_code, mods), mode);
 
-    app.config.keymap(keybind!(Insert, Backspace, NoMod), |app| {
-        let buf = buf!(app);
-        let append = buf.append;
+        app.config.keymap(bind(KeyCode::D, Modifiers::Ctrl),  actions::page_down);
+        app.config.keymap(bind(KeyCode::U, Modifiers::Ctrl),  actions::page_up);
+        app.config.keymap(bind(KeyCode::V, Modifiers::NoMod), actions::visual);
+        app.config.keymap(bind(KeyCode::V, Modifiers::Shift), actions::visual_line);
+        app.config.keymap(bind(KeyCode::V, Modifiers::Ctrl),  actions::visual_block);
+    }
 
-        buf.move_left();
-        buf.delete_char();
+    // leaving any mode also discards the count
+    for mode in [Mode::Normal, Mode::Insert, Mode::Visual, Mode::VisualLine, Mode::VisualBlock, Mode::OperatorPending] {
+        app.config.keymap(Keybind::new(Key::new(KeyCode::Escape, Modifiers::NoMod), mode), actions::normal);
+    }
 
-        if append {
-            buf.move_append_end_line();
-        }
-    });
+    for mode in [Mode::Visual, Mode::VisualLine, Mode::VisualBlock] {
+        app.config.keymap(Keybind::new(Key::new(KeyCode::X, Modifiers::NoMod), mode), actions::delete);
+    }
 
-    app.config.keymap(keybind!(Insert, Escape, NoMod), |app| {
-        app.ed.buf_mut().unwrap().set_mode(Mode::Normal);
-        app.ed.buf_mut().unwrap().move_left();
-    });
+    app.config.keymap(keybind!(OperatorPending, [I, NoMod], [W, NoMod]),            actions::inner_word);
+    app.config.keymap(keybind!(OperatorPending, [I, NoMod], [P, NoMod]),            actions::inner_paragraph);
+    app.config.keymap(keybind!(OperatorPending, [I, NoMod], [Key9, Shift]),         actions::inner_parens);
+    app.config.keymap(keybind!(OperatorPending, [I, NoMod], [Key0, Shift]),         actions::inner_parens);
+    app.config.keymap(keybind!(OperatorPending, [I, NoMod], [LeftBracket, NoMod]),  actions::inner_brackets);
+    app.config.keymap(keybind!(OperatorPending, [I, NoMod], [RightBracket, NoMod]), actions::inner_brackets);
+    app.config.keymap(keybind!(OperatorPending, [I, NoMod], [LeftBracket, Shift]),  actions::inner_braces);
+    app.config.keymap(keybind!(OperatorPending, [I, NoMod], [RightBracket, Shift]), actions::inner_braces);
+    app.config.keymap(keybind!(OperatorPending, [I, NoMod], [Apostrophe, Shift]),   actions::inner_double_quote);
+    app.config.keymap(keybind!(OperatorPending, [I, NoMod], [Apostrophe, NoMod]),   actions::inner_single_quote);
+    app.config.keymap(keybind!(OperatorPending, [A, NoMod], [W, NoMod]),            actions::around_word);
+    app.config.keymap(keybind!(OperatorPending, [A, NoMod], [P, NoMod]),            actions::around_paragraph);
+    app.config.keymap(keybind!(OperatorPending, [A, NoMod], [Key9, Shift]),         actions::around_parens);
+    app.config.keymap(keybind!(OperatorPending, [A, NoMod], [Key0, Shift]),         actions::around_parens);
+    app.config.keymap(keybind!(OperatorPending, [A, NoMod], [LeftBracket, NoMod]),  actions::around_brackets);
+    app.config.keymap(keybind!(OperatorPending, [A, NoMod], [RightBracket, NoMod]), actions::around_brackets);
+    app.config.keymap(keybind!(OperatorPending, [A, NoMod], [LeftBracket, Shift]),  actions::around_braces);
+    app.config.keymap(keybind!(OperatorPending, [A, NoMod], [RightBracket, Shift]), actions::around_braces);
+    app.config.keymap(keybind!(OperatorPending, [A, NoMod], [Apostrophe, Shift]),   actions::around_double_quote);
+    app.config.keymap(keybind!(OperatorPending, [A, NoMod], [Apostrophe, NoMod]),   actions::around_single_quote);
+
+    app.config.keymap(keybind!(Normal, [W, Ctrl], [L, NoMod]), actions::window_next);
+    app.config.keymap(keybind!(Normal, [W, Ctrl], [H, NoMod]), actions::window_prev);
+    app.config.keymap(Keybind::sequence([ Key::LEADER, Key::new(KeyCode::W, Modifiers::NoMod) ], Mode::Normal), actions::save);
+
+    app.config.keymap(keybind!(Normal, M, NoMod), actions::messages);
+    app.config.keymap(keybind!(Normal, I, NoMod), actions::insert);
+    app.config.keymap(keybind!(Normal, I, Shift), actions::insert_line_start);
+
+    app.config.keymap(keybind!(Normal, Q, NoMod), actions::quit);
+    app.config.keymap(keybind!(Normal, U, NoMod), actions::undo);
+    app.config.keymap(keybind!(Normal, R, Ctrl),  actions::redo);
+
+    app.config.keymap(keybind!(Insert, Backspace, NoMod), actions::backspace);
+    app.config.keymap(keybind!(Insert, U,         Ctrl),  actions::clear_line);
+    app.config.keymap(keybind!(Insert, Enter,     NoMod), actions::newline);
+    app.config.keymap(keybind!(Insert, Tab,       NoMod), actions::tab);
+
+    app.config.keymap(keybind!(Normal,  Semicolon, Shift), actions::command);
+    app.config.keymap(keybind!(Normal,  Slash,     NoMod), actions::search_forward);
+    app.config.keymap(keybind!(Normal,  Slash,     Shift), actions::search_backward);
+    app.config.keymap(keybind!(Normal,  N,         NoMod), actions::search_next);
+    app.config.keymap(keybind!(Normal,  N,         Shift), actions::search_prev);
+    app.config.keymap(keybind!(Command, Escape,    NoMod), actions::cmdline_cancel);
+    app.config.keymap(keybind!(Command, Enter,     NoMod), actions::cmdline_execute);
+    app.config.keymap(keybind!(Command, Backspace, NoMod), actions::cmdline_backspace);
+    app.config.keymap(keybind!(Command, Delete,    NoMod), actions::cmdline_delete);
+    app.config.keymap(keybind!(Command, U,         Ctrl),  actions::cmdline_clear);
+    app.config.keymap(keybind!(Command, Left,      NoMod), actions::cmdline_left);
+    app.config.keymap(keybind!(Command, Right,     NoMod), actions::cmdline_right);
+    app.config.keymap(keybind!(Command, Home,      NoMod), actions::cmdline_start);
+    app.config.keymap(keybind!(Command, End,       NoMod), actions::cmdline_end);
+    app.config.keymap(keybind!(Command, Up,        NoMod), actions::cmdline_history_prev);
+    app.config.keymap(keybind!(Command, Down,      NoMod), actions::cmdline_history_next);
+    app.config.keymap(keybind!(Command, Tab,       NoMod), actions::cmdline_complete);
+
+    app.config.keymap(keybind!(Normal, Period, NoMod), actions::font_bigger);
+    app.config.keymap(keybind!(Normal, Comma,  NoMod), actions::font_smaller);
+
+    app.config.keymap(keybind!(Normal, X, NoMod), actions::delete_char);
+    app.config.keymap(keybind!(Normal, W, Shift), actions::window_new);
+    app.config.keymap(keybind!(Normal, X, Shift), actions::window_close);
+    app.config.keymap(keybind!(Normal, Z, Shift), actions::new_buffer);
+    app.config.keymap(keybind!(Normal, U, Shift), actions::first_buffer);
+    app.config.keymap(keybind!(Normal, A, NoMod), actions::append);
+    app.config.keymap(keybind!(Normal, A, Shift), actions::append_line_end);
+    app.config.keymap(keybind!(Normal, O, Shift), actions::open_above);
+    app.config.keymap(keybind!(Normal, O, NoMod), actions::open_below);
 
-    app.config.keymap(keybind!(Normal, Period, NoMod), |app| {
-        let buf = &mut app.renderer.canvas.win.buf;
-        buf.set_fontsize(buf.fontsize() + 1);
-    });
+}
 
-    app.config.keymap(keybind!(Normal, Comma, NoMod), |app| {
-        let buf = &mut app.renderer.canvas.win.buf;
-        buf.set_fontsize(buf.fontsize() - 1);
-    });
+#[cfg(test)]
+mod tests {
+    use super::*;
 
+    fn key(key_code: KeyCode) -> Key {
+        Key::new(key_code, Modifiers::NoMod)
+    }
 
-    app.config.keymap(keybind!(Normal, X,    NoMod), |app| buf!(app).delete_char());
-    app.config.keymap(keybind!(Normal, Key0, NoMod), |app| buf!(app).move_start_line());
-    app.config.keymap(keybind!(Normal, Key4, Shift), |app| buf!(app).move_end_line());
-    app.config.keymap(keybind!(Normal, W, Shift), |app| { app.ed.windows_mut().add(None); });
-    app.config.keymap(keybind!(Normal, X, Shift), |app| app.ed.windows_mut().delete());
-    // app.config.keymap(keybind!(Normal, N, Shift), |app| app.ed.windows_mut().next(true));
-    // app.config.keymap(keybind!(Normal, P, Shift), |app| app.ed.windows_mut().prev(true));
-    app.config.keymap(keybind!(Normal, Z, Shift), |app| { app.ed.buffers_mut().add(); });
-    app.config.keymap(keybind!(Normal, A, NoMod), |app| {
-        let buf = buf!(app);
-        // not working correctly when on last char
-        buf.append = true;
-        buf.move_right();
-        app.ed.buf_mut().unwrap().set_mode(Mode::Insert);
-    });
-    app.config.keymap(keybind!(Normal, A, Shift), |app| { buf!(app).move_append_end_line(); app.ed.buf_mut().unwrap().set_mode(Mode::Insert); });
-    app.config.keymap(keybind!(Normal, O, Shift), |app| { buf!(app).newline_above(); app.ed.buf_mut().unwrap().set_mode(Mode::Insert); });
-    app.config.keymap(keybind!(Normal, O, NoMod), |app| {
-        buf!(app).newline_below();
-        buf!(app).move_down();
-        app.ed.buf_mut().unwrap().set_mode(Mode::Insert);
-    });
-    app.config.keymap(keybind!(Insert, U, Ctrl), |app| buf!(app).clear_current_line());
-    app.config.keymap(keybind!(Insert, Enter, NoMod), |app| buf!(app).split_newline());
+    #[test]
+    fn test_lookup_sequences() {
+        let mut config = Config::default();
+        config.keymap(keybind!(Normal, G, NoMod), |_, _| {});
+        config.keymap(keybind!(Normal, [G, NoMod], [G, NoMod]), |_, _| {});
+        config.keymap(keybind!(Normal, [W, Ctrl], [L, NoMod]), |_, _| {});
 
-    app.config.keymap(keybind!(Normal, U, Shift), |app| {
-        let id = app.ed.winid().unwrap();
-        let win = app.ed.windows_mut().get_mut(id).unwrap();
-        win.set_buf(0);
-    });
+        // `g` is both bound and the beginning of `gg`
+        let lookup = config.lookup(Mode::Normal, &[ key(KeyCode::G) ]);
+        assert!(lookup.action.is_some() && lookup.is_prefix);
 
-    app.config.keymap(keybind!(Insert, Tab, NoMod), |app| {
-        let buf = buf!(app);
-        buf.insert_string("    ");
-        buf.move_right();
-        buf.move_right();
-        buf.move_right();
-        buf.move_right();
-    });
+        let lookup = config.lookup(Mode::Normal, &[ key(KeyCode::G); 2 ]);
+        assert!(lookup.action.is_some() && !lookup.is_prefix);
+
+        let lookup = config.lookup(Mode::Normal, &[ Key::new(KeyCode::W, Modifiers::Ctrl) ]);
+        assert!(lookup.action.is_none() && lookup.is_prefix);
+
+        let lookup = config.lookup(Mode::Insert, &[ key(KeyCode::G) ]);
+        assert!(lookup.action.is_none() && !lookup.is_prefix);
+    }
+
+    #[test]
+    fn test_lookup_leader() {
+        let mut config = Config::default();
+        config.set_leader(key(KeyCode::Backslash));
+        config.keymap(Keybind::sequence([ Key::LEADER, key(KeyCode::F) ], Mode::Normal), |_, _| {});
+
+        assert_eq!(config.next_keys(Mode::Normal, &[]), vec![ key(KeyCode::Backslash) ]);
+        let lookup = config.lookup(Mode::Normal, &[ key(KeyCode::Backslash), key(KeyCode::F) ]);
+        assert!(lookup.action.is_some());
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*.rs", "main.rs"));
+        assert!(glob_match("*.rs", ".rs"));
+        assert!(!glob_match("*.rs", "main.rs.bak"));
+        assert!(glob_match("ma?n.*", "main.rs"));
+        assert!(glob_match("*a*b", "xaxxab"));
+        assert!(!glob_match("?", ""));
+    }
+
+    #[test]
+    fn test_autocmd_triggered() {
+        let mut config = Config::default();
+        let noop = |_: &mut Application, _: &EventData| {};
+        config.autocmd(Event::BufNew, noop);
+        config.add_autocmd(Autocmd::new(Event::BufNew, noop).pattern("*.rs").group("rust"));
+        config.add_autocmd(Autocmd::new(Event::BufNew, noop).pattern("/tmp/*").once());
+        config.add_autocmd(Autocmd::new(Event::BufDel, noop).group("rust"));
+
+        let ev = EventData::BufNew(0);
+        assert_eq!(config.triggered(&ev, None).len(), 1);
+        assert_eq!(config.triggered(&ev, Some(Path::new("/src/main.rs"))).len(), 2);
+        assert_eq!(config.triggered(&ev, Some(Path::new("/tmp/main.rs"))).len(), 3);
+        // ran once already
+        assert_eq!(config.triggered(&ev, Some(Path::new("/tmp/main.rs"))).len(), 2);
+
+        config.clear_group("rust");
+        assert_eq!(config.autocmds().len(), 1);
+        assert_eq!(config.triggered(&ev, Some(Path::new("/src/main.rs"))).len(), 1);
+    }
 
 }
@@ -0,0 +1,569 @@
+use crate::Application;
+use crate::edit::Mode;
+use crate::edit::cmdline::Prompt;
+use crate::edit::motion::{Motion, Operator, Scope, TextObject};
+use crate::edit::search::{Search, SearchError};
+
+use super::Action;
+use super::commands;
+use super::registry::ActionInfo;
+
+
+
+const NORMAL:   &[Mode] = &[ Mode::Normal ];
+const INSERT:   &[Mode] = &[ Mode::Insert ];
+const PENDING:  &[Mode] = &[ Mode::OperatorPending ];
+const COMMAND:  &[Mode] = &[ Mode::Command ];
+/// normal mode and the visual modes, which the visual toggles switch between
+const SELECT:   &[Mode] = &[ Mode::Normal, Mode::Visual, Mode::VisualLine, Mode::VisualBlock ];
+/// motions also take the range of a pending operator
+const MOTION:   &[Mode] = &[ Mode::Normal, Mode::Visual, Mode::VisualLine, Mode::VisualBlock, Mode::OperatorPending ];
+const ANY:      &[Mode] = &[];
+
+macro_rules! info {
+    ($name:literal, $modes:expr, $action:expr, $desc:literal) => {
+        ActionInfo::new($name, $desc, $modes, $action)
+    };
+}
+
+/// Built-in actions, which are addressable by name through the [`Registry`].
+///
+/// [`Registry`]: super::registry::Registry
+#[must_use]
+pub fn builtin() -> Vec<ActionInfo> {
+    vec![
+        info!("buffer.move_down",            MOTION,  move_down,           "Move down by count lines"),
+        info!("buffer.move_up",              MOTION,  move_up,             "Move up by count lines"),
+        info!("buffer.move_left",            MOTION,  move_left,           "Move left by count characters"),
+        info!("buffer.move_right",           MOTION,  move_right,          "Move right by count characters"),
+        info!("buffer.page_down",            SELECT,  page_down,           "Move down by count lines, 10 by default"),
+        info!("buffer.page_up",              SELECT,  page_up,             "Move up by count lines, 10 by default"),
+        info!("buffer.goto_top",             MOTION,  goto_top,            "Move to the first line, or to line count"),
+        info!("buffer.goto_bottom",          MOTION,  goto_bottom,         "Move to the last line, or to line count"),
+        info!("buffer.line_start",           MOTION,  line_start,          "Move to the start of the line"),
+        info!("buffer.line_end",             MOTION,  line_end,            "Move to the end of the line, count - 1 lines down"),
+        info!("buffer.word_forward",         MOTION,  word_forward,        "Move to the start of the next word"),
+        info!("buffer.word_end",             MOTION,  word_end,            "Move to the end of the word"),
+        info!("buffer.word_backward",        MOTION,  word_backward,       "Move to the start of the previous word"),
+        info!("buffer.paragraph_forward",    MOTION,  paragraph_forward,   "Move to the next blank line"),
+        info!("buffer.paragraph_backward",   MOTION,  paragraph_backward,  "Move to the previous blank line"),
+        info!("buffer.delete_char",          NORMAL,  delete_char,         "Delete count characters under the cursor"),
+        info!("buffer.paste",                NORMAL,  paste,               "Paste a register after the cursor, count times"),
+        info!("buffer.paste_before",         NORMAL,  paste_before,        "Paste a register before the cursor, count times"),
+        info!("buffer.paste_cycle",          NORMAL,  paste_cycle,         "Replace the text just pasted with the previous kill"),
+        info!("buffer.undo",                 NORMAL,  undo,                "Undo count changes"),
+        info!("buffer.redo",                 NORMAL,  redo,                "Redo count changes"),
+        info!("buffer.save",                 ANY,     save,                "Write the buffer to its file"),
+        info!("buffer.normal",               ANY,     normal,              "Return to normal mode"),
+        info!("buffer.insert",               NORMAL,  insert,              "Insert before the cursor"),
+        info!("buffer.insert_line_start",    NORMAL,  insert_line_start,   "Insert at the start of the line"),
+        info!("buffer.append",               NORMAL,  append,              "Insert after the cursor"),
+        info!("buffer.append_line_end",      NORMAL,  append_line_end,     "Insert at the end of the line"),
+        info!("buffer.open_below",           NORMAL,  open_below,          "Insert on a new line below"),
+        info!("buffer.open_above",           NORMAL,  open_above,          "Insert on a new line above"),
+        info!("buffer.visual",               SELECT,  visual,              "Toggle charwise visual mode"),
+        info!("buffer.visual_line",          SELECT,  visual_line,         "Toggle linewise visual mode"),
+        info!("buffer.visual_block",         SELECT,  visual_block,        "Toggle blockwise visual mode"),
+        info!("buffer.delete",               MOTION,  delete,              "Delete the selection, or await a motion"),
+        info!("buffer.change",               MOTION,  change,              "Change the selection, or await a motion"),
+        info!("buffer.yank",                 MOTION,  yank,                "Yank the selection, or await a motion"),
+        info!("buffer.indent",               MOTION,  indent,              "Indent the selection, or await a motion"),
+        info!("buffer.dedent",               MOTION,  dedent,              "Dedent the selection, or await a motion"),
+        info!("insert.backspace",            INSERT,  backspace,           "Delete the character before the cursor"),
+        info!("insert.newline",              INSERT,  newline,             "Split the line at the cursor"),
+        info!("insert.tab",                  INSERT,  tab,                 "Insert one level of indentation"),
+        info!("insert.clear_line",           INSERT,  clear_line,          "Clear the current line"),
+        info!("cmdline.execute",             COMMAND, cmdline_execute,     "Run the command line"),
+        info!("cmdline.cancel",              COMMAND, cmdline_cancel,      "Leave the command line without running it"),
+        info!("cmdline.backspace",           COMMAND, cmdline_backspace,   "Delete the character before the cursor, or leave if empty"),
+        info!("cmdline.delete",              COMMAND, cmdline_delete,      "Delete the character under the cursor"),
+        info!("cmdline.clear",               COMMAND, cmdline_clear,       "Delete everything before the cursor"),
+        info!("cmdline.move_left",           COMMAND, cmdline_left,        "Move left by one character"),
+        info!("cmdline.move_right",          COMMAND, cmdline_right,       "Move right by one character"),
+        info!("cmdline.line_start",          COMMAND, cmdline_start,       "Move to the start of the command line"),
+        info!("cmdline.line_end",            COMMAND, cmdline_end,         "Move to the end of the command line"),
+        info!("cmdline.history_prev",        COMMAND, cmdline_history_prev, "Recall the previous command"),
+        info!("cmdline.history_next",        COMMAND, cmdline_history_next, "Recall the next command"),
+        info!("cmdline.complete",            COMMAND, cmdline_complete,    "Complete the word before the cursor, cycling on repeat"),
+        info!("object.inner_word",           PENDING, inner_word,          "Operate on the word under the cursor"),
+        info!("object.around_word",          PENDING, around_word,         "Operate on the word and trailing whitespace"),
+        info!("object.inner_paragraph",      PENDING, inner_paragraph,     "Operate on the paragraph"),
+        info!("object.around_paragraph",     PENDING, around_paragraph,    "Operate on the paragraph and trailing blank lines"),
+        info!("object.inner_parens",         PENDING, inner_parens,        "Operate inside of ()"),
+        info!("object.around_parens",        PENDING, around_parens,       "Operate on () and its contents"),
+        info!("object.inner_brackets",       PENDING, inner_brackets,      "Operate inside of []"),
+        info!("object.around_brackets",      PENDING, around_brackets,     "Operate on [] and its contents"),
+        info!("object.inner_braces",         PENDING, inner_braces,        "Operate inside of {}"),
+        info!("object.around_braces",        PENDING, around_braces,       "Operate on {} and its contents"),
+        info!("object.inner_double_quote",   PENDING, inner_double_quote,  "Operate inside of \"\""),
+        info!("object.around_double_quote",  PENDING, around_double_quote, "Operate on \"\" and its contents"),
+        info!("object.inner_single_quote",   PENDING, inner_single_quote,  "Operate inside of ''"),
+        info!("object.around_single_quote",  PENDING, around_single_quote, "Operate on '' and its contents"),
+        info!("window.new",                  ANY,     window_new,          "Open a new empty window"),
+        info!("window.close",                ANY,     window_close,        "Close the current window"),
+        info!("window.next",                 ANY,     window_next,         "Focus the next window"),
+        info!("window.prev",                 ANY,     window_prev,         "Focus the previous window"),
+        info!("window.first_buffer",         ANY,     first_buffer,        "Show the first buffer in the current window"),
+        info!("editor.quit",                 ANY,     quit,                "Quit the editor"),
+        info!("editor.command",              NORMAL,  command,             "Open the command line"),
+        info!("editor.search_forward",       NORMAL,  search_forward,      "Search for a regex towards the bottom"),
+        info!("editor.search_backward",      NORMAL,  search_backward,     "Search for a regex towards the top"),
+        info!("editor.search_next",          NORMAL,  search_next,         "Move to the count-th next match of the last search"),
+        info!("editor.search_prev",          NORMAL,  search_prev,         "Move to the count-th match of the last search in the opposite direction"),
+        info!("editor.messages",             ANY,     messages,            "Show all messages in a new window"),
+        info!("editor.new_buffer",           ANY,     new_buffer,          "Create a new empty buffer"),
+        info!("editor.delete_buffer",        ANY,     delete_buffer,       "Delete the current buffer"),
+        info!("editor.font_bigger",          ANY,     font_bigger,         "Increase the font size by count"),
+        info!("editor.font_smaller",         ANY,     font_smaller,        "Decrease the font size by count"),
+    ]
+}
+
+// TODO: refactor into function
+macro_rules! buf {
+    ($app:expr) => {
+        $app.ed.buf_mut().unwrap()
+    }
+}
+
+/// Number of times an action is repeated, when given `count`.
+fn times(count: Option<usize>) -> usize {
+    count.unwrap_or(1)
+}
+
+/// Enters the given visual mode, or leaves it if it is already active.
+fn toggle_visual(app: &mut Application, mode: Mode) {
+    let buf = buf!(app);
+    buf.set_mode(if buf.mode() == mode { Mode::Normal } else { mode });
+}
+
+/// Applies `op` to the selection in visual modes, and awaits a motion otherwise.
+fn operate(app: &mut Application, op: Operator, count: Option<usize>) {
+    let buf = buf!(app);
+
+    if buf.mode().is_visual() {
+        buf.operate_selection(op);
+    } else {
+        buf.begin_operator(op, count);
+    }
+}
+
+/// Applies the pending operator over `motion` in operator-pending mode.
+/// Returns false in every other mode, where the motion moves the cursor instead.
+fn operate_motion(app: &mut Application, motion: Motion, count: Option<usize>) -> bool {
+    let buf = buf!(app);
+    if buf.mode() != Mode::OperatorPending {
+        return false;
+    }
+
+    buf.operate_motion(motion, count);
+    true
+}
+
+pub fn move_down(app: &mut Application, count: Option<usize>) {
+    if operate_motion(app, Motion::Down, count) { return }
+    buf!(app).move_down_many(times(count) as isize);
+}
+
+pub fn move_up(app: &mut Application, count: Option<usize>) {
+    if operate_motion(app, Motion::Up, count) { return }
+    buf!(app).move_up_many(times(count) as isize);
+}
+
+pub fn move_left(app: &mut Application, count: Option<usize>) {
+    if operate_motion(app, Motion::Left, count) { return }
+    (0..times(count)).for_each(|_| buf!(app).move_left());
+}
+
+pub fn move_right(app: &mut Application, count: Option<usize>) {
+    if operate_motion(app, Motion::Right, count) { return }
+    (0..times(count)).for_each(|_| buf!(app).move_right());
+}
+
+pub fn page_down(app: &mut Application, count: Option<usize>) {
+    buf!(app).move_down_many(count.unwrap_or(10) as isize);
+}
+
+pub fn page_up(app: &mut Application, count: Option<usize>) {
+    buf!(app).move_up_many(count.unwrap_or(10) as isize);
+}
+
+pub fn goto_top(app: &mut Application, count: Option<usize>) {
+    if operate_motion(app, Motion::FileStart, count) { return }
+    buf!(app).move_to_line(times(count) - 1);
+}
+
+pub fn goto_bottom(app: &mut Application, count: Option<usize>) {
+    if operate_motion(app, Motion::FileEnd, count) { return }
+    match count {
+        Some(line) => buf!(app).move_to_line(line - 1),
+        None       => buf!(app).move_bottom(),
+    }
+}
+
+pub fn line_start(app: &mut Application, count: Option<usize>) {
+    if operate_motion(app, Motion::LineStart, count) { return }
+    buf!(app).move_start_line();
+}
+
+pub fn line_end(app: &mut Application, count: Option<usize>) {
+    if operate_motion(app, Motion::LineEnd, count) { return }
+    let buf = buf!(app);
+    buf.move_down_many(times(count) as isize - 1);
+    buf.move_end_line();
+}
+
+pub fn word_forward(app: &mut Application, count: Option<usize>) {
+    if operate_motion(app, Motion::WordForward, count) { return }
+    buf!(app).move_motion(Motion::WordForward, count);
+}
+
+pub fn word_end(app: &mut Application, count: Option<usize>) {
+    if operate_motion(app, Motion::WordEnd, count) { return }
+    buf!(app).move_motion(Motion::WordEnd, count);
+}
+
+pub fn word_backward(app: &mut Application, count: Option<usize>) {
+    if operate_motion(app, Motion::WordBackward, count) { return }
+    buf!(app).move_motion(Motion::WordBackward, count);
+}
+
+pub fn paragraph_forward(app: &mut Application, count: Option<usize>) {
+    if operate_motion(app, Motion::ParagraphForward, count) { return }
+    buf!(app).move_motion(Motion::ParagraphForward, count);
+}
+
+pub fn paragraph_backward(app: &mut Application, count: Option<usize>) {
+    if operate_motion(app, Motion::ParagraphBackward, count) { return }
+    buf!(app).move_motion(Motion::ParagraphBackward, count);
+}
+
+pub fn delete_char(app: &mut Application, count: Option<usize>) {
+    (0..times(count)).for_each(|_| buf!(app).delete_char());
+}
+
+pub fn paste(app: &mut Application, count: Option<usize>) {
+    paste_register(app, false, count);
+}
+
+pub fn paste_before(app: &mut Application, count: Option<usize>) {
+    paste_register(app, true, count);
+}
+
+pub fn paste_cycle(app: &mut Application, _: Option<usize>) {
+    if !app.ed.cycle_paste() {
+        app.ed.cmdline_mut().set_message("previous command was not a paste");
+    }
+}
+
+fn paste_register(app: &mut Application, before: bool, count: Option<usize>) {
+    if let Err(err) = app.ed.paste(app.register(), before, times(count)) {
+        app.ed.cmdline_mut().set_message(format!("clipboard: {err}"));
+    }
+}
+
+pub fn undo(app: &mut Application, count: Option<usize>) {
+    (0..times(count)).for_each(|_| buf!(app).undo());
+}
+
+pub fn redo(app: &mut Application, count: Option<usize>) {
+    (0..times(count)).for_each(|_| buf!(app).redo());
+}
+
+pub fn save(app: &mut Application, _: Option<usize>) {
+    if let Some(Err(err)) = app.write_buffer(None) {
+        app.ed.add_message(format!("failed to save: {err}"));
+    }
+}
+
+/// Leaves the current mode. Leaving insert mode moves the cursor back onto the last inserted character.
+pub fn normal(app: &mut Application, _: Option<usize>) {
+    let buf = buf!(app);
+    let mode = buf.mode();
+
+    buf.set_mode(Mode::Normal);
+    if mode == Mode::Insert {
+        buf.move_left();
+    }
+}
+
+pub fn insert(app: &mut Application, _: Option<usize>) {
+    buf!(app).set_mode(Mode::Insert);
+}
+
+pub fn insert_line_start(app: &mut Application, _: Option<usize>) {
+    let buf = buf!(app);
+    buf.move_start_line();
+    buf.set_mode(Mode::Insert);
+}
+
+pub fn append(app: &mut Application, _: Option<usize>) {
+    let buf = buf!(app);
+    // not working correctly when on last char
+    buf.append = true;
+    buf.move_right();
+    buf.set_mode(Mode::Insert);
+}
+
+pub fn append_line_end(app: &mut Application, _: Option<usize>) {
+    let buf = buf!(app);
+    buf.move_append_end_line();
+    buf.set_mode(Mode::Insert);
+}
+
+pub fn open_below(app: &mut Application, _: Option<usize>) {
+    let buf = buf!(app);
+    buf.newline_below();
+    buf.move_down();
+    buf.set_mode(Mode::Insert);
+}
+
+pub fn open_above(app: &mut Application, _: Option<usize>) {
+    let buf = buf!(app);
+    buf.newline_above();
+    buf.set_mode(Mode::Insert);
+}
+
+pub fn visual(app: &mut Application, _: Option<usize>) {
+    toggle_visual(app, Mode::Visual);
+}
+
+pub fn visual_line(app: &mut Application, _: Option<usize>) {
+    toggle_visual(app, Mode::VisualLine);
+}
+
+pub fn visual_block(app: &mut Application, _: Option<usize>) {
+    toggle_visual(app, Mode::VisualBlock);
+}
+
+pub fn delete(app: &mut Application, count: Option<usize>) {
+    operate(app, Operator::Delete, count);
+}
+
+pub fn change(app: &mut Application, count: Option<usize>) {
+    operate(app, Operator::Change, count);
+}
+
+pub fn yank(app: &mut Application, count: Option<usize>) {
+    operate(app, Operator::Yank, count);
+}
+
+pub fn indent(app: &mut Application, count: Option<usize>) {
+    operate(app, Operator::Indent, count);
+}
+
+pub fn dedent(app: &mut Application, count: Option<usize>) {
+    operate(app, Operator::Dedent, count);
+}
+
+pub fn backspace(app: &mut Application, _: Option<usize>) {
+    let buf = buf!(app);
+    let append = buf.append;
+
+    buf.move_left();
+    buf.delete_char();
+
+    if append {
+        buf.move_append_end_line();
+    }
+}
+
+pub fn newline(app: &mut Application, _: Option<usize>) {
+    buf!(app).split_newline();
+}
+
+pub fn tab(app: &mut Application, _: Option<usize>) {
+    let buf = buf!(app);
+    buf.insert_string("    ");
+    buf.move_right();
+    buf.move_right();
+    buf.move_right();
+    buf.move_right();
+}
+
+pub fn clear_line(app: &mut Application, _: Option<usize>) {
+    buf!(app).clear_current_line();
+}
+
+macro_rules! text_objects {
+    ($($inner:ident, $around:ident => $object:expr;)*) => {$(
+        pub fn $inner(app: &mut Application, _: Option<usize>) {
+            buf!(app).operate_object($object, Scope::Inner);
+        }
+
+        pub fn $around(app: &mut Application, _: Option<usize>) {
+            buf!(app).operate_object($object, Scope::Around);
+        }
+    )*};
+}
+
+text_objects! {
+    inner_word,         around_word         => TextObject::Word;
+    inner_paragraph,    around_paragraph    => TextObject::Paragraph;
+    inner_parens,       around_parens       => TextObject::Pair('(', ')');
+    inner_brackets,     around_brackets     => TextObject::Pair('[', ']');
+    inner_braces,       around_braces       => TextObject::Pair('{', '}');
+    inner_double_quote, around_double_quote => TextObject::Quote('"');
+    inner_single_quote, around_single_quote => TextObject::Quote('\'');
+}
+
+pub fn command(app: &mut Application, _: Option<usize>) {
+    app.ed.cmdline_mut().open(Prompt::Command);
+    buf!(app).set_mode(Mode::Command);
+}
+
+pub fn search_forward(app: &mut Application, _: Option<usize>) {
+    app.ed.start_search(false);
+}
+
+pub fn search_backward(app: &mut Application, _: Option<usize>) {
+    app.ed.start_search(true);
+}
+
+pub fn search_next(app: &mut Application, count: Option<usize>) {
+    for _ in 0..times(count) {
+        let result = app.ed.search_next(false);
+        if !report_search(app, result, false) { break }
+    }
+}
+
+pub fn search_prev(app: &mut Application, count: Option<usize>) {
+    for _ in 0..times(count) {
+        let result = app.ed.search_next(true);
+        if !report_search(app, result, true) { break }
+    }
+}
+
+/// Tells whether a search wrapped around or failed. Returns false if it failed.
+fn report_search(app: &mut Application, result: Result<bool, SearchError>, reverse: bool) -> bool {
+    let backward = app.ed.search().is_some_and(Search::is_backward) != reverse;
+    match result {
+        Ok(true) if backward => app.ed.cmdline_mut().set_message("search hit TOP, continuing at BOTTOM"),
+        Ok(true)             => app.ed.cmdline_mut().set_message("search hit BOTTOM, continuing at TOP"),
+        Ok(false)            => {}
+        Err(err) => {
+            app.ed.cmdline_mut().set_message(err.to_string());
+            return false;
+        }
+    }
+    true
+}
+
+/// Leaves command mode before running the line, so that
+/// commands may change the mode or the buffer themselves.
+pub fn cmdline_execute(app: &mut Application, _: Option<usize>) {
+    let prompt = app.ed.cmdline().prompt();
+    let line = app.ed.cmdline_mut().submit();
+    buf!(app).set_mode(Mode::Normal);
+
+    if prompt.is_search() {
+        let result = app.ed.submit_search(&line, prompt == Prompt::SearchBackward);
+        report_search(app, result, false);
+        return;
+    }
+
+    if let Err(err) = commands::execute(app, &line) {
+        app.ed.cmdline_mut().set_message(err.to_string());
+        app.ed.add_message(format!(":{line}: {err}"));
+    }
+}
+
+pub fn cmdline_cancel(app: &mut Application, _: Option<usize>) {
+    app.ed.cancel_search();
+    app.ed.cmdline_mut().clear();
+    buf!(app).set_mode(Mode::Normal);
+}
+
+pub fn cmdline_backspace(app: &mut Application, count: Option<usize>) {
+    if !app.ed.cmdline_mut().backspace() {
+        cmdline_cancel(app, count);
+    }
+}
+
+pub fn cmdline_delete(app: &mut Application, _: Option<usize>) {
+    app.ed.cmdline_mut().delete();
+}
+
+pub fn cmdline_clear(app: &mut Application, _: Option<usize>) {
+    app.ed.cmdline_mut().clear_before();
+}
+
+pub fn cmdline_left(app: &mut Application, _: Option<usize>) {
+    app.ed.cmdline_mut().move_left();
+}
+
+pub fn cmdline_right(app: &mut Application, _: Option<usize>) {
+    app.ed.cmdline_mut().move_right();
+}
+
+pub fn cmdline_start(app: &mut Application, _: Option<usize>) {
+    app.ed.cmdline_mut().move_start();
+}
+
+pub fn cmdline_end(app: &mut Application, _: Option<usize>) {
+    app.ed.cmdline_mut().move_end();
+}
+
+pub fn cmdline_history_prev(app: &mut Application, _: Option<usize>) {
+    app.ed.cmdline_mut().history_prev();
+}
+
+pub fn cmdline_history_next(app: &mut Application, _: Option<usize>) {
+    app.ed.cmdline_mut().history_next();
+}
+
+pub fn cmdline_complete(app: &mut Application, _: Option<usize>) {
+    if app.ed.cmdline().prompt().is_search() { return }
+    let registry = app.config.registry();
+    app.ed.cmdline_mut().complete(|line| commands::complete(registry, line));
+}
+
+pub fn window_new(app: &mut Application, _: Option<usize>) {
+    app.ed.windows_mut().add(None);
+}
+
+pub fn window_close(app: &mut Application, _: Option<usize>) {
+    app.ed.windows_mut().delete();
+}
+
+pub fn window_next(app: &mut Application, count: Option<usize>) {
+    (0..times(count)).for_each(|_| app.ed.windows_mut().next(true));
+}
+
+pub fn window_prev(app: &mut Application, count: Option<usize>) {
+    (0..times(count)).for_each(|_| app.ed.windows_mut().prev(true));
+}
+
+pub fn first_buffer(app: &mut Application, _: Option<usize>) {
+    let Some(first) = app.ed.buffers().ids().next() else { return };
+    let id = app.ed.winid().unwrap();
+    let win = app.ed.windows_mut().get_mut(id).unwrap();
+    win.set_buf(first);
+}
+
+pub fn quit(app: &mut Application, _: Option<usize>) {
+    app.quit();
+}
+
+pub fn messages(app: &mut Application, _: Option<usize>) {
+    app.ed.show_messages();
+}
+
+pub fn new_buffer(app: &mut Application, _: Option<usize>) {
+    app.ed.buffers_mut().add();
+}
+
+pub fn delete_buffer(app: &mut Application, _: Option<usize>) {
+    if let Some(id) = app.ed.bufid() {
+        app.ed.delete_buffer(id);
+    }
+}
+
+pub fn font_bigger(app: &mut Application, count: Option<usize>) {
+    let buf = &mut app.view.canvas.win.buf;
+    buf.set_fontsize(buf.fontsize().saturating_add(times(count) as u16));
+}
+
+pub fn font_smaller(app: &mut Application, count: Option<usize>) {
+    let buf = &mut app.view.canvas.win.buf;
+    buf.set_fontsize(buf.fontsize().saturating_sub(times(count) as u16).max(1));
+}
@@ -0,0 +1,576 @@
+use std::path::{self, Path};
+use std::{fs, io};
+
+use crate::Application;
+use crate::edit::Mode;
+use crate::edit::buffer::{Buffer, BufferID, Cursor};
+use crate::edit::cmdline::Prompt;
+use crate::edit::search::Search;
+use crate::edit::substitute::{Substitute, SubstituteError, Substitution};
+
+use super::file::parse_linenumbers;
+use super::keys::{Key, KeyParseError};
+use super::registry::Registry;
+
+
+
+pub type Command = fn(&mut Application, &str) -> Result<(), CommandError>;
+
+/// Zero-based first and last line (inclusive) that a command applies to
+pub type LineRange = (usize, usize);
+
+/// What the arguments of a command are completed with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Complete {
+    Nothing,
+    Path,
+    Option,
+}
+
+/// An ex-style command, as typed into the command line.
+#[derive(Debug, Clone, Copy)]
+pub struct CommandInfo {
+    /// the first name is the full one, the others are abbreviations
+    pub names: &'static [&'static str],
+    pub description: &'static str,
+    pub complete: Complete,
+    pub run: Command,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CommandError {
+    #[error("not an editor command: {0}")]
+    Unknown(String),
+    #[error("{0} cannot be used in {1} mode")]
+    UnsupportedMode(String, Mode),
+    #[error("argument required")]
+    MissingArgument,
+    #[error("trailing characters: {0}")]
+    TrailingCharacters(String),
+    #[error("no file name")]
+    NoFileName,
+    #[error("unknown option: {0}")]
+    UnknownOption(String),
+    #[error("invalid value for {option}: {value}")]
+    InvalidValue { option: String, value: String },
+    #[error("no such buffer: {0}")]
+    NoSuchBuffer(String),
+    #[error("invalid range")]
+    InvalidRange,
+    #[error("no range allowed")]
+    NoRangeAllowed,
+    #[error("pattern not found: {0}")]
+    PatternNotFound(String),
+    #[error(transparent)]
+    Substitute(#[from] SubstituteError),
+    #[error("invalid leader `{0}`: {1}")]
+    Leader(String, KeyParseError),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+pub const COMMANDS: &[CommandInfo] = &[
+    CommandInfo { names: &["write", "w"],   description: "Write the buffer, to the given file if any", complete: Complete::Path,    run: write },
+    CommandInfo { names: &["quit", "q"],    description: "Quit the editor",                            complete: Complete::Nothing, run: quit },
+    CommandInfo { names: &["wq", "x"],      description: "Write the buffer and quit",                  complete: Complete::Path,    run: write_quit },
+    CommandInfo { names: &["edit", "e"],    description: "Open a file in the current window",          complete: Complete::Path,    run: edit },
+    CommandInfo { names: &["set"],          description: "Set options, or show them without a value",  complete: Complete::Option,  run: set },
+    CommandInfo { names: &["bnext", "bn"],  description: "Show the next buffer in the current window", complete: Complete::Nothing, run: bnext },
+    CommandInfo { names: &["bprev", "bp"],  description: "Show the previous buffer in the current window", complete: Complete::Nothing, run: bprev },
+    CommandInfo { names: &["bdelete", "bd"], description: "Delete the current buffer, or the one with the given id", complete: Complete::Nothing, run: bdelete },
+    CommandInfo { names: &["messages"],     description: "Show all messages in a new window",          complete: Complete::Nothing, run: messages },
+    CommandInfo { names: &["nohlsearch", "noh"], description: "Stop highlighting the matches of the last search", complete: Complete::Nothing, run: nohlsearch },
+    CommandInfo { names: &["substitute", "s"], description: "Replace a pattern in the lines of the range, eg. `%s/foo/bar/g`", complete: Complete::Nothing, run: substitute_line },
+];
+
+/// Options that can be changed with `:set`
+pub const OPTIONS: &[&str] = &[
+    "fontsize",
+    "leader",
+    "linenumbers",
+    "padding",
+    "repeat_delay",
+    "repeat_rate",
+    "statusline_fontsize",
+    "timeout",
+];
+
+#[must_use]
+pub fn command(name: &str) -> Option<&'static CommandInfo> {
+    COMMANDS.iter().find(|cmd| cmd.names.contains(&name))
+}
+
+/// Runs a line typed into the command line. A number jumps to that line,
+/// and the names of registered actions can be used as commands as well.
+/// Only `:s` takes a range, without which it applies to the current line.
+pub fn execute(app: &mut Application, line: &str) -> Result<(), CommandError> {
+
+    let line = line.trim();
+    if line.is_empty() {
+        return Ok(());
+    }
+
+    if let Ok(number) = line.parse::<usize>() {
+        if let Some(buf) = app.ed.buf_mut() {
+            buf.move_to_line(number.saturating_sub(1));
+        }
+        return Ok(());
+    }
+
+    let (range, line) = parse_range(app, line)?;
+    if let Some(args) = substitute_args(line) {
+        return substitute(app, range, args);
+    }
+    if let Some((_, last)) = range {
+        if !line.is_empty() {
+            return Err(CommandError::NoRangeAllowed);
+        }
+        if let Some(buf) = app.ed.buf_mut() {
+            buf.move_to_line(last);
+        }
+        return Ok(());
+    }
+
+    let (name, args) = line
+        .split_once(char::is_whitespace)
+        .map_or((line, ""), |(name, args)| (name, args.trim()));
+
+    if let Some(cmd) = command(name) {
+        return (cmd.run)(app, args);
+    }
+
+    let info = app.config.registry().get(name).ok_or_else(|| CommandError::Unknown(name.to_string()))?;
+    let mode = app.ed.buf().map_or(Mode::Normal, |buf| buf.mode());
+    if !info.supports(mode) {
+        return Err(CommandError::UnsupportedMode(name.to_string(), mode));
+    }
+
+    let action = info.action.clone();
+    no_args(args)?;
+    action(app, None);
+    Ok(())
+
+}
+
+/// Returns the completions of the last word of `line`.
+#[must_use]
+pub fn complete(registry: &Registry, line: &str) -> Vec<String> {
+
+    let Some((name, args)) = line.split_once(char::is_whitespace) else {
+        let mut names = COMMANDS
+            .iter()
+            .flat_map(|cmd| cmd.names.iter().copied())
+            .chain(registry.iter().map(|info| info.name))
+            .filter(|candidate| candidate.starts_with(line))
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+        return names;
+    };
+
+    let word = args.rsplit(char::is_whitespace).next().unwrap_or_default();
+
+    match command(name).map_or(Complete::Nothing, |cmd| cmd.complete) {
+        Complete::Nothing => Vec::new(),
+        Complete::Path    => complete_path(word),
+        Complete::Option  => OPTIONS
+            .iter()
+            .filter(|option| option.starts_with(word))
+            .map(|option| option.to_string())
+            .collect(),
+    }
+
+}
+
+/// Lists the entries of the directory `word` points into, which start with its file name.
+/// Hidden files are only listed if the file name starts with a dot.
+fn complete_path(word: &str) -> Vec<String> {
+
+    let (dir, prefix) = match word.rfind('/') {
+        Some(i) => (&word[..=i], &word[i + 1..]),
+        None    => ("", word),
+    };
+
+    let Ok(entries) = fs::read_dir(if dir.is_empty() { "." } else { dir }) else {
+        return Vec::new();
+    };
+
+    let mut paths = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            let slash = if entry.file_type().ok()?.is_dir() { "/" } else { "" };
+            Some(format!("{dir}{name}{slash}"))
+        })
+        .collect::<Vec<_>>();
+
+    paths.sort();
+    paths
+
+}
+
+fn no_args(args: &str) -> Result<(), CommandError> {
+    if args.is_empty() {
+        Ok(())
+    } else {
+        Err(CommandError::TrailingCharacters(args.to_string()))
+    }
+}
+
+/// Splits `:set` arguments into options and their values, eg. `fontsize=24 linenumbers`.
+fn parse_set(args: &str) -> impl Iterator<Item = (&str, Option<&str>)> {
+    args.split_whitespace().map(|arg| match arg.split_once('=') {
+        Some((option, value)) => (option, Some(value)),
+        None                  => (arg, None),
+    })
+}
+
+fn write(app: &mut Application, args: &str) -> Result<(), CommandError> {
+    let path = (!args.is_empty()).then(|| Path::new(args));
+    app.write_buffer(path).ok_or(CommandError::NoFileName)??;
+
+    let Some(buf) = app.ed.buf_mut() else { return Ok(()) };
+    // like vim, writing an unnamed buffer names it
+    if !args.is_empty() && buf.filename().is_none() {
+        buf.set_filename(args)?;
+    }
+    Ok(())
+}
+
+/// Splits off the range in front of a command, eg. `%`, `.`, `$`, `3` or `2,$`.
+fn parse_range<'a>(app: &Application, line: &'a str) -> Result<(Option<LineRange>, &'a str), CommandError> {
+    let Some(buf) = app.ed.buf() else { return Ok((None, line)) };
+
+    if let Some(rest) = line.strip_prefix('%') {
+        return Ok((Some((0, buf.getlines().len() - 1)), rest.trim_start()));
+    }
+
+    let Some((first, rest)) = parse_address(buf, line)? else { return Ok((None, line)) };
+    let (last, rest) = match rest.strip_prefix(',') {
+        Some(rest) => parse_address(buf, rest)?.ok_or(CommandError::InvalidRange)?,
+        None       => (first, rest),
+    };
+
+    // like vim, backwards ranges are swapped
+    Ok((Some((first.min(last), first.max(last))), rest.trim_start()))
+}
+
+/// Parses a single line of a range. Returns [`None`] if `s` does not start with one.
+fn parse_address<'a>(buf: &Buffer, s: &'a str) -> Result<Option<(usize, &'a str)>, CommandError> {
+    let count = buf.getlines().len();
+
+    if let Some(rest) = s.strip_prefix('.') {
+        return Ok(Some((buf.cursor().y as usize, rest)));
+    }
+    if let Some(rest) = s.strip_prefix('$') {
+        return Ok(Some((count - 1, rest)));
+    }
+
+    let digits = s.len() - s.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if digits == 0 {
+        return Ok(None);
+    }
+    match s[..digits].parse::<usize>() {
+        Ok(number) if (1..=count).contains(&number) => Ok(Some((number - 1, &s[digits..]))),
+        _ => Err(CommandError::InvalidRange),
+    }
+}
+
+/// Returns the arguments of `:s`, which need no space after its name, eg. `s/foo/bar/`.
+fn substitute_args(line: &str) -> Option<&str> {
+    let args = line.strip_prefix("substitute").or_else(|| line.strip_prefix('s'))?;
+    args.starts_with(|c: char| !c.is_alphanumeric() && !c.is_whitespace()).then_some(args)
+}
+
+fn nohlsearch(app: &mut Application, args: &str) -> Result<(), CommandError> {
+    no_args(args)?;
+    app.ed.clear_highlights();
+    Ok(())
+}
+
+fn substitute_line(app: &mut Application, args: &str) -> Result<(), CommandError> {
+    substitute(app, None, args)
+}
+
+fn substitute(app: &mut Application, range: Option<LineRange>, args: &str) -> Result<(), CommandError> {
+    let sub = Substitute::parse(args, app.ed.search())?;
+    let Some(buf) = app.ed.buf() else { return Ok(()) };
+    let (first, last) = range.unwrap_or((buf.cursor().y as usize, buf.cursor().y as usize));
+
+    // like vim, `n` searches for the pattern afterwards
+    if let Ok(search) = Search::new(sub.pattern(), false) {
+        app.ed.set_search(search);
+    }
+
+    let pattern = sub.pattern().to_string();
+    let (count_only, confirm) = (sub.is_count_only(), sub.is_confirm());
+    let mut substitution = Substitution::new(sub, first, last);
+
+    if count_only {
+        let buf = app.ed.buf().unwrap();
+        let matches = substitution.matches(buf);
+        if matches.is_empty() {
+            return Err(CommandError::PatternNotFound(pattern));
+        }
+        let mut lines = matches.iter().map(|m| m.start.y).collect::<Vec<_>>();
+        lines.dedup();
+        let msg = format!("{} on {}", plural(matches.len(), "match", "matches"), plural(lines.len(), "line", "lines"));
+        app.ed.cmdline_mut().set_message(msg);
+        return Ok(());
+    }
+
+    if confirm {
+        if !app.ed.start_confirm(substitution) {
+            return Err(CommandError::PatternNotFound(pattern));
+        }
+        prompt_confirm(app);
+        return Ok(());
+    }
+
+    substitution.run(app.ed.buf_mut().unwrap());
+    report_substitution(app, &substitution)
+}
+
+/// Answers the substitution being confirmed, see [`crate::edit::Editor::confirm`].
+pub fn confirm(app: &mut Application, answer: char) {
+    match app.ed.confirm(answer) {
+        Some(sub) => {
+            if let Err(err) = report_substitution(app, &sub) {
+                app.ed.cmdline_mut().set_message(err.to_string());
+            }
+        }
+        None => prompt_confirm(app),
+    }
+}
+
+fn prompt_confirm(app: &mut Application) {
+    let Some(found) = app.ed.confirming().and_then(|sub| sub.current(app.ed.buf()?)) else { return };
+    let msg = format!("replace with {} (y/n/a/q/l)?", found.replacement.escape_debug());
+    app.ed.cmdline_mut().set_message(msg);
+}
+
+/// Moves to the line of the last replacement, telling how many have been made.
+fn report_substitution(app: &mut Application, sub: &Substitution) -> Result<(), CommandError> {
+    let Some(line) = sub.last_line() else {
+        return Err(CommandError::PatternNotFound(sub.substitute().pattern().to_string()));
+    };
+
+    if let Some(buf) = app.ed.buf_mut() {
+        buf.jump_to(Cursor::new(0, line as isize));
+    }
+    let msg = format!(
+        "{} on {}",
+        plural(sub.replaced(), "substitution", "substitutions"),
+        plural(sub.lines(), "line", "lines"),
+    );
+    app.ed.cmdline_mut().set_message(msg);
+    Ok(())
+}
+
+fn plural(count: usize, one: &str, many: &str) -> String {
+    format!("{count} {}", if count == 1 { one } else { many })
+}
+
+/// Highlights what the substitution being typed into the command line would replace.
+pub fn preview(app: &mut Application) {
+    let typing = app.ed.buf().is_some_and(|buf| buf.mode() == Mode::Command)
+        && app.ed.cmdline().prompt() == Prompt::Command;
+
+    let command = app.ed.cmdline().text();
+    if typing && app.ed.preview_command() == Some(command) {
+        return;
+    }
+
+    // only the lines on screen are searched, when drawing the highlights
+    match typing.then(|| preview_substitution(app, command)).flatten() {
+        Some(sub) => app.ed.set_preview(command.to_string(), sub),
+        None      => app.ed.clear_preview(),
+    }
+}
+
+fn preview_substitution(app: &Application, line: &str) -> Option<Substitution> {
+    let (range, line) = parse_range(app, line.trim()).ok()?;
+    let sub = Substitute::parse(substitute_args(line)?, app.ed.search()).ok()?;
+    let buf = app.ed.buf()?;
+    let (first, last) = range.unwrap_or((buf.cursor().y as usize, buf.cursor().y as usize));
+    Some(Substitution::new(sub, first, last))
+}
+
+fn quit(app: &mut Application, args: &str) -> Result<(), CommandError> {
+    no_args(args)?;
+    app.quit();
+    Ok(())
+}
+
+fn write_quit(app: &mut Application, args: &str) -> Result<(), CommandError> {
+    write(app, args)?;
+    app.quit();
+    Ok(())
+}
+
+/// Shows the buffer of the given file, loading it if no buffer contains it yet.
+fn edit(app: &mut Application, args: &str) -> Result<(), CommandError> {
+    if args.is_empty() {
+        return Err(CommandError::MissingArgument);
+    }
+
+    let path = path::absolute(args)?;
+    let buffers = app.ed.buffers();
+    let existing = buffers
+        .ids()
+        .find(|&id| buffers.get(id).and_then(Buffer::filename) == Some(path.as_path()));
+
+    let id = match existing {
+        Some(id) => id,
+        None     => app.ed.buffers_mut().open(&path)?,
+    };
+
+    show_buffer(app, id);
+    Ok(())
+}
+
+fn set(app: &mut Application, args: &str) -> Result<(), CommandError> {
+    if args.is_empty() {
+        return Err(CommandError::MissingArgument);
+    }
+
+    let mut shown = Vec::new();
+    for (option, value) in parse_set(args) {
+        match value {
+            Some(value) => set_option(app, option, value)?,
+            None        => shown.push(format!("{option}={}", get_option(app, option)?)),
+        }
+    }
+
+    if !shown.is_empty() {
+        app.ed.cmdline_mut().set_message(shown.join(" "));
+    }
+    Ok(())
+}
+
+fn set_option(app: &mut Application, option: &str, value: &str) -> Result<(), CommandError> {
+
+    let invalid = || CommandError::InvalidValue { option: option.to_string(), value: value.to_string() };
+
+    match option {
+        "fontsize"            => app.view.canvas.win.buf.set_fontsize(value.parse().map_err(|_| invalid())?),
+        "statusline_fontsize" => app.view.statusline.set_fontsize(value.parse().map_err(|_| invalid())?),
+        "padding"             => app.view.set_padding(value.parse().map_err(|_| invalid())?),
+        "timeout"             => app.config.set_timeout(value.parse::<u64>().map_err(|_| invalid())? as f64 / 1000.),
+        "repeat_delay"        => app.config.set_repeat_delay(value.parse::<u64>().map_err(|_| invalid())? as f64 / 1000.),
+        "repeat_rate"         => app.config.set_repeat_rate(value.parse::<u32>().map_err(|_| invalid())? as f64),
+        "linenumbers"         => app.view.canvas.win.buf.set_mode(parse_linenumbers(value).ok_or_else(invalid)?),
+        "leader"              => app.config.set_leader(
+            value.parse::<Key>().map_err(|err| CommandError::Leader(value.to_string(), err))?
+        ),
+        _ => return Err(CommandError::UnknownOption(option.to_string())),
+    }
+
+    Ok(())
+
+}
+
+fn get_option(app: &Application, option: &str) -> Result<String, CommandError> {
+    Ok(match option {
+        "fontsize"            => app.view.canvas.win.buf.fontsize().to_string(),
+        "statusline_fontsize" => app.view.statusline.fontsize().to_string(),
+        "padding"             => app.view.padding().to_string(),
+        "timeout"             => ((app.config.timeout() * 1000.) as u64).to_string(),
+        "repeat_delay"        => ((app.config.repeat_delay() * 1000.) as u64).to_string(),
+        "repeat_rate"         => (app.config.repeat_rate() as u32).to_string(),
+        "linenumbers"         => app.view.canvas.win.buf.mode().to_string(),
+        "leader"              => app.config.leader().to_string(),
+        _ => return Err(CommandError::UnknownOption(option.to_string())),
+    })
+}
+
+/// Cycles the buffer of the current window through all buffers.
+fn cycle_buffer(app: &mut Application, args: &str, forward: bool) -> Result<(), CommandError> {
+    no_args(args)?;
+
+    let ids = app.ed.buffers().ids().collect::<Vec<_>>();
+    let Some(current) = app.ed.bufid() else { return Ok(()) };
+    let Some(pos) = ids.iter().position(|&id| id == current) else { return Ok(()) };
+
+    let next = if forward { (pos + 1) % ids.len() } else { (pos + ids.len() - 1) % ids.len() };
+    show_buffer(app, ids[next]);
+    Ok(())
+}
+
+fn bnext(app: &mut Application, args: &str) -> Result<(), CommandError> {
+    cycle_buffer(app, args, true)
+}
+
+fn bprev(app: &mut Application, args: &str) -> Result<(), CommandError> {
+    cycle_buffer(app, args, false)
+}
+
+fn bdelete(app: &mut Application, args: &str) -> Result<(), CommandError> {
+    let id = match args.trim() {
+        ""   => app.ed.bufid(),
+        args => Some(args.parse().map_err(|_| CommandError::NoSuchBuffer(args.to_string()))?),
+    };
+
+    if let Some(id) = id && !app.ed.delete_buffer(id) {
+        return Err(CommandError::NoSuchBuffer(id.to_string()));
+    }
+    Ok(())
+}
+
+fn messages(app: &mut Application, args: &str) -> Result<(), CommandError> {
+    no_args(args)?;
+    app.ed.show_messages();
+    Ok(())
+}
+
+/// Shows the buffer in the current window, opening a window if there is none.
+fn show_buffer(app: &mut Application, id: BufferID) {
+    let windows = app.ed.windows_mut();
+    match windows.winid() {
+        Some(win) => windows.get_mut(win).unwrap().set_buf(id),
+        None      => { windows.add(Some(id)); }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_names() {
+        assert!(command("w").is_some_and(|cmd| cmd.names[0] == "write"));
+        assert!(command("bnext").is_some());
+        assert!(command("frobnicate").is_none());
+
+        // names must be unique across commands
+        let mut names = COMMANDS.iter().flat_map(|cmd| cmd.names).collect::<Vec<_>>();
+        let count = names.len();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), count);
+    }
+
+    #[test]
+    fn test_complete() {
+        let registry = Registry::default();
+
+        assert_eq!(complete(&registry, "bn"), ["bn", "bnext"]);
+        assert!(complete(&registry, "editor.q").contains(&"editor.quit".to_string()));
+        assert_eq!(complete(&registry, "set line"), ["linenumbers"]);
+        assert_eq!(complete(&registry, "set fontsize=3 status"), ["statusline_fontsize"]);
+        assert!(complete(&registry, "q ").is_empty());
+        assert!(complete(&registry, "e src/ma").contains(&"src/main.rs".to_string()));
+        assert!(complete(&registry, "e sr").contains(&"src/".to_string()));
+    }
+
+    #[test]
+    fn test_parse_set() {
+        let args = parse_set("fontsize=24  linenumbers").collect::<Vec<_>>();
+        assert_eq!(args, [ ("fontsize", Some("24")), ("linenumbers", None) ]);
+    }
+
+}
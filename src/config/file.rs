@@ -0,0 +1,331 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::{env, fs, io};
+
+use macroquad::prelude::Color;
+use serde::Deserialize;
+
+use crate::Application;
+use crate::edit::Mode;
+use crate::edit::clipboard;
+use crate::render::{LineNumberMode, Theme};
+
+use super::{Config, KeymapError};
+use super::keys::{Key, KeyParseError, Keybind};
+
+
+
+/// User configuration, which is merged over the built-in defaults.
+///
+/// ```toml
+/// [options]
+/// fontsize = 24
+/// linenumbers = "absolute"
+/// leader = "<Space>"
+/// clipboard = "osc52"
+///
+/// [colors]
+/// background = "#282b2e"
+///
+/// [keymap.normal]
+/// "<leader>q" = "editor.quit"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigFile {
+    pub options: Options,
+    pub colors: Colors,
+    /// mode -> keys -> action name
+    pub keymap: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Options {
+    pub padding: Option<f32>,
+    pub fontsize: Option<u16>,
+    pub statusline_fontsize: Option<u16>,
+    pub linenumbers: Option<String>,
+    pub leader: Option<String>,
+    /// milliseconds to wait for the next key of an ambiguous sequence
+    pub timeout: Option<u64>,
+    /// milliseconds a key is held down before it repeats
+    pub repeat_delay: Option<u64>,
+    /// repeats per second of a key held down
+    pub repeat_rate: Option<u32>,
+    /// system clipboard to use instead of the detected one
+    pub clipboard: Option<String>,
+}
+
+/// Colors given as `#rrggbb` or `#rrggbbaa`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Colors {
+    pub background: Option<String>,
+    pub text: Option<String>,
+    pub cursor: Option<String>,
+    pub cursorline: Option<String>,
+    pub selection: Option<String>,
+    pub search: Option<String>,
+    pub search_current: Option<String>,
+    pub statusline: Option<String>,
+    pub widget: Option<String>,
+    pub widget_active: Option<String>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("failed to read config: {0}")]
+    Io(#[from] io::Error),
+    #[error("invalid config: {0}")]
+    Parse(#[from] toml::de::Error),
+    #[error("unknown mode `{0}`")]
+    UnknownMode(String),
+    #[error("invalid keys `{keys}`: {source}")]
+    Keys { keys: String, source: KeyParseError },
+    #[error(transparent)]
+    Keymap(#[from] KeymapError),
+    #[error("invalid color `{value}` for `{name}`")]
+    Color { name: &'static str, value: String },
+    #[error("invalid line number mode `{0}`, expected `relative` or `absolute`")]
+    LineNumbers(String),
+    #[error("invalid leader `{0}`: {1}")]
+    Leader(String, KeyParseError),
+    #[error("unknown clipboard `{0}`, expected `xclip`, `wl-copy`, `osc52` or `memory`")]
+    Clipboard(String),
+}
+
+/// `$XDG_CONFIG_HOME/ace/config.toml`, falling back to `~/.config/ace/config.toml`.
+#[must_use]
+pub fn default_path() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+    Some(base.join("ace").join("config.toml"))
+}
+
+impl ConfigFile {
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        Ok(fs::read_to_string(path)?.parse()?)
+    }
+
+    /// Applies the configuration to `app`. Invalid entries are skipped and returned.
+    pub fn apply(&self, app: &mut Application) -> Vec<ConfigError> {
+        let mut errors = self.apply_config(&mut app.config);
+
+        // padding and fonts are ignored by the terminal
+        let opts = &self.options;
+        if let Some(padding) = opts.padding {
+            app.view.set_padding(padding);
+        }
+        if let Some(fontsize) = opts.fontsize {
+            app.view.canvas.win.buf.set_fontsize(fontsize);
+        }
+        if let Some(fontsize) = opts.statusline_fontsize {
+            app.view.statusline.set_fontsize(fontsize);
+        }
+        if let Some(mode) = &opts.linenumbers {
+            match parse_linenumbers(mode) {
+                Some(mode) => app.view.canvas.win.buf.set_mode(mode),
+                None       => errors.push(ConfigError::LineNumbers(mode.clone())),
+            }
+        }
+        if let Some(name) = &opts.clipboard {
+            match clipboard::by_name(name) {
+                Some(provider) => app.ed.set_clipboard(provider),
+                None           => errors.push(ConfigError::Clipboard(name.clone())),
+            }
+        }
+
+        errors.extend(self.colors.apply(&mut app.view.theme));
+        errors
+    }
+
+    /// Applies the parts of the configuration that live in [`Config`].
+    pub fn apply_config(&self, config: &mut Config) -> Vec<ConfigError> {
+        let mut errors = Vec::new();
+
+        if let Some(leader) = &self.options.leader {
+            match leader.parse::<Key>() {
+                Ok(key) => config.set_leader(key),
+                Err(err) => errors.push(ConfigError::Leader(leader.clone(), err)),
+            }
+        }
+        if let Some(timeout) = self.options.timeout {
+            config.set_timeout(timeout as f64 / 1000.);
+        }
+        if let Some(delay) = self.options.repeat_delay {
+            config.set_repeat_delay(delay as f64 / 1000.);
+        }
+        if let Some(rate) = self.options.repeat_rate {
+            config.set_repeat_rate(rate as f64);
+        }
+
+        for (mode, binds) in &self.keymap {
+            let Some(mode) = parse_mode(mode) else {
+                errors.push(ConfigError::UnknownMode(mode.clone()));
+                continue;
+            };
+
+            for (keys, name) in binds {
+                let keys = match Key::parse_sequence(keys) {
+                    Ok(parsed) => parsed,
+                    Err(source) => {
+                        errors.push(ConfigError::Keys { keys: keys.clone(), source });
+                        continue;
+                    }
+                };
+
+                if let Err(err) = config.keymap_named(Keybind::sequence(keys, mode), name) {
+                    errors.push(err.into());
+                }
+            }
+        }
+
+        errors
+    }
+
+}
+
+impl std::str::FromStr for ConfigFile {
+    type Err = toml::de::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        toml::from_str(s)
+    }
+}
+
+impl Colors {
+    fn apply(&self, theme: &mut Theme) -> Vec<ConfigError> {
+        let fields = [
+            ("background",    &self.background,    &mut theme.background),
+            ("text",          &self.text,          &mut theme.text),
+            ("cursor",        &self.cursor,        &mut theme.cursor),
+            ("cursorline",    &self.cursorline,    &mut theme.cursorline),
+            ("selection",     &self.selection,     &mut theme.selection),
+            ("search",        &self.search,        &mut theme.search),
+            ("search_current", &self.search_current, &mut theme.search_current),
+            ("statusline",    &self.statusline,    &mut theme.statusline),
+            ("widget",        &self.widget,        &mut theme.widget),
+            ("widget_active", &self.widget_active, &mut theme.widget_active),
+        ];
+
+        let mut errors = Vec::new();
+        for (name, value, color) in fields {
+            let Some(value) = value else { continue };
+            match parse_color(value) {
+                Some(parsed) => *color = parsed,
+                None         => errors.push(ConfigError::Color { name, value: value.clone() }),
+            }
+        }
+        errors
+    }
+}
+
+fn parse_mode(mode: &str) -> Option<Mode> {
+    Some(match mode {
+        "normal"           => Mode::Normal,
+        "insert"           => Mode::Insert,
+        "visual"           => Mode::Visual,
+        "visual_line"      => Mode::VisualLine,
+        "visual_block"     => Mode::VisualBlock,
+        "operator_pending" => Mode::OperatorPending,
+        "command"          => Mode::Command,
+        _                  => return None,
+    })
+}
+
+pub(super) fn parse_linenumbers(mode: &str) -> Option<LineNumberMode> {
+    match mode {
+        "relative" => Some(LineNumberMode::Relative),
+        "absolute" => Some(LineNumberMode::Absolute),
+        _          => None,
+    }
+}
+
+/// Parses `#rrggbb` or `#rrggbbaa`.
+fn parse_color(color: &str) -> Option<Color> {
+    let hex = color.strip_prefix('#')?;
+    if !matches!(hex.len(), 6 | 8) || !hex.is_ascii() {
+        return None;
+    }
+
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2).unwrap_or("ff"), 16).ok();
+    Some(Color::from_rgba(channel(0)?, channel(2)?, channel(4)?, channel(6)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use macroquad::prelude::KeyCode;
+    use crate::config::keys::Modifiers;
+
+    #[test]
+    fn test_config_file_parse() {
+        let file: ConfigFile = r##"
+            [options]
+            fontsize = 24
+            linenumbers = "absolute"
+            leader = ","
+
+            [colors]
+            background = "#102030"
+
+            [keymap.normal]
+            "<leader>q" = "editor.quit"
+        "##.parse().unwrap();
+
+        assert_eq!(file.options.fontsize, Some(24));
+        assert_eq!(file.colors.background.as_deref(), Some("#102030"));
+
+        let mut config = Config::default();
+        assert!(file.apply_config(&mut config).is_empty());
+        assert_eq!(config.leader(), Key::new(KeyCode::Comma, Modifiers::NoMod));
+
+        let keys = Key::parse_sequence(",q").unwrap();
+        assert!(config.lookup(Mode::Normal, &keys).action.is_some());
+
+        // unknown options are rejected along with their location
+        assert!("[options]\nfontsise = 2".parse::<ConfigFile>().is_err());
+    }
+
+    #[test]
+    fn test_config_file_invalid_entries() {
+        let file: ConfigFile = r#"
+            [keymap.normal]
+            "ä" = "editor.quit"
+            "q" = "editor.explode"
+            "Q" = "editor.quit"
+            "x" = "insert.backspace"
+
+            [keymap.sideways]
+            "x" = "editor.quit"
+        "#.parse().unwrap();
+
+        let mut config = Config::default();
+        let errors = file.apply_config(&mut config);
+
+        assert_eq!(errors.len(), 4);
+        assert!(errors.iter().any(|e| matches!(e, ConfigError::Keys { .. })));
+        assert!(errors.iter().any(|e| matches!(e, ConfigError::Keymap(KeymapError::UnknownAction(name)) if name == "editor.explode")));
+        assert!(errors.iter().any(|e| matches!(e, ConfigError::Keymap(KeymapError::UnsupportedMode(_, Mode::Normal)))));
+        assert!(errors.iter().any(|e| matches!(e, ConfigError::UnknownMode(mode) if mode == "sideways")));
+
+        // valid entries are applied regardless
+        let keys = Key::parse_sequence("Q").unwrap();
+        assert!(config.lookup(Mode::Normal, &keys).action.is_some());
+    }
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("#ff0000"), Some(Color::from_rgba(255, 0, 0, 255)));
+        assert_eq!(parse_color("#00ff0080"), Some(Color::from_rgba(0, 255, 0, 128)));
+        assert_eq!(parse_color("ff0000"), None);
+        assert_eq!(parse_color("#ff00"), None);
+        assert_eq!(parse_color("#gg0000"), None);
+    }
+
+}
@@ -1,13 +1,9 @@
+use std::fmt;
+use std::str::FromStr;
+
 use bitflags::bitflags;
 use macroquad::prelude::*;
 
-use crate::wrap::{
-    is_ctrl_down,
-    is_meta_down,
-    is_shift_down,
-    is_super_down,
-    is_key_active
-};
 use crate::edit::Mode;
 
 
@@ -25,24 +21,6 @@ bitflags! {
 
 impl Modifiers {
 
-    /// Returns true if all of the required modifiers are down
-    #[must_use]
-    pub fn are_down(&self) -> bool {
-
-        // TODO: refactor
-        let nomod = self.is_empty() && !(
-            is_shift_down() ||
-            is_ctrl_down()  ||
-            is_meta_down()  ||
-            is_super_down()
-        );
-        let shift  = self.intersects(Self::Shift) && is_shift_down();
-        let ctrl   = self.intersects(Self::Ctrl)  && is_ctrl_down();
-        let meta   = self.intersects(Self::Meta)  && is_meta_down();
-        let super_ = self.intersects(Self::Super) && is_super_down();
-        nomod || shift || ctrl || meta || super_
-    }
-
     #[must_use]
     pub fn count(&self) -> usize {
         self.iter().count()
@@ -58,21 +36,269 @@ pub struct Key {
 
 impl Key {
 
+    /// Stands in for the leader key, which is resolved when dispatching keybinds.
+    pub const LEADER: Self = Self { key_code: KeyCode::Unknown, mods: Modifiers::NoMod };
+
     pub fn new(key_code: KeyCode, mods: Modifiers) -> Self {
         Self { key_code, mods }
     }
 
-    // TODO: parse string into key
+    /// Returns true for keys that are only ever held down together with others.
+    #[must_use]
+    pub fn is_modifier(key_code: KeyCode) -> bool {
+        matches!(
+            key_code,
+            KeyCode::LeftShift   | KeyCode::RightShift   |
+            KeyCode::LeftControl | KeyCode::RightControl |
+            KeyCode::LeftAlt     | KeyCode::RightAlt     |
+            KeyCode::LeftSuper   | KeyCode::RightSuper
+        )
+    }
+
+}
+
+/// Named keys, as written in between angle brackets. The first name is used for display.
+const NAMED_KEYS: &[(&str, KeyCode)] = &[
+    ("Tab",      KeyCode::Tab),
+    ("Esc",      KeyCode::Escape),
+    ("Escape",   KeyCode::Escape),
+    ("CR",       KeyCode::Enter),
+    ("Enter",    KeyCode::Enter),
+    ("Return",   KeyCode::Enter),
+    ("BS",       KeyCode::Backspace),
+    ("Space",    KeyCode::Space),
+    ("Del",      KeyCode::Delete),
+    ("Insert",   KeyCode::Insert),
+    ("Up",       KeyCode::Up),
+    ("Down",     KeyCode::Down),
+    ("Left",     KeyCode::Left),
+    ("Right",    KeyCode::Right),
+    ("Home",     KeyCode::Home),
+    ("End",      KeyCode::End),
+    ("PageUp",   KeyCode::PageUp),
+    ("PageDown", KeyCode::PageDown),
+    ("F1",       KeyCode::F1),
+    ("F2",       KeyCode::F2),
+    ("F3",       KeyCode::F3),
+    ("F4",       KeyCode::F4),
+    ("F5",       KeyCode::F5),
+    ("F6",       KeyCode::F6),
+    ("F7",       KeyCode::F7),
+    ("F8",       KeyCode::F8),
+    ("F9",       KeyCode::F9),
+    ("F10",      KeyCode::F10),
+    ("F11",      KeyCode::F11),
+    ("F12",      KeyCode::F12),
+];
+
+/// Characters produced by a key on a US layout, without and with shift.
+const CHAR_KEYS: &[(KeyCode, char, char)] = &[
+    (KeyCode::A, 'a', 'A'), (KeyCode::B, 'b', 'B'), (KeyCode::C, 'c', 'C'),
+    (KeyCode::D, 'd', 'D'), (KeyCode::E, 'e', 'E'), (KeyCode::F, 'f', 'F'),
+    (KeyCode::G, 'g', 'G'), (KeyCode::H, 'h', 'H'), (KeyCode::I, 'i', 'I'),
+    (KeyCode::J, 'j', 'J'), (KeyCode::K, 'k', 'K'), (KeyCode::L, 'l', 'L'),
+    (KeyCode::M, 'm', 'M'), (KeyCode::N, 'n', 'N'), (KeyCode::O, 'o', 'O'),
+    (KeyCode::P, 'p', 'P'), (KeyCode::Q, 'q', 'Q'), (KeyCode::R, 'r', 'R'),
+    (KeyCode::S, 's', 'S'), (KeyCode::T, 't', 'T'), (KeyCode::U, 'u', 'U'),
+    (KeyCode::V, 'v', 'V'), (KeyCode::W, 'w', 'W'), (KeyCode::X, 'x', 'X'),
+    (KeyCode::Y, 'y', 'Y'), (KeyCode::Z, 'z', 'Z'),
+    (KeyCode::Key0, '0', ')'), (KeyCode::Key1, '1', '!'), (KeyCode::Key2, '2', '@'),
+    (KeyCode::Key3, '3', '#'), (KeyCode::Key4, '4', '$'), (KeyCode::Key5, '5', '%'),
+    (KeyCode::Key6, '6', '^'), (KeyCode::Key7, '7', '&'), (KeyCode::Key8, '8', '*'),
+    (KeyCode::Key9, '9', '('),
+    (KeyCode::Minus,        '-',  '_'),
+    (KeyCode::Equal,        '=',  '+'),
+    (KeyCode::LeftBracket,  '[',  '{'),
+    (KeyCode::RightBracket, ']',  '}'),
+    (KeyCode::Backslash,    '\\', '|'),
+    (KeyCode::Semicolon,    ';',  ':'),
+    (KeyCode::Apostrophe,   '\'', '"'),
+    (KeyCode::GraveAccent,  '`',  '~'),
+    (KeyCode::Comma,        ',',  '<'),
+    (KeyCode::Period,       '.',  '>'),
+    (KeyCode::Slash,        '/',  '?'),
+];
+
+const MOD_PREFIXES: [(Modifiers, char); 4] = [
+    (Modifiers::Ctrl,  'C'),
+    (Modifiers::Shift, 'S'),
+    (Modifiers::Meta,  'M'),
+    (Modifiers::Super, 'D'),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum KeyParseError {
+    #[error("empty key")]
+    Empty,
+    #[error("unknown key `{0}`")]
+    UnknownKey(String),
+    #[error("unknown modifier `{0}`")]
+    UnknownModifier(char),
+    #[error("missing `>` in `{0}`")]
+    Unclosed(String),
+    #[error("`{0}` is more than a single key")]
+    Sequence(String),
+}
+
+impl Key {
+
+    /// Parses a sequence of keys such as `<leader>ff` or `<C-w>l`.
+    /// Like in vim, `<` is a key of its own unless it starts a valid key name, as in `<<`.
+    pub fn parse_sequence(s: &str) -> Result<Vec<Self>, KeyParseError> {
+        if s.is_empty() {
+            return Err(KeyParseError::Empty);
+        }
+
+        let mut keys = Vec::new();
+        let mut rest = s;
+
+        while let Some(c) = rest.chars().next() {
+            let bracketed = rest
+                .find('>')
+                .filter(|&end| c == '<' && end > 1)
+                .and_then(|end| Some((Self::parse_token(&rest[..=end]).ok()?, end + 1)));
+
+            let (key, len) = match bracketed {
+                Some(bracketed) => bracketed,
+                None            => (Self::parse_token(&rest[..c.len_utf8()])?, c.len_utf8()),
+            };
+
+            keys.push(key);
+            rest = &rest[len..];
+        }
+
+        Ok(keys)
+    }
+
+    fn parse_token(token: &str) -> Result<Self, KeyParseError> {
+        let unknown = || KeyParseError::UnknownKey(token.to_string());
+
+        let Some(inner) = token.strip_prefix('<').and_then(|t| t.strip_suffix('>')) else {
+            let mut chars = token.chars();
+            return match (chars.next(), chars.next()) {
+                (Some(c), None) => Self::from_char(c).ok_or_else(unknown),
+                _               => Err(unknown()),
+            };
+        };
+
+        if inner.eq_ignore_ascii_case("leader") {
+            return Ok(Self::LEADER);
+        }
+
+        // every `X-` in front of the key is a modifier, the key itself may be `-`
+        let mut mods = Modifiers::NoMod;
+        let mut name = inner;
+        while let Some((prefix, rest)) = name.split_once('-') && prefix.chars().count() == 1 && !rest.is_empty() {
+            let c = prefix.chars().next().unwrap().to_ascii_uppercase();
+            let (flag, _) = MOD_PREFIXES
+                .iter()
+                .find(|(_, p)| *p == c)
+                .ok_or(KeyParseError::UnknownModifier(c))?;
+            mods |= *flag;
+            name = rest;
+        }
 
+        let key = match name.to_ascii_lowercase().as_str() {
+            "lt"     => Self::new(KeyCode::Comma, Modifiers::Shift),
+            "bar"    => Self::new(KeyCode::Backslash, Modifiers::Shift),
+            "bslash" => Self::new(KeyCode::Backslash, Modifiers::NoMod),
+            lower    => NAMED_KEYS
+                .iter()
+                .find(|(n, _)| n.to_ascii_lowercase() == lower)
+                .map(|&(_, key_code)| Self::new(key_code, Modifiers::NoMod))
+                .or_else(|| {
+                    let mut chars = name.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) => Self::from_char(c),
+                        _               => None,
+                    }
+                })
+                .ok_or_else(unknown)?,
+        };
+
+        Ok(Self::new(key.key_code, key.mods | mods))
+    }
+
+    /// Returns the key typing `c` on a US layout.
     #[must_use]
-    pub fn is_active(&self) -> bool {
-        is_key_active(self.key_code) && self.mods.are_down()
+    pub fn from_char(c: char) -> Option<Self> {
+        if c == ' ' {
+            return Some(Self::new(KeyCode::Space, Modifiers::NoMod));
+        }
+
+        CHAR_KEYS.iter().find_map(|&(key_code, lower, upper)| match c {
+            _ if c == lower => Some(Self::new(key_code, Modifiers::NoMod)),
+            _ if c == upper => Some(Self::new(key_code, Modifiers::Shift)),
+            _               => None,
+        })
     }
 
+    /// Returns the character typed by this key, ignoring modifiers other than shift.
+    #[must_use]
+    pub fn to_char(self) -> Option<char> {
+        let &(_, lower, upper) = CHAR_KEYS.iter().find(|(key_code, ..)| *key_code == self.key_code)?;
+        Some(if self.mods.contains(Modifiers::Shift) { upper } else { lower })
+    }
+
+}
+
+impl FromStr for Key {
+    type Err = KeyParseError;
+
+    /// Parses vim-style notation of a single key: `x`, `$`, `<C-d>`, `<S-Tab>`, `<M-x>`, `<leader>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match Self::parse_sequence(s)?.as_slice() {
+            [ key ] => Ok(*key),
+            // a key name that did not parse, reported as such instead of as a sequence
+            _ if s.starts_with('<') && s.ends_with('>') => Self::parse_token(s),
+            _ if s.starts_with('<') && !s.contains('>') => Err(KeyParseError::Unclosed(s.to_string())),
+            _ => Err(KeyParseError::Sequence(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if *self == Self::LEADER {
+            return f.write_str("<leader>");
+        }
+
+        // characters only need brackets if modifiers other than shift are involved
+        let shifted = self.mods - Modifiers::Shift;
+        if shifted.is_empty() && let Some(c) = self.to_char() {
+            return match c {
+                '<' => f.write_str("<lt>"),
+                c   => write!(f, "{c}"),
+            };
+        }
+
+        f.write_str("<")?;
+        for (mods, prefix) in MOD_PREFIXES {
+            if self.mods.contains(mods) {
+                write!(f, "{prefix}-")?;
+            }
+        }
+
+        let name = NAMED_KEYS.iter().find(|(_, key_code)| *key_code == self.key_code);
+        match (name, Self::new(self.key_code, Modifiers::NoMod).to_char()) {
+            (Some((name, _)), _) => write!(f, "{name}>"),
+            (None, Some('<'))    => f.write_str("lt>"),
+            (None, Some(c))      => write!(f, "{c}>"),
+            (None, None)         => write!(f, "{:?}>", self.key_code),
+        }
+    }
 }
 
+/// `keybind!(Normal, G, Shift)` binds a single key,
+/// `keybind!(Normal, [G, NoMod], [G, NoMod])` a sequence of keys.
 #[macro_export]
 macro_rules! keybind {
+    ($mode:ident, $([$key:ident, $($mod_:ident),+]),+ $(,)?) => {
+        Keybind::sequence(
+            [ $(Key::new(KeyCode::$key, $(Modifiers::$mod_) | +)),+ ],
+            Mode::$mode
+        )
+    };
     ($mode:ident, $key:ident, $($mod_:ident),+ $(,)?) => {
         Keybind::new(
             Key::new(KeyCode::$key, $(Modifiers::$mod_) | +),
@@ -81,15 +307,71 @@ macro_rules! keybind {
     };
 }
 
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub struct Keybind {
-    // TODO: key-chord
     pub mode: Mode,
-    pub key: Key,
+    /// keys that have to be typed one after another
+    pub keys: Vec<Key>,
 }
 
 impl Keybind {
+
     pub fn new(key: Key, mode: Mode) -> Self {
-        Self { key, mode }
+        Self::sequence([key], mode)
+    }
+
+    pub fn sequence(keys: impl IntoIterator<Item = Key>, mode: Mode) -> Self {
+        Self { keys: keys.into_iter().collect(), mode }
     }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_parse() {
+        assert_eq!("x".parse(), Ok(Key::new(KeyCode::X, Modifiers::NoMod)));
+        assert_eq!("$".parse(), Ok(Key::new(KeyCode::Key4, Modifiers::Shift)));
+        assert_eq!("<C-d>".parse(), Ok(Key::new(KeyCode::D, Modifiers::Ctrl)));
+        assert_eq!("<S-Tab>".parse(), Ok(Key::new(KeyCode::Tab, Modifiers::Shift)));
+        assert_eq!("<m-x>".parse(), Ok(Key::new(KeyCode::X, Modifiers::Meta)));
+        assert_eq!("<C-S-p>".parse(), Ok(Key::new(KeyCode::P, Modifiers::Ctrl | Modifiers::Shift)));
+        assert_eq!("<C-->".parse(), Ok(Key::new(KeyCode::Minus, Modifiers::Ctrl)));
+        assert_eq!("<esc>".parse(), Ok(Key::new(KeyCode::Escape, Modifiers::NoMod)));
+        assert_eq!("<leader>".parse(), Ok(Key::LEADER));
+
+        assert_eq!("<X-a>".parse::<Key>(), Err(KeyParseError::UnknownModifier('X')));
+        assert_eq!("<Foo>".parse::<Key>(), Err(KeyParseError::UnknownKey("<Foo>".to_string())));
+        assert_eq!("<C-d".parse::<Key>(), Err(KeyParseError::Unclosed("<C-d".to_string())));
+        assert_eq!("gg".parse::<Key>(), Err(KeyParseError::Sequence("gg".to_string())));
+        assert_eq!("".parse::<Key>(), Err(KeyParseError::Empty));
+    }
+
+    #[test]
+    fn test_key_parse_sequence() {
+        let keys = Key::parse_sequence("<leader>w<C-w>l").unwrap();
+        assert_eq!(keys, vec![
+            Key::LEADER,
+            Key::new(KeyCode::W, Modifiers::NoMod),
+            Key::new(KeyCode::W, Modifiers::Ctrl),
+            Key::new(KeyCode::L, Modifiers::NoMod),
+        ]);
+
+        // `<` is a key of its own if it does not start a key name
+        let lt = Key::new(KeyCode::Comma, Modifiers::Shift);
+        assert_eq!(Key::parse_sequence("<<"), Ok(vec![ lt, lt ]));
+        assert_eq!(Key::parse_sequence("<a"), Ok(vec![ lt, Key::new(KeyCode::A, Modifiers::NoMod) ]));
+        assert_eq!(Key::parse_sequence("<<C-d>").unwrap()[1], Key::new(KeyCode::D, Modifiers::Ctrl));
+        assert_eq!(Key::parse_sequence("<Foo>").unwrap().len(), 5);
+    }
+
+    #[test]
+    fn test_key_display() {
+        for s in [ "x", "G", "$", "<lt>", "<Space>", "<C-d>", "<S-Tab>", "<M-x>", "<C-S-p>", "<leader>", "<F5>" ] {
+            assert_eq!(s.parse::<Key>().unwrap().to_string(), s);
+        }
+    }
+
 }
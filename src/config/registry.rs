@@ -0,0 +1,130 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::Application;
+use crate::edit::Mode;
+
+use super::Action;
+use super::actions::builtin;
+
+
+
+/// An action that can be referred to by name.
+#[derive(Clone)]
+pub struct ActionInfo {
+    /// dotted name, eg. `buffer.move_down`
+    pub name: &'static str,
+    pub description: &'static str,
+    /// modes the action is meant for, empty if it works in any mode
+    pub modes: &'static [Mode],
+    pub action: Action,
+}
+
+impl ActionInfo {
+    pub fn new(
+        name: &'static str,
+        description: &'static str,
+        modes: &'static [Mode],
+        action: impl Fn(&mut Application, Option<usize>) + 'static,
+    ) -> Self {
+        Self { name, description, modes, action: Rc::new(action) }
+    }
+
+    #[must_use]
+    pub fn supports(&self, mode: Mode) -> bool {
+        self.modes.is_empty() || self.modes.contains(&mode)
+    }
+}
+
+impl fmt::Debug for ActionInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ActionInfo")
+            .field("name", &self.name)
+            .field("description", &self.description)
+            .field("modes", &self.modes)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Maps names to actions, so that keymaps, config files and commands
+/// can refer to them. Contains all [built-in actions](builtin) by default.
+#[derive(Debug, Clone)]
+pub struct Registry {
+    actions: BTreeMap<&'static str, ActionInfo>,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        let mut registry = Self { actions: BTreeMap::new() };
+        for info in builtin() {
+            registry.register(info);
+        }
+        registry
+    }
+}
+
+impl Registry {
+
+    /// Adds an action, replacing any action of the same name.
+    pub fn register(&mut self, info: ActionInfo) {
+        self.actions.insert(info.name, info);
+    }
+
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&ActionInfo> {
+        self.actions.get(name)
+    }
+
+    #[must_use]
+    pub fn action(&self, name: &str) -> Option<Action> {
+        self.get(name).map(|info| info.action.clone())
+    }
+
+    /// Returns the name an action has been registered under.
+    #[must_use]
+    pub fn name_of(&self, action: &Action) -> Option<&'static str> {
+        self.iter()
+            .find(|info| Rc::ptr_eq(&info.action, action))
+            .map(|info| info.name)
+    }
+
+    /// Iterates over all actions, sorted by name.
+    pub fn iter(&self) -> impl Iterator<Item = &ActionInfo> {
+        self.actions.values()
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_builtin() {
+        let registry = Registry::default();
+
+        let info = registry.get("buffer.move_down").unwrap();
+        assert!(info.supports(Mode::OperatorPending));
+        assert!(!info.supports(Mode::Insert));
+        let quit = registry.action("editor.quit").unwrap();
+        assert_eq!(registry.name_of(&quit), Some("editor.quit"));
+        assert!(registry.get("buffer.explode").is_none());
+
+        // names are unique
+        assert_eq!(registry.iter().count(), builtin().len());
+    }
+
+    #[test]
+    fn test_registry_register() {
+        let mut registry = Registry::default();
+        let greeting = String::from("hello");
+        registry.register(ActionInfo::new("user.hello", "Say hello", &[], move |app, _| {
+            app.ed.add_message(greeting.clone());
+        }));
+
+        assert!(registry.get("user.hello").unwrap().supports(Mode::Insert));
+        assert!(registry.iter().map(|info| info.name).is_sorted());
+    }
+
+}
@@ -1,17 +1,31 @@
 use std::{fmt::Display, sync::mpsc::Sender};
 use std::io;
 use std::path::Path;
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::cell::RefCell;
-use std::sync::Mutex;
+use std::rc::Rc;
+use std::ops::Range;
 
 pub mod buffer;
 pub mod window;
 pub mod event;
+pub mod history;
+pub mod text;
+pub mod motion;
+pub mod cmdline;
+pub mod search;
+pub mod substitute;
+pub mod clipboard;
+pub mod register;
 use event::EventData;
 
-use buffer::{Buffer, Buffers, BufferID};
+use buffer::{Buffer, Buffers, BufferID, Cursor};
 use window::{Windows, WindowID, Window};
+use cmdline::{Cmdline, Prompt};
+use search::{Highlight, HighlightKind, Match, Search, SearchError};
+use substitute::Substitution;
+use clipboard::ClipboardProvider;
+use register::{Register, RegisterKind, Registers};
 
 pub struct EditorContext {
     pub event_queue: VecDeque<EventData>,
@@ -25,20 +39,55 @@ impl EditorContext {
     }
 }
 
-pub static CONTEXT: Mutex<EditorContext> = Mutex::new(EditorContext::new());
+thread_local! {
+    /// The editor runs on a single thread, which keeps the events of tests apart.
+    pub static CONTEXT: RefCell<EditorContext> = const { RefCell::new(EditorContext::new()) };
+}
+
+/// Queues an event for the autocmds subscribed to it.
+pub fn emit(ev: EventData) {
+    CONTEXT.with_borrow_mut(|ctx| ctx.event_queue.push_back(ev));
+}
+
+/// Takes the oldest queued event.
+pub fn next_event() -> Option<EventData> {
+    CONTEXT.with_borrow_mut(|ctx| ctx.event_queue.pop_front())
+}
+
+#[must_use]
+pub fn has_events() -> bool {
+    CONTEXT.with_borrow(|ctx| !ctx.event_queue.is_empty())
+}
 
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
 pub enum Mode {
     #[default] Normal,
     Insert,
+    Visual,
+    VisualLine,
+    VisualBlock,
+    OperatorPending,
+    Command,
+}
+
+impl Mode {
+    #[must_use]
+    pub fn is_visual(&self) -> bool {
+        matches!(self, Self::Visual | Self::VisualLine | Self::VisualBlock)
+    }
 }
 
 impl Display for Mode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str(match self {
-            Mode::Normal => "normal",
-            Mode::Insert => "insert",
+            Mode::Normal      => "normal",
+            Mode::Insert      => "insert",
+            Mode::Visual      => "visual",
+            Mode::VisualLine  => "visual line",
+            Mode::VisualBlock => "visual block",
+            Mode::OperatorPending => "operator pending",
+            Mode::Command     => "command",
         })
     }
 }
@@ -61,33 +110,113 @@ impl Statusline {
 
 pub type Message = String;
 
+/// What has the focus, compared before and after an action, see [`Editor::emit_focus_changes`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Focus {
+    win:    Option<WindowID>,
+    buf:    Option<BufferID>,
+    cursor: Option<Cursor>,
+}
+
+/// State of a search while its pattern is being typed
+#[derive(Debug, Clone)]
+struct Incsearch {
+    /// where the cursor was when the search started
+    origin: Cursor,
+    /// matches of the pattern typed so far
+    matches: Rc<[Match]>,
+    /// the match the cursor moved to
+    current: Option<Match>,
+}
+
+/// The matches of a search in a buffer, which stay valid until the pattern or the text changes.
 #[derive(Debug, Clone)]
+struct MatchCache {
+    pattern: String,
+    revision: u64,
+    matches: Rc<[Match]>,
+}
+
+/// Where the last paste went, so that an older kill can replace it.
+#[derive(Debug, Clone)]
+struct Paste {
+    buf: BufferID,
+    /// where the cursor was before pasting
+    cursor: Cursor,
+    start: Cursor,
+    end: Cursor,
+    /// the lines a block has been pasted into, as they were before,
+    /// since the rows may have been padded and lines added
+    lines: Option<String>,
+    before: bool,
+    count: usize,
+    /// position in the kill ring of the pasted text, if it came from there
+    kill: Option<usize>,
+    /// whether it was pasted by the action that is currently running, or the last one
+    fresh: bool,
+}
+
+#[derive(Debug)]
 pub struct Editor {
     messages: Vec<Message>,
     buffers:  Buffers,
     mode:     Mode,
     windows:  Windows,
+    cmdline:  Cmdline,
+    /// the last search, which `n` and `N` repeat
+    search:   Option<Search>,
+    /// whether the matches of the last search are highlighted
+    hlsearch: bool,
+    /// matches of the last pattern searched for in each buffer
+    match_cache: RefCell<HashMap<BufferID, MatchCache>>,
+    incsearch: Option<Incsearch>,
+    /// substitution the command being typed would run, along with that command
+    preview:  Option<(String, Substitution)>,
+    /// substitution asking before each replacement
+    confirm:  Option<Substitution>,
+    clipboard: Box<dyn ClipboardProvider>,
+    registers: Registers,
+    paste:    Option<Paste>,
+    /// whether the command line changed since it has last been drawn
+    dirty:    bool,
 }
 
 impl Editor {
 
+    /// Starts out with an empty buffer.
     pub fn new() -> Self {
+        let mut buffers = Buffers::default();
+        buffers.add();
+        Self::with_buffers(buffers)
+    }
+
+    pub fn with_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::with_buffers(Buffers::with_file(path)?))
+    }
+
+    /// Shows the first of `buffers` in a window.
+    fn with_buffers(buffers: Buffers) -> Self {
+        let id = buffers.ids().next().expect("there is a buffer to show");
+
         Self {
             messages: Vec::new(),
-            windows:  Windows::default(),
-            buffers:  Buffers::default(),
+            windows:  Windows::with_buffer(id),
+            buffers,
             mode:     Mode::default(),
+            cmdline:  Cmdline::default(),
+            search:   None,
+            hlsearch: false,
+            match_cache: RefCell::default(),
+            incsearch: None,
+            preview:  None,
+            confirm:  None,
+            clipboard: Box::new(clipboard::Memory::default()),
+            registers: Registers::default(),
+            paste:    None,
+            dirty:    true,
         }
     }
 
-    pub fn with_file(path: impl AsRef<Path>) -> io::Result<Self> {
-        Ok(Self {
-            buffers: Buffers::with_file(path)?,
-            windows: Windows::with_buffer(0),
-            ..Self::new()
-        })
-    }
-
     /// Add a [`Message`] to the Editors message buffer.
     pub fn add_message(&mut self, msg: Message) {
         self.messages.push(msg);
@@ -107,6 +236,362 @@ impl Editor {
             .load_buffer(messages);
     }
 
+    /// Deletes the buffer, showing another one in the windows that showed it.
+    /// Deleting the last buffer leaves an empty one behind.
+    /// Returns false if there is no such buffer.
+    pub fn delete_buffer(&mut self, id: BufferID) -> bool {
+        if self.buffers.remove(id).is_none() {
+            return false;
+        }
+        self.match_cache.get_mut().remove(&id);
+
+        let other = self.buffers.ids().find(|&other| other > id).or_else(|| self.buffers.ids().last());
+        let other = other.unwrap_or_else(|| self.buffers.add());
+
+        let showing = self.windows.windows
+            .iter()
+            .filter(|(_, win)| win.buf() == Some(id))
+            .map(|(&winid, _)| winid)
+            .collect::<Vec<_>>();
+
+        for winid in showing {
+            self.windows.get_mut(winid).unwrap().set_buf(other);
+        }
+        true
+    }
+
+    #[must_use]
+    pub fn focus(&self) -> Focus {
+        Focus {
+            win:    self.winid(),
+            buf:    self.bufid(),
+            cursor: self.buf().map(Buffer::cursor),
+        }
+    }
+
+    /// Emits the events of the window, buffer and cursor that the focus moved away from and onto.
+    /// Windows and buffers that have been deleted in the meantime are not left.
+    pub fn emit_focus_changes(&self, before: Focus) {
+        let after = self.focus();
+
+        if before.buf != after.buf && let Some(id) = before.buf && self.buffers.get(id).is_some() {
+            emit(EventData::BufLeave(id));
+        }
+        if before.win != after.win && let Some(id) = before.win && self.windows.get(id).is_some() {
+            emit(EventData::WinLeave(id));
+        }
+        if before.win != after.win && let Some(id) = after.win {
+            emit(EventData::WinEnter(id));
+        }
+        if before.buf != after.buf && let Some(id) = after.buf {
+            emit(EventData::BufEnter(id));
+        }
+        if (before.buf, before.cursor) != (after.buf, after.cursor)
+            && let (Some(buf), Some(cursor)) = (after.buf, after.cursor)
+        {
+            emit(EventData::CursorMoved { buf, cursor });
+        }
+    }
+
+    #[must_use]
+    pub fn search(&self) -> Option<&Search> {
+        self.search.as_ref()
+    }
+
+    /// Opens the prompt of a search towards the bottom, or towards the top if `backward`.
+    pub fn start_search(&mut self, backward: bool) {
+        let Some(buf) = self.buf_mut() else { return };
+        let origin = buf.cursor();
+        buf.set_mode(Mode::Command);
+
+        self.incsearch = Some(Incsearch { origin, matches: Rc::from([]), current: None });
+        self.cmdline_mut().open(if backward { Prompt::SearchBackward } else { Prompt::Search });
+    }
+
+    /// Moves the cursor to the match of the pattern typed so far, see [`Editor::start_search`].
+    /// Invalid or empty patterns leave the cursor where the search started.
+    pub fn update_incsearch(&mut self) {
+        let Some(Incsearch { origin, .. }) = self.incsearch else { return };
+        let Some(id) = self.bufid() else { return };
+
+        let backward = self.cmdline.prompt() == Prompt::SearchBackward;
+        let pattern = self.cmdline.text();
+        let matches = Search::new(pattern, backward)
+            .ok()
+            .filter(|_| !pattern.is_empty())
+            .map_or_else(|| Rc::from([]), |search| self.matches(&search, id));
+        let current = Search::next(&matches, origin, backward).map(|(m, _)| m);
+
+        self.incsearch = Some(Incsearch { origin, matches, current });
+        if let Some(buf) = self.buf_mut() {
+            buf.jump_to(current.map_or(origin, |m| m.start));
+        }
+    }
+
+    /// Moves the cursor back to where the search started.
+    pub fn cancel_search(&mut self) {
+        let Some(Incsearch { origin, .. }) = self.incsearch.take() else { return };
+        self.dirty = true;
+        if let Some(buf) = self.buf_mut() {
+            buf.jump_to(origin);
+        }
+    }
+
+    /// Searches for `pattern` from where the search started, or for the last pattern if it is empty.
+    /// Returns true if the search wrapped around the end of the buffer.
+    pub fn submit_search(&mut self, pattern: &str, backward: bool) -> Result<bool, SearchError> {
+        self.cancel_search();
+
+        let pattern = match (pattern, &self.search) {
+            ("", Some(search)) => search.pattern().to_string(),
+            ("", None)         => return Err(SearchError::NoPattern),
+            (pattern, _)       => pattern.to_string(),
+        };
+        self.set_search(Search::new(&pattern, backward)?);
+        self.search_next(false)
+    }
+
+    /// Moves the cursor to the next match of the last search, in the opposite direction if `reverse`.
+    /// Returns true if the search wrapped around the end of the buffer.
+    pub fn search_next(&mut self, reverse: bool) -> Result<bool, SearchError> {
+        self.hlsearch = true;
+        let search = self.search.as_ref().ok_or(SearchError::NoPattern)?;
+        let Some(buf) = self.buf() else { return Ok(false) };
+
+        let backward = search.is_backward() != reverse;
+        let matches = self.matches(search, self.bufid().unwrap());
+        let (m, wrapped) = Search::next(&matches, buf.cursor(), backward)
+            .ok_or_else(|| SearchError::NotFound(search.pattern().to_string()))?;
+
+        self.buf_mut().unwrap().jump_to(m.start);
+        Ok(wrapped)
+    }
+
+    /// Ranges of the zero-based `lines` of the buffer to highlight. The focused buffer shows the
+    /// matches of the search or substitution being typed or confirmed, any buffer those of the last search.
+    #[must_use]
+    pub fn highlights(&self, id: BufferID, lines: Range<usize>) -> Vec<Highlight> {
+        let Some(buf) = self.buffers.get(id) else { return Vec::new() };
+        let focused = self.bufid() == Some(id);
+
+        let (matches, current) = if focused && let Some(sub) = &self.confirm {
+            (sub.matches_in(buf, lines.clone()), sub.current(buf).map(|found| found.range))
+        } else if focused && let Some(incsearch) = &self.incsearch {
+            (Search::within(&incsearch.matches, &lines).to_vec(), incsearch.current)
+        } else if focused && let Some((_, sub)) = &self.preview {
+            (sub.matches_in(buf, lines.clone()), None)
+        } else if self.hlsearch && let Some(search) = &self.search {
+            let matches = search.matches_in(buf, lines.clone());
+            let current = matches.iter().copied().find(|m| m.contains(buf.cursor()));
+            (matches, current)
+        } else {
+            return Vec::new();
+        };
+
+        matches
+            .into_iter()
+            .filter(|m| lines.contains(&(m.start.y as usize)))
+            .map(|range| Highlight {
+                range,
+                kind: if Some(range) == current { HighlightKind::Current } else { HighlightKind::Match },
+            })
+            .collect()
+    }
+
+    /// All matches of `search` in the buffer `id`. They are kept until the pattern or the text
+    /// changes, so that drawing them and counting them does not search the buffer every time.
+    #[must_use]
+    pub fn matches(&self, search: &Search, id: BufferID) -> Rc<[Match]> {
+        let Some(buf) = self.buffers.get(id) else { return Rc::from([]) };
+        let mut cache = self.match_cache.borrow_mut();
+
+        if let Some(cached) = cache.get(&id)
+            && cached.pattern == search.pattern()
+            && cached.revision == buf.revision()
+        {
+            return cached.matches.clone();
+        }
+
+        let matches: Rc<[Match]> = search.matches(buf).into();
+        cache.insert(id, MatchCache {
+            pattern: search.pattern().to_string(),
+            revision: buf.revision(),
+            matches: matches.clone(),
+        });
+        matches
+    }
+
+    #[must_use]
+    pub fn clipboard(&self) -> &dyn ClipboardProvider {
+        self.clipboard.as_ref()
+    }
+
+    pub fn clipboard_mut(&mut self) -> &mut dyn ClipboardProvider {
+        self.clipboard.as_mut()
+    }
+
+    pub fn set_clipboard(&mut self, clipboard: Box<dyn ClipboardProvider>) {
+        self.clipboard = clipboard;
+    }
+
+    #[must_use]
+    pub fn registers(&self) -> &Registers {
+        &self.registers
+    }
+
+    /// Moves what the buffers yanked or deleted into the register `name`,
+    /// copying it to the system clipboard for `+` and `*`.
+    pub fn store_yanks(&mut self, name: Option<char>) -> io::Result<()> {
+        let ids = self.buffers.ids().collect::<Vec<_>>();
+        let yanks = ids
+            .into_iter()
+            .filter_map(|id| self.buffers.get_mut(id)?.take_yank())
+            .collect::<Vec<_>>();
+
+        for yank in yanks {
+            let text = yank.register.to_clipboard();
+            self.registers.store(name, yank);
+            if name.is_some_and(clipboard::is_clipboard_register) {
+                self.clipboard.set(&text)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The contents of the register `name`, or the unnamed one.
+    pub fn register(&mut self, name: Option<char>) -> io::Result<Option<Register>> {
+        if name.is_some_and(clipboard::is_clipboard_register) {
+            return Ok(Some(Register::from_clipboard(&self.clipboard.get()?)));
+        }
+        Ok(self.registers.get(name).cloned())
+    }
+
+    /// Pastes the register `name` into the focused buffer `count` times, after the cursor
+    /// or `before` it. Pasting the unnamed register starts cycling through the kill ring.
+    pub fn paste(&mut self, name: Option<char>, before: bool, count: usize) -> io::Result<()> {
+        let Some(register) = self.register(name)? else { return Ok(()) };
+        let kill = matches!(name, None | Some('"')).then_some(0);
+        self.paste_register(&register, before, count, kill);
+        Ok(())
+    }
+
+    fn paste_register(&mut self, register: &Register, before: bool, count: usize, kill: Option<usize>) {
+        let Some(id) = self.bufid() else { return };
+        let buf = self.buffers.get_mut(id).unwrap();
+        let cursor = buf.cursor();
+        let lines = (register.kind == RegisterKind::Blockwise).then(|| {
+            let rows = register.text.split('\n').count();
+            buf.getlines().skip(cursor.y as usize).take(rows).collect::<Vec<_>>().join("\n")
+        });
+        let (start, end) = buf.paste(register, before, count);
+        self.paste = Some(Paste { buf: id, cursor, start, end, lines, before, count, kill, fresh: true });
+    }
+
+    /// Replaces the text just pasted with the kill before it, like emacs' `yank-pop`.
+    /// Returns false if the last action has not pasted anything.
+    pub fn cycle_paste(&mut self) -> bool {
+        let Some(paste) = self.paste.clone().filter(|paste| Some(paste.buf) == self.bufid()) else { return false };
+        if self.registers.kills() == 0 {
+            return false;
+        }
+
+        let kill = paste.kill.map_or(0, |kill| kill + 1) % self.registers.kills();
+        let register = self.registers.kill(kill).unwrap().clone();
+
+        let buf = self.buffers.get_mut(paste.buf).unwrap();
+        match &paste.lines {
+            Some(lines) => {
+                let width = text::grapheme_count(&buf.line(paste.end.y as usize)) as isize;
+                buf.replace(Cursor::new(0, paste.start.y), Cursor::new(width, paste.end.y), lines);
+            }
+            None => buf.replace(paste.start, paste.end, ""),
+        }
+        buf.jump_to(paste.cursor);
+        self.paste_register(&register, paste.before, paste.count, Some(kill));
+        true
+    }
+
+    /// Forgets about the last paste, unless it happened during the last action,
+    /// so that only the action right after a paste can cycle it.
+    pub fn expire_paste(&mut self) {
+        match &mut self.paste {
+            Some(paste) if paste.fresh => paste.fresh = false,
+            _                          => self.paste = None,
+        }
+    }
+
+    /// Stops highlighting the matches of the last search, until searching again.
+    pub fn clear_highlights(&mut self) {
+        self.dirty = true;
+        self.hlsearch = false;
+    }
+
+    /// The command whose substitution is previewed, see [`Editor::set_preview`].
+    #[must_use]
+    pub fn preview_command(&self) -> Option<&str> {
+        self.preview.as_ref().map(|(command, _)| command.as_str())
+    }
+
+    /// Highlights what `sub` would replace, as the `command` being typed.
+    pub fn set_preview(&mut self, command: String, sub: Substitution) {
+        self.dirty = true;
+        self.preview = Some((command, sub));
+    }
+
+    pub fn clear_preview(&mut self) {
+        if self.preview.take().is_some() {
+            self.dirty = true;
+        }
+    }
+
+    #[must_use]
+    pub fn confirming(&self) -> Option<&Substitution> {
+        self.confirm.as_ref()
+    }
+
+    /// Asks before each replacement of `sub`, moving the cursor to the first one.
+    /// Returns false if there is nothing to replace.
+    pub fn start_confirm(&mut self, sub: Substitution) -> bool {
+        let Some(buf) = self.buf_mut() else { return false };
+        let Some(found) = sub.current(buf) else { return false };
+
+        buf.jump_to(found.range.start);
+        self.confirm = Some(sub);
+        true
+    }
+
+    /// Answers whether to replace the current match of the substitution being confirmed:
+    /// `y` replaces it, `l` replaces it as the last one, `n` skips it, `a` replaces all remaining
+    /// and anything else stops. Returns the substitution once it is over.
+    pub fn confirm(&mut self, answer: char) -> Option<Substitution> {
+        let mut sub = self.confirm.take()?;
+        let Some(buf) = self.buf_mut() else { return Some(sub) };
+        let Some(found) = sub.current(buf) else { return Some(sub) };
+
+        match answer {
+            'y' => sub.replace(buf, &found),
+            'n' => sub.skip(buf, &found),
+            'a' => sub.run(buf),
+            'l' => {
+                sub.replace(buf, &found);
+                return Some(sub);
+            }
+            _ => return Some(sub),
+        }
+
+        let Some(next) = sub.current(buf) else { return Some(sub) };
+        buf.jump_to(next.range.start);
+        self.confirm = Some(sub);
+        None
+    }
+
+    /// Sets the pattern that `n` and `N` search for, highlighting its matches.
+    pub fn set_search(&mut self, search: Search) {
+        self.dirty = true;
+        self.search = Some(search);
+        self.hlsearch = true;
+    }
+
     /// Retrieves the [`WindowID`] of the currently focused [`Window`]
     #[must_use]
     pub fn winid(&self) -> Option<WindowID> {
@@ -152,4 +637,63 @@ impl Editor {
         &mut self.buffers
     }
 
+    #[must_use]
+    pub fn cmdline(&self) -> &Cmdline {
+        &self.cmdline
+    }
+
+    #[must_use]
+    pub fn cmdline_mut(&mut self) -> &mut Cmdline {
+        &mut self.cmdline
+    }
+
+    /// Returns true if anything changed since the editor has last been drawn.
+    #[must_use]
+    pub fn is_dirty(&self) -> bool {
+        self.dirty || self.windows.is_dirty() || self.buffers.is_dirty() || self.cmdline.is_dirty()
+    }
+
+    /// Marks everything as drawn.
+    pub fn clean(&mut self) {
+        self.dirty = false;
+        self.windows.clean();
+        self.buffers.clean();
+        self.cmdline.clean();
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn events() -> Vec<EventData> {
+        std::iter::from_fn(next_event).collect()
+    }
+
+    #[test]
+    fn test_editor_events() {
+        use EventData::*;
+
+        let mut ed = Editor::new();
+        assert_eq!(events(), [ BufNew(0), WinNew(0) ]);
+
+        let id = ed.buffers_mut().add();
+        let win = ed.windows_mut().add(Some(id));
+        assert_eq!(events(), [ BufNew(1), WinNew(1) ]);
+
+        // windows showing a deleted buffer show another one
+        assert!(ed.delete_buffer(id));
+        assert!(!ed.delete_buffer(id));
+        assert_eq!(ed.windows().get(win).unwrap().buf(), Some(0));
+
+        // deleting the last buffer leaves an empty one
+        assert!(ed.delete_buffer(0));
+        assert_eq!(ed.bufid(), Some(2));
+        assert_eq!(events(), [ BufDel(1), BufDel(0), BufNew(2) ]);
+
+        ed.windows_mut().delete();
+        assert_eq!(events(), [ WinDel(0) ]);
+    }
+
 }
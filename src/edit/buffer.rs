@@ -1,10 +1,17 @@
-use std::io::Write;
+use std::borrow::Cow;
 use std::{fs, io, path};
+use std::io::Write;
 use std::path::{PathBuf, Path};
 use std::collections::BTreeMap;
 use std::sync::mpsc::Sender;
 
+use unicode_segmentation::UnicodeSegmentation;
+
 use super::{EventData, Mode};
+use super::history::{History, Change};
+use super::text::{self, Text, Lines};
+use super::motion::{Motion, Operator, Scope, TextObject, TextRange};
+use super::register::{Register, RegisterKind, Yank};
 
 
 
@@ -18,15 +25,24 @@ pub struct Buffers {
     idcount: BufferID,
     /// Using [`BTreeMap`], as the data should always be in order.
     buffers: BTreeMap<BufferID, Buffer>,
+    /// whether buffers have been added since they have last been drawn
+    dirty: bool,
 }
 
 impl Buffers {
 
     pub fn with_file(path: impl AsRef<Path>) -> io::Result<Self> {
-        Ok(Self {
-            buffers: [ (0, Buffer::with_file(path)?) ].into(),
-            idcount: 1
-        })
+        let mut self_ = Self::default();
+        self_.open(path)?;
+        Ok(self_)
+    }
+
+    /// Adds a buffer loaded from the file at `path`. It is only added once loading succeeded,
+    /// so that no events are emitted for a buffer that never existed, and `BufReadPre` not at all.
+    pub fn open(&mut self, path: impl AsRef<Path>) -> io::Result<BufferID> {
+        let id = self.insert(Buffer::with_file(path)?);
+        super::emit(EventData::BufReadPost(id));
+        Ok(id)
     }
 
     #[must_use]
@@ -39,19 +55,51 @@ impl Buffers {
         self.buffers.get_mut(&id)
     }
 
+    /// Returns true if any buffer changed since they have last been drawn.
+    #[must_use]
+    pub fn is_dirty(&self) -> bool {
+        self.dirty || self.buffers.values().any(Buffer::is_dirty)
+    }
+
+    pub fn clean(&mut self) {
+        self.dirty = false;
+        self.buffers.values_mut().for_each(Buffer::clean);
+    }
+
     #[must_use]
     pub fn count(&self) -> usize {
         self.buffers.len()
     }
 
     pub fn add(&mut self) -> BufferID {
+        self.insert(Buffer::new())
+    }
+
+    /// Adds an existing [`Buffer`], eg. one that has been loaded from a file.
+    pub fn insert(&mut self, mut buf: Buffer) -> BufferID {
+        self.dirty = true;
         let id = self.idcount;
         self.idcount += 1;
-        let ret = self.buffers.insert(id, Buffer::new());
+        buf.id = Some(id);
+        let ret = self.buffers.insert(id, buf);
         assert!(ret.is_none());
+        super::emit(EventData::BufNew(id));
         id
     }
 
+    /// Removes the buffer, which may still be shown in windows, see [`super::Editor::delete_buffer`].
+    pub fn remove(&mut self, id: BufferID) -> Option<Buffer> {
+        let buf = self.buffers.remove(&id)?;
+        self.dirty = true;
+        super::emit(EventData::BufDel(id));
+        Some(buf)
+    }
+
+    /// Iterates over the ids of all buffers in ascending order.
+    pub fn ids(&self) -> impl Iterator<Item = BufferID> + '_ {
+        self.buffers.keys().copied()
+    }
+
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
@@ -67,6 +115,13 @@ impl Cursor {
     }
 }
 
+impl From<Cursor> for (usize, usize) {
+    /// Converts into a `(line, column)` position as used by [`Text`]
+    fn from(value: Cursor) -> Self {
+        (value.y as usize, value.x as usize)
+    }
+}
+
 impl From<(isize, isize)> for Cursor {
     fn from(value: (isize, isize)) -> Self {
         Self::new(value.0, value.1)
@@ -88,14 +143,61 @@ impl std::ops::Add for Cursor {
 }
 
 
-// TODO:
-#[derive(Debug, Clone)]
+const INDENT: &str = "    ";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionKind {
+    Char,
+    Line,
+    Block,
+}
+
+/// The region between the anchor, which is set when entering a visual mode,
+/// and the cursor. Both ends are inclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Selection {
-    // anchor is current cursor
-    target: Cursor,
+    pub anchor: Cursor,
+    pub cursor: Cursor,
+    pub kind: SelectionKind,
 }
 
-// TODO: implement kill ring and permanent clipboard
+impl Selection {
+
+    pub fn new(anchor: Cursor, cursor: Cursor, kind: SelectionKind) -> Self {
+        Self { anchor, cursor, kind }
+    }
+
+    /// Returns the start and end of the selection in text order.
+    #[must_use]
+    pub fn bounds(&self) -> (Cursor, Cursor) {
+        let (a, c) = (self.anchor, self.cursor);
+        if (a.y, a.x) <= (c.y, c.x) { (a, c) } else { (c, a) }
+    }
+
+    /// Returns the selected columns of line `y` as an exclusive range,
+    /// given the amount of chars of that line.
+    #[must_use]
+    pub fn columns(&self, y: isize, width: isize) -> Option<(isize, isize)> {
+        let (start, end) = self.bounds();
+        if y < start.y || y > end.y { return None }
+
+        let (from, to) = match self.kind {
+            SelectionKind::Line  => (0, width),
+            SelectionKind::Block => {
+                let left = self.anchor.x.min(self.cursor.x);
+                let right = self.anchor.x.max(self.cursor.x);
+                (left, right + 1)
+            }
+            SelectionKind::Char  => (
+                if y == start.y { start.x } else { 0 },
+                if y == end.y { end.x + 1 } else { width },
+            ),
+        };
+
+        Some((from.min(width), to.min(width)))
+    }
+
+}
 
 // TODO: move mode into buffer struct instead of editor
 
@@ -103,17 +205,35 @@ pub struct Selection {
 pub struct Buffer {
     filename: Option<PathBuf>,
     cursor: Cursor,
-    lines: Vec<String>,
+    text: Text,
     mode: Mode,
-    pub clipboard: Vec<String>,
+    /// text yanked or deleted by the last operator, until the editor stores it in a register
+    yank: Option<Yank>,
 
-    pub search_query: String,
+
+    history: History,
+
+    /// set while in one of the visual modes
+    anchor: Option<Cursor>,
+
+    /// set while in operator-pending mode
+    operator: Option<Operator>,
+    operator_count: Option<usize>,
 
     /// allows the cursor to be out-of-bounds
     /// by one char at the end of the line.
     /// used by `A`, and `a` at end of a line.
     // TODO: make private, as its an implementation detail
     pub append: bool,
+
+    /// whether the text, cursor or mode changed since the buffer has last been drawn
+    dirty: bool,
+
+    /// counts changes to the text, to tell when what was derived from it is outdated
+    revision: u64,
+
+    /// set once the buffer is added to [`Buffers`], which its events refer to
+    id: Option<BufferID>,
 }
 
 impl Buffer {
@@ -131,53 +251,67 @@ impl Buffer {
     pub fn new() -> Self {
         Self {
             mode: Mode::default(),
-            clipboard: Vec::new(),
-            search_query: "foo".to_string(),
+            yank: None,
             filename: None,
             cursor: Cursor::default(),
-            lines: vec![ String::new() ],
+            text: Text::new(),
+            history: History::default(),
+            anchor: None,
+            operator: None,
+            operator_count: None,
             append: true,
+            dirty: true,
+            revision: 0,
+            id: None,
         }
     }
 
+    #[must_use]
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub fn clean(&mut self) {
+        self.dirty = false;
+    }
+
     pub fn set_filename(&mut self, filename: impl AsRef<Path>) -> io::Result<()> {
         // using absolute() instead of canonicalize() as
         // `filename` could possibly not exist yet
         self.filename = Some(path::absolute(filename)?);
+        self.dirty = true;
         Ok(())
     }
 
     /// Wipes the buffer, loading the given buffer.
     pub fn load_buffer(&mut self, buf: Vec<String>) {
-        *self = Self::new();
-
-        self.lines = buf;
-
-        let is_empty = self.lines.is_empty();
-        self.append = is_empty;
-
-        if is_empty {
-            self.lines.push(String::new());
-        }
+        self.load_text(Text::from(buf.join("\n").as_str()));
+    }
 
+    fn load_text(&mut self, text: Text) {
+        let id = self.id;
+        let revision = self.revision;
+        *self = Self::new();
+        self.id = id;
+        self.revision = revision + 1;
+        self.text = text;
+        self.check_cursor();
     }
 
     /// Wipes the buffer, loading a buffer from the file at the given path.
     pub fn load_file(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
         let path = path.as_ref();
+        self.emit(EventData::BufReadPre);
 
-        self.set_filename(path)?;
-
-        let lines = if fs::exists(path)? {
-            fs::read_to_string(path)?
-                .lines()
-                .map(str::to_string)
-                .collect()
+        let text = if fs::exists(path)? {
+            Text::from_reader(io::BufReader::new(fs::File::open(path)?))?
         } else {
-            Vec::new()
+            Text::new()
         };
 
-        self.load_buffer(lines);
+        self.load_text(text);
+        self.set_filename(path)?;
+        self.emit(EventData::BufReadPost);
 
         Ok(())
 
@@ -190,40 +324,56 @@ impl Buffer {
 
     pub fn save_to_file(&self, filename: impl AsRef<Path>) -> io::Result<()> {
 
-        if self.getlines().is_empty() {
-            return Ok(());
-        }
-
-        let buf = self.lines.join("\n");
-
-        fs::File::options()
+        let file = fs::File::options()
             .create(true)
             .write(true)
             .truncate(true)
-            .open(filename)?
-            .write_all(buf.as_bytes())?;
+            .open(filename)?;
 
-        Ok(())
+        // flushing explicitly, as an error would be lost when the writer is dropped
+        let mut writer = io::BufWriter::new(file);
+        self.text.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Changes whenever the text does.
+    #[must_use]
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    #[must_use]
+    pub fn id(&self) -> Option<BufferID> {
+        self.id
     }
 
     //
     // Internal helpers
     //
 
+    /// Emits an event about this buffer, unless it is not part of [`Buffers`].
+    fn emit(&self, ev: impl FnOnce(BufferID) -> EventData) {
+        if let Some(id) = self.id {
+            super::emit(ev(id));
+        }
+    }
+
     /// moves the cursor to a valid position if it is out-of-bounds.
-    /// used after most operations that modify the cursor or data.
+    /// used after most operations that modify the cursor or data,
+    /// which is why it marks the buffer as dirty.
     fn check_cursor(&mut self) {
+        self.dirty = true;
 
         // line cursor has to be verified before char cursor,
         // as it may hold an invalid value, such that indexing into
-        // the lines vec may panic
-        let max_line = self.lines.len() as isize - 1;
+        // the text may panic
+        let max_line = self.text.line_count() as isize - 1;
         self.cursor.y = self.cursor.y
             .clamp(0, max_line);
 
-        let len = self.getline().len() as isize;
+        let len = self.linewidth() as isize;
 
-        if self.getline().is_empty() {
+        if len == 0 {
             self.append = true;
         } else if self.cursor.x != len {
             self.append = false;
@@ -235,6 +385,84 @@ impl Buffer {
 
     }
 
+    /// Number of grapheme clusters in the current line.
+    fn linewidth(&self) -> usize {
+        text::grapheme_count(&self.getline())
+    }
+
+    /// Converts a cursor, whose column is a grapheme index, into
+    /// a position whose column is a byte offset into the line.
+    fn byte_pos(&self, cursor: Cursor) -> Cursor {
+        let line = self.text.line(cursor.y as usize);
+        let x = text::grapheme_offset(&line, cursor.x as usize);
+        Cursor::new(x as isize, cursor.y)
+    }
+
+    /// Returns the position right after `text`, if it were inserted at `pos`.
+    fn text_end(pos: Cursor, text: &str) -> Cursor {
+        match text.rsplit_once('\n') {
+            Some((head, tail)) => Cursor::new(
+                tail.len() as isize,
+                pos.y + head.matches('\n').count() as isize + 1,
+            ),
+            None => Cursor::new(pos.x + text.len() as isize, pos.y),
+        }
+    }
+
+    /// Inserts `text` at `pos` without recording it in the history.
+    /// Newlines in `text` split the line.
+    fn raw_insert(&mut self, pos: Cursor, text: &str) {
+        self.text.insert((pos.y as usize, pos.x as usize), text);
+        self.revision += 1;
+        self.dirty = true;
+        let end = Self::text_end(pos, text);
+        self.emit(|buf| EventData::TextChanged { buf, start: pos.y as usize, end: end.y as usize + 1 });
+    }
+
+    /// Removes the text between `start` (inclusive) and `end` (exclusive)
+    /// without recording it in the history. Returns the removed text.
+    fn raw_remove(&mut self, start: Cursor, end: Cursor) -> String {
+        let text = self.text.remove(
+            (start.y as usize, start.x as usize),
+            (end.y as usize, end.x as usize),
+        );
+        if !text.is_empty() {
+            self.revision += 1;
+            self.dirty = true;
+            self.emit(|buf| EventData::TextChanged { buf, start: start.y as usize, end: start.y as usize + 1 });
+        }
+        text
+    }
+
+    fn apply(&mut self, change: &Change) {
+        match change {
+            Change::Insert { pos, text } => self.raw_insert(*pos, text),
+            Change::Remove { pos, text } => {
+                self.raw_remove(*pos, Self::text_end(*pos, text));
+            }
+        }
+    }
+
+    /// Inserts `text` at the byte position `pos`, recording the change in the history.
+    fn insert_text(&mut self, pos: Cursor, text: &str) {
+        if text.is_empty() { return }
+
+        self.history.record(Change::Insert { pos, text: text.to_string() }, self.cursor);
+        self.raw_insert(pos, text);
+    }
+
+    /// Removes the text between the byte positions `start` (inclusive)
+    /// and `end` (exclusive), recording the change in the history. Returns the removed text.
+    fn remove_text(&mut self, start: Cursor, end: Cursor) -> String {
+        let text = self.raw_remove(start, end);
+
+        if !text.is_empty() {
+            self.history.record(Change::Remove { pos: start, text: text.clone() }, self.cursor);
+        }
+
+        text
+    }
+
     //
     // Getter API
     //
@@ -245,10 +473,23 @@ impl Buffer {
     }
 
     pub fn set_mode(&mut self, mode: Mode) {
-        self.mode = mode;
+        self.dirty = true;
+        if mode != Mode::OperatorPending {
+            self.operator = None;
+            self.operator_count = None;
+        }
+
+        // switching between visual modes keeps the anchor
+        if !mode.is_visual() {
+            self.anchor = None;
+        } else if self.anchor.is_none() {
+            self.anchor = Some(self.cursor);
+        }
 
-        let mut ctx = super::CONTEXT.lock().unwrap();
-        ctx.event_queue.push_back(EventData::ModeChanged(mode));
+        let old = std::mem::replace(&mut self.mode, mode);
+        if old != mode {
+            super::emit(EventData::ModeChanged { old, new: mode });
+        }
     }
 
 
@@ -263,22 +504,42 @@ impl Buffer {
     }
 
     #[must_use]
-    pub fn getlines(&self) -> &[String] {
-        &self.lines
+    pub fn getlines(&self) -> Lines<'_> {
+        self.text.lines()
+    }
+
+    #[must_use]
+    pub fn getline(&self) -> Cow<'_, str> {
+        self.text.line(self.cursor.y as usize)
     }
 
+    /// Returns the zero-based line `y`, which must exist.
     #[must_use]
-    pub fn getline(&self) -> &str {
-        &self.lines[self.cursor.y as usize]
+    pub fn line(&self, y: usize) -> Cow<'_, str> {
+        self.text.line(y)
     }
 
+    /// Returns [`None`] if not in any of the visual modes.
+    #[must_use]
+    pub fn selection(&self) -> Option<Selection> {
+        let kind = match self.mode {
+            Mode::Visual      => SelectionKind::Char,
+            Mode::VisualLine  => SelectionKind::Line,
+            Mode::VisualBlock => SelectionKind::Block,
+            _ => return None,
+        };
+        Some(Selection::new(self.anchor?, self.cursor, kind))
+    }
+
+    /// Returns the grapheme cluster under the cursor.
     /// Returns [`None`] if cursor is out-of-bounds (append mode)
     /// in which case the cursor is not pointing to any valid char
     #[must_use]
-    pub fn getchar(&self) -> Option<char> {
-        self.lines[self.cursor.y as usize]
-            .chars()
+    pub fn getchar(&self) -> Option<String> {
+        self.getline()
+            .graphemes(true)
             .nth(self.cursor.x as usize)
+            .map(str::to_string)
     }
 
     //
@@ -286,53 +547,119 @@ impl Buffer {
     //
 
     pub fn newline_above(&mut self) {
-        self.lines.insert(self.cursor.y as usize, String::new());
+        self.insert_text(Cursor::new(0, self.cursor.y), "\n");
         // cursor will be on the newly inserted line,
         // and could therefore be out-of-bounds
         self.check_cursor();
     }
 
     pub fn newline_below(&mut self) {
-        self.lines.insert(self.cursor.y as usize + 1, String::new());
+        let end = Cursor::new(self.text.line_len(self.cursor.y as usize) as isize, self.cursor.y);
+        self.insert_text(end, "\n");
     }
 
     pub fn insert_string(&mut self, str: impl AsRef<str>) {
-        self.lines[self.cursor.y as usize]
-            .insert_str(self.cursor.x as usize, str.as_ref());
+        self.insert_text(self.byte_pos(self.cursor), str.as_ref());
     }
 
     pub fn insert_char(&mut self, c: char) {
-        self.lines[self.cursor.y as usize]
-            .insert(self.cursor.x as usize, c);
+        self.insert_text(self.byte_pos(self.cursor), c.encode_utf8(&mut [0; 4]));
     }
 
     /// Splits the text at the cursor into two lines.
     /// Intended to be used in insert mode.
     pub fn split_newline(&mut self) {
-        let line = &mut self.lines[self.cursor.y as usize];
-
-        let str = line.split_off(self.cursor.x as usize);
-        self.lines.insert(self.cursor.y as usize + 1, str);
+        self.insert_text(self.byte_pos(self.cursor), "\n");
         self.move_start_line();
         self.move_down();
     }
 
-    /// Pops from the Killring
-    pub fn paste_pop(&mut self) {
-        self.newline_below();
-        self.move_down();
-        if let Some(str) = self.clipboard.pop() {
-            self.insert_string(str);
-        }
+    /// Pastes `register` `count` times after the cursor, or `before` it. Lines go below
+    /// or above the current one, and the rows of a block into the lines from the current one on.
+    /// Returns the range of the pasted text in graphemes, with the end exclusive,
+    /// which are the corners of a block.
+    pub fn paste(&mut self, register: &Register, before: bool, count: usize) -> (Cursor, Cursor) {
+        let y = self.cursor.y;
+
+        let (start, end) = if register.kind == RegisterKind::Blockwise {
+            let x = if before { self.cursor.x } else { (self.cursor.x + 1).min(self.linewidth() as isize) };
+            let end = self.paste_block(&register.text, Cursor::new(x, y), count);
+            // on the top left corner of the block
+            self.cursor = Cursor::new(x, y);
+            (Cursor::new(x, y), end)
+        } else if register.is_linewise() {
+            let lines = vec![ register.text.as_str(); count ].join("\n");
+            let added = lines.matches('\n').count() as isize + 1;
+
+            if before {
+                self.insert_text(Cursor::new(0, y), &format!("{lines}\n"));
+                self.cursor = Cursor::new(0, y);
+                (Cursor::new(0, y), Cursor::new(0, y + added))
+            } else {
+                let width = self.linewidth() as isize;
+                self.insert_text(Cursor::new(self.text.line_len(y as usize) as isize, y), &format!("\n{lines}"));
+                self.cursor = Cursor::new(0, y + 1);
+                let last = lines.rsplit('\n').next().unwrap_or_default();
+                (Cursor::new(width, y), Cursor::new(text::grapheme_count(last) as isize, y + added))
+            }
+        } else {
+            let text = register.text.repeat(count);
+            let x = if before { self.cursor.x } else { (self.cursor.x + 1).min(self.linewidth() as isize) };
+            let start = Cursor::new(x, y);
+            self.insert_text(self.byte_pos(start), &text);
+
+            let end = match text.rsplit_once('\n') {
+                Some((head, tail)) => Cursor::new(
+                    text::grapheme_count(tail) as isize,
+                    y + head.matches('\n').count() as isize + 1,
+                ),
+                None => Cursor::new(x + text::grapheme_count(&text) as isize, y),
+            };
+            // on the last pasted character
+            self.cursor = Cursor::new(end.x - 1, end.y);
+            (start, end)
+        };
+
+        self.check_cursor();
+        (start, end)
     }
 
-    /// Pastes the from the Killring, without popping
-    pub fn paste(&mut self) {
-        self.newline_below();
-        self.move_down();
-        if let Some(str) = self.clipboard.last() {
-            self.insert_string(str.clone());
+    /// Inserts each row of `block` `count` times into the lines from `pos` on, at the column of `pos`.
+    /// Lines are added at the end of the text, and short ones padded with spaces to reach the column.
+    /// Rows are padded to the width of the block as well, unless nothing follows them.
+    /// Returns the bottom right corner of the block, exclusive.
+    fn paste_block(&mut self, block: &str, pos: Cursor, count: usize) -> Cursor {
+        let rows = block.split('\n').collect::<Vec<_>>();
+        let width = rows.iter().map(|row| text::grapheme_count(row)).max().unwrap_or(0);
+
+        for (i, row) in rows.iter().enumerate() {
+            let y = pos.y + i as isize;
+            if y as usize >= self.text.line_count() {
+                let last = self.text.line_count() - 1;
+                self.insert_text(Cursor::new(self.text.line_len(last) as isize, last as isize), "\n");
+            }
+
+            let len = text::grapheme_count(&self.text.line(y as usize)) as isize;
+            let padded = format!("{row}{}", " ".repeat(width - text::grapheme_count(row)));
+            let mut text = padded.repeat(count.saturating_sub(1));
+            text.push_str(if pos.x < len { &padded } else { row });
+            if text.is_empty() { continue }
+
+            let padding = " ".repeat((pos.x - len).max(0) as usize);
+            let at = Cursor::new(pos.x.min(len), y);
+            self.insert_text(self.byte_pos(at), &format!("{padding}{text}"));
         }
+
+        Cursor::new(pos.x + (width * count) as isize, pos.y + rows.len() as isize - 1)
+    }
+
+    /// Takes what the last operator yanked or deleted.
+    pub fn take_yank(&mut self) -> Option<Yank> {
+        self.yank.take()
+    }
+
+    fn set_yank(&mut self, register: Register, deleted: bool) {
+        self.yank = Some(Yank { register, deleted });
     }
 
     //
@@ -340,33 +667,360 @@ impl Buffer {
     //
 
     pub fn clear_current_line(&mut self) {
-        self.lines[self.cursor.y as usize].clear();
+        let y = self.cursor.y;
+        self.remove_text(Cursor::new(0, y), Cursor::new(self.text.line_len(y as usize) as isize, y));
         self.check_cursor();
     }
 
     pub fn yank_line(&mut self) {
-        self.clipboard.push(self.getline().to_string());
+        self.set_yank(Register::linewise(self.getline()), false);
     }
 
     pub fn delete_line(&mut self) {
 
-        self.clipboard.push(self.getline().to_string());
+        self.set_yank(Register::linewise(self.getline()), true);
+
+        self.remove_lines(self.cursor.y, self.cursor.y);
+        self.check_cursor();
+
+    }
+
+    /// Returns the selection as a range, unless it is blockwise.
+    fn selection_range(&self, sel: &Selection) -> Option<TextRange> {
+        let (start, end) = sel.bounds();
+
+        match sel.kind {
+            SelectionKind::Line  => Some(TextRange::lines(start.y, end.y)),
+            SelectionKind::Block => None,
+            SelectionKind::Char  => {
+                let width = text::grapheme_count(&self.text.line(end.y as usize)) as isize;
+                let last = self.text.line_count() as isize - 1;
+
+                // selecting past the end of a line includes its newline
+                let end = if end.x >= width && end.y < last {
+                    Cursor::new(0, end.y + 1)
+                } else {
+                    Cursor::new(end.x + 1, end.y)
+                };
+
+                Some(TextRange::new(start, end))
+            }
+        }
+    }
+
+    /// Returns the byte range of the selected part of line `y` of a block selection.
+    fn block_range(&self, sel: &Selection, y: isize) -> (Cursor, Cursor) {
+        let width = text::grapheme_count(&self.text.line(y as usize)) as isize;
+        let (from, to) = sel.columns(y, width).unwrap_or_default();
+        (self.byte_pos(Cursor::new(from, y)), self.byte_pos(Cursor::new(to, y)))
+    }
+
+    /// Returns the selected text. Rows of a block selection are separated by newlines.
+    #[must_use]
+    pub fn selected_text(&self) -> Option<String> {
+        let sel = self.selection()?;
+
+        if let Some(range) = self.selection_range(&sel) {
+            return Some(self.range_text(&range));
+        }
+
+        let (start, end) = sel.bounds();
+        let text = (start.y..=end.y)
+            .map(|y| {
+                let (from, to) = self.block_range(&sel, y);
+                self.text.slice(from.into(), to.into())
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        Some(text)
+    }
+
+    /// Applies an operator to the selection, leaving visual mode.
+    pub fn operate_selection(&mut self, op: Operator) {
+        let Some(sel) = self.selection() else { return };
+
+        if let Some(range) = self.selection_range(&sel) {
+            self.apply_operator(op, range);
+            return;
+        }
+
+        // blockwise
+        let (start, end) = sel.bounds();
+        let left = Cursor::new(sel.anchor.x.min(sel.cursor.x), start.y);
+
+        match op {
+            Operator::Yank => {
+                self.set_yank(Register::blockwise(self.selected_text().unwrap()), false);
+            }
+            Operator::Delete | Operator::Change => {
+                self.set_yank(Register::blockwise(self.selected_text().unwrap()), true);
+                for y in start.y..=end.y {
+                    let (from, to) = self.block_range(&sel, y);
+                    self.remove_text(from, to);
+                }
+            }
+            Operator::Indent | Operator::Dedent => {
+                self.apply_operator(op, TextRange::lines(start.y, end.y));
+                return;
+            }
+        }
+
+        self.cursor = left;
+        self.finish_operator(op);
+    }
+
+    //
+    // Range API
+    //
+
+    /// Converts a charwise range into byte positions.
+    fn byte_range(&self, range: &TextRange) -> (Cursor, Cursor) {
+        (self.byte_pos(range.start), self.byte_pos(range.end))
+    }
+
+    /// Removes the lines from `start` to `end` (inclusive), always leaving at least
+    /// one line in the buffer. Returns the removed lines, without their line breaks.
+    fn remove_lines(&mut self, start: isize, end: isize) -> String {
+        let last = self.text.line_count() as isize - 1;
+        let len = self.text.line_len(end as usize) as isize;
+
+        // line breaks may be `\r\n`, so the lines are read rather than cut out of the removed text
+        let lines = (start..=end)
+            .map(|y| self.text.line(y as usize))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        if end < last {
+            self.remove_text(Cursor::new(0, start), Cursor::new(0, end + 1));
+        } else if start > 0 {
+            // last line has no trailing line break, so remove the preceding one instead
+            let prev = self.text.line_len(start as usize - 1) as isize;
+            self.remove_text(Cursor::new(prev, start - 1), Cursor::new(len, end));
+        } else {
+            self.remove_text(Cursor::new(0, start), Cursor::new(len, end));
+        }
+
+        lines
+    }
+
+    /// Indents (or dedents) the lines from `start` to `end` (inclusive) by one level.
+    /// Empty lines are not indented.
+    fn indent_lines(&mut self, start: isize, end: isize, dedent: bool) {
+        for y in start..=end {
+            let line = self.text.line(y as usize);
+
+            if dedent {
+                let spaces = line
+                    .bytes()
+                    .take(INDENT.len())
+                    .take_while(|b| *b == b' ')
+                    .count() as isize;
+                self.remove_text(Cursor::new(0, y), Cursor::new(spaces, y));
+            } else if !line.is_empty() {
+                self.insert_text(Cursor::new(0, y), INDENT);
+            }
+        }
+    }
+
+    /// Returns the text covered by `range`.
+    /// Linewise ranges do not include the final newline.
+    #[must_use]
+    pub fn range_text(&self, range: &TextRange) -> String {
+        if range.linewise {
+            let len = self.text.line_len(range.end.y as usize);
+            self.text.slice((range.start.y as usize, 0), (range.end.y as usize, len))
+        } else {
+            let (start, end) = self.byte_range(range);
+            self.text.slice(start.into(), end.into())
+        }
+    }
+
+    /// Removes the text covered by `range`, returning it.
+    pub fn delete_range(&mut self, range: &TextRange) -> String {
+        let text = if range.linewise {
+            self.remove_lines(range.start.y, range.end.y)
+        } else {
+            let (start, end) = self.byte_range(range);
+            self.remove_text(start, end)
+        };
+
+        self.cursor = if range.linewise {
+            Cursor::new(self.cursor.x, range.start.y)
+        } else {
+            range.start
+        };
+        self.check_cursor();
 
-        if self.lines.len() == 1 {
-            self.clear_current_line();
+        text
+    }
+
+    /// Applies `op` to the text covered by `range`.
+    /// Deleted, changed and yanked text is kept for the registers, see [`Self::take_yank`].
+    pub fn apply_operator(&mut self, op: Operator, range: TextRange) {
+        let register = |text: String| if range.linewise {
+            Register::linewise(text)
         } else {
-            self.lines.remove(self.cursor.y as usize);
-            self.check_cursor();
+            Register::charwise(text)
+        };
+
+        match op {
+            Operator::Yank => {
+                self.set_yank(register(self.range_text(&range)), false);
+                if !range.linewise {
+                    self.cursor = range.start;
+                }
+                self.cursor.y = range.start.y;
+            }
+            Operator::Delete => {
+                let text = self.delete_range(&range);
+                self.set_yank(register(text), true);
+            }
+            Operator::Change if range.linewise => {
+                // changed lines are replaced by a single empty line
+                self.set_yank(register(self.range_text(&range)), true);
+                let len = self.text.line_len(range.end.y as usize) as isize;
+                self.remove_text(Cursor::new(0, range.start.y), Cursor::new(len, range.end.y));
+                self.cursor = Cursor::new(0, range.start.y);
+            }
+            Operator::Change => {
+                let text = self.delete_range(&range);
+                self.set_yank(register(text), true);
+                self.cursor = range.start;
+            }
+            Operator::Indent | Operator::Dedent => {
+                // a charwise range ending at the start of a line does not include that line
+                let end = if !range.linewise && range.end.x == 0 && range.end.y > range.start.y {
+                    range.end.y - 1
+                } else {
+                    range.end.y
+                };
+                self.indent_lines(range.start.y, end, op == Operator::Dedent);
+                self.cursor = Cursor::new(0, range.start.y);
+            }
         }
 
+        self.finish_operator(op);
+    }
+
+    /// Leaves visual/operator-pending mode after an operator has been applied.
+    fn finish_operator(&mut self, op: Operator) {
+        if op == Operator::Change {
+            // the changed text may have reached until the end of the line
+            self.append = true;
+            self.set_mode(Mode::Insert);
+        } else {
+            self.set_mode(Mode::Normal);
+        }
+        self.check_cursor();
+    }
+
+    //
+    // Operator-pending API
+    //
+
+    /// Enters operator-pending mode. Repeating the same operator
+    /// (eg. `dd`, `yy`, `>>`) applies it to `count` lines, starting at the current one.
+    pub fn begin_operator(&mut self, op: Operator, count: Option<usize>) {
+        if self.mode == Mode::OperatorPending && self.operator == Some(op) {
+            let count = self.pending_count(count).unwrap_or(1) as isize;
+            let last = self.text.line_count() as isize - 1;
+            let end = (self.cursor.y + count - 1).min(last);
+            self.apply_operator(op, TextRange::lines(self.cursor.y, end));
+            return;
+        }
+
+        // another operator aborts the pending one, as in vim
+        if self.mode == Mode::OperatorPending {
+            self.cancel_operator();
+            return;
+        }
+
+        self.set_mode(Mode::OperatorPending);
+        self.operator = Some(op);
+        self.operator_count = count;
+    }
+
+    /// Combines the count given to the operator with the one given to the motion (`2d3w`)
+    fn pending_count(&self, count: Option<usize>) -> Option<usize> {
+        match (self.operator_count, count) {
+            (None, None) => None,
+            (a, b)       => Some(a.unwrap_or(1) * b.unwrap_or(1)),
+        }
+    }
+
+    /// Leaves operator-pending mode without doing anything.
+    pub fn cancel_operator(&mut self) {
+        self.set_mode(Mode::Normal);
+    }
+
+    /// Applies the pending operator to the range covered by moving `count` times.
+    pub fn operate_motion(&mut self, motion: Motion, count: Option<usize>) {
+        let Some(op) = self.operator else { return };
+
+        // `cw` on a word behaves like `ce`
+        let motion = if op == Operator::Change && motion == Motion::WordForward && !self.on_blank() {
+            Motion::WordEnd
+        } else {
+            motion
+        };
+
+        let range = self.motion_range(motion, self.pending_count(count));
+        self.apply_operator(op, range);
+    }
+
+    /// Applies the pending operator to the inner (`i`) or around (`a`) part of a text object.
+    pub fn operate_object(&mut self, object: TextObject, scope: Scope) {
+        let Some(op) = self.operator else { return };
+
+        match self.text_object_range(object, scope) {
+            Some(range) => self.apply_operator(op, range),
+            None        => self.cancel_operator(),
+        }
     }
 
     /// Deletes the character at the cursor
     pub fn delete_char(&mut self) {
-        if self.getline().is_empty() { return }
+        let Some(grapheme) = self.getchar() else { return };
 
-        self.lines[self.cursor.y as usize]
-            .remove(self.cursor.x as usize);
+        let start = self.byte_pos(self.cursor);
+        let end = Cursor::new(start.x + grapheme.len() as isize, start.y);
+        self.remove_text(start, end);
+        self.check_cursor();
+    }
+
+    //
+    // History API
+    //
+
+    /// Groups all changes made since the last commit into a single undo step.
+    pub fn commit(&mut self) {
+        self.history.commit(self.cursor);
+    }
+
+    /// Reverts the most recent undo step, restoring the cursor
+    /// to where it was before the step was made.
+    pub fn undo(&mut self) {
+        self.commit();
+        let Some(step) = self.history.undo() else { return };
+
+        for change in step.changes.iter().rev() {
+            self.apply(&change.inverse());
+        }
+
+        self.cursor = step.cursor_before;
+        self.check_cursor();
+    }
+
+    /// Reapplies the most recently undone step.
+    pub fn redo(&mut self) {
+        let Some(step) = self.history.redo() else { return };
+
+        for change in &step.changes {
+            self.apply(change);
+        }
+
+        self.cursor = step.cursor_after;
         self.check_cursor();
     }
 
@@ -374,6 +1028,12 @@ impl Buffer {
     // Movement API
     //
 
+    /// Moves the cursor to the target of `motion`, repeated `count` times.
+    pub fn move_motion(&mut self, motion: Motion, count: Option<usize>) {
+        self.cursor = self.motion_target(motion, count);
+        self.check_cursor();
+    }
+
     pub fn move_down_many(&mut self, count: isize) {
         self.cursor.y += count;
         self.check_cursor();
@@ -410,7 +1070,29 @@ impl Buffer {
     }
 
     pub fn move_bottom(&mut self) {
-        self.cursor.y = self.lines.len() as isize - 1;
+        self.cursor.y = self.text.line_count() as isize - 1;
+        self.check_cursor();
+    }
+
+    /// Replaces the text between `start` (inclusive) and `end` (exclusive) with `text`.
+    /// Newlines in `text` split the line.
+    pub fn replace(&mut self, start: Cursor, end: Cursor, text: &str) {
+        let start = self.byte_pos(start);
+        let end = self.byte_pos(end);
+        self.remove_text(start, end);
+        self.insert_text(start, text);
+        self.check_cursor();
+    }
+
+    /// Moves to `cursor`, clamped to the text.
+    pub fn jump_to(&mut self, cursor: Cursor) {
+        self.cursor = cursor;
+        self.check_cursor();
+    }
+
+    /// Moves to the zero-based line `line`, clamped to the last line.
+    pub fn move_to_line(&mut self, line: usize) {
+        self.cursor.y = line as isize;
         self.check_cursor();
     }
 
@@ -429,25 +1111,8 @@ impl Buffer {
 
     /// move to the last char of the current line
     pub fn move_end_line(&mut self) {
-        self.cursor.x = self.getline().len() as isize - 1;
-    }
-
-    //
-    // Miscellaneous
-    //
-
-    /// Searches the buffer for all occurances of the given substring.
-    /// Returns a Vec of indices to the start of the found substrings.
-    pub fn search(&self) -> Vec<Cursor> {
-
-        let query = &self.search_query;
-        self.lines
-            .iter()
-            // TODO: what about multiple search results?
-            .enumerate()
-            // TODO: refactor
-            .filter_map(|(idx, line)| line.find(query).map(|q| (idx as isize, q as isize).into()))
-            .collect()
+        self.cursor.x = self.linewidth() as isize - 1;
+        self.dirty = true;
     }
 
 }
@@ -458,6 +1123,10 @@ impl Buffer {
 mod tests {
     use super::*;
 
+    fn lines(buf: &Buffer) -> Vec<String> {
+        buf.getlines().map(Cow::into_owned).collect()
+    }
+
     #[test]
     fn test_buffers_count() {
         let mut buffers = Buffers::default();
@@ -510,7 +1179,7 @@ mod tests {
         buf.newline_below();
         buf.move_down();
         buf.insert_string("foobarbaz");
-        assert_eq!(buf.lines, vec![ "foo", "foobarbaz" ]);
+        assert_eq!(lines(&buf), vec![ "foo", "foobarbaz" ]);
         buf.move_end_line();
 
         buf.move_up();
@@ -524,7 +1193,7 @@ mod tests {
         buf.newline_below();
         buf.move_down();
         buf.insert_string("foo");
-        assert_eq!(buf.lines, vec![ "foobarbaz", "foo" ]);
+        assert_eq!(lines(&buf), vec![ "foobarbaz", "foo" ]);
         buf.move_up();
         buf.move_end_line();
 
@@ -546,7 +1215,7 @@ mod tests {
         buf.insert_char('o');
         buf.move_left();
         buf.split_newline();
-        assert_eq!(buf.lines, vec![ "hel", "lo" ]);
+        assert_eq!(lines(&buf), vec![ "hel", "lo" ]);
     }
 
     #[test]
@@ -560,7 +1229,250 @@ mod tests {
         buf.insert_char('x');
         buf.insert_char('y');
         buf.insert_char('z');
-        assert_eq!(buf.lines, vec![ "cba", "zyx" ]);
+        assert_eq!(lines(&buf), vec![ "cba", "zyx" ]);
+    }
+
+    #[test]
+    fn test_buffer_undo_redo() {
+        let mut buf = Buffer::new();
+        buf.insert_string("foo");
+        buf.commit();
+        buf.newline_below();
+        buf.move_down();
+        buf.insert_string("bar");
+        buf.commit();
+        assert_eq!(lines(&buf), vec![ "foo", "bar" ]);
+
+        buf.undo();
+        assert_eq!(lines(&buf), vec![ "foo" ]);
+        assert_eq!(buf.cursor, Cursor::new(0, 0));
+
+        buf.undo();
+        assert_eq!(lines(&buf), vec![ "" ]);
+
+        buf.redo();
+        buf.redo();
+        assert_eq!(lines(&buf), vec![ "foo", "bar" ]);
+        assert_eq!(buf.cursor.y, 1);
+    }
+
+    #[test]
+    fn test_buffer_undo_delete_line() {
+        let mut buf = Buffer::new();
+        buf.load_buffer(vec![ "foo".into(), "bar".into(), "baz".into() ]);
+        buf.move_bottom();
+        buf.delete_line();
+        buf.commit();
+        buf.move_top();
+        buf.delete_line();
+        buf.commit();
+        assert_eq!(lines(&buf), vec![ "bar" ]);
+
+        buf.undo();
+        buf.undo();
+        assert_eq!(lines(&buf), vec![ "foo", "bar", "baz" ]);
+        assert_eq!(buf.cursor.y, 2);
+    }
+
+    #[test]
+    fn test_buffer_undo_groups_insert_session() {
+        let mut buf = Buffer::new();
+        buf.set_mode(Mode::Insert);
+        for c in "hello".chars() {
+            buf.insert_char(c);
+            buf.move_right();
+        }
+        buf.split_newline();
+        buf.insert_string("world");
+        buf.set_mode(Mode::Normal);
+        buf.commit();
+        assert_eq!(lines(&buf), vec![ "hello", "world" ]);
+
+        buf.undo();
+        assert_eq!(lines(&buf), vec![ "" ]);
+
+        // making a change discards the redo history
+        buf.insert_char('x');
+        buf.redo();
+        assert_eq!(lines(&buf), vec![ "x" ]);
+    }
+
+    #[test]
+    fn test_buffer_multibyte_insert_delete() {
+        let mut buf = Buffer::new();
+        for c in "Grüße".chars() {
+            buf.insert_char(c);
+            buf.move_right();
+        }
+        assert_eq!(lines(&buf), vec![ "Grüße" ]);
+        assert_eq!(buf.cursor.x, 5);
+
+        buf.move_left();
+        buf.move_left();
+        buf.move_left();
+        assert_eq!(buf.getchar().as_deref(), Some("ü"));
+        buf.delete_char();
+        assert_eq!(lines(&buf), vec![ "Grße" ]);
+
+        buf.insert_string("日本");
+        buf.split_newline();
+        assert_eq!(lines(&buf), vec![ "Gr", "日本ße" ]);
+    }
+
+    #[test]
+    fn test_buffer_grapheme_clusters() {
+        let mut buf = Buffer::new();
+        // family emoji (joined by zero-width joiners) and `e` with combining acute accent
+        buf.load_buffer(vec![ "a👨‍👩‍👧e\u{301}b".into() ]);
+
+        buf.move_end_line();
+        assert_eq!(buf.cursor.x, 3);
+        assert_eq!(buf.getchar().as_deref(), Some("b"));
+
+        buf.move_left();
+        assert_eq!(buf.getchar().as_deref(), Some("e\u{301}"));
+        buf.delete_char();
+        buf.move_left();
+        buf.delete_char();
+        assert_eq!(lines(&buf), vec![ "ab" ]);
+
+        buf.undo();
+        buf.undo();
+        assert_eq!(lines(&buf), vec![ "a👨‍👩‍👧e\u{301}b" ]);
+    }
+
+    fn sample() -> Buffer {
+        let mut buf = Buffer::new();
+        buf.load_buffer(vec![ "foo bar".into(), "baz qux".into(), "quux".into() ]);
+        buf
+    }
+
+    #[test]
+    fn test_buffer_visual_charwise() {
+        let mut buf = sample();
+        buf.move_right();
+        buf.set_mode(Mode::Visual);
+        buf.move_down();
+        assert_eq!(buf.selected_text().as_deref(), Some("oo bar\nba"));
+
+        buf.operate_selection(Operator::Delete);
+        assert_eq!(buf.mode(), Mode::Normal);
+        assert_eq!(lines(&buf), vec![ "fz qux", "quux" ]);
+        assert_eq!(buf.cursor, Cursor::new(1, 0));
+        assert_eq!(buf.take_yank().map(|yank| yank.register), Some(Register::charwise("oo bar\nba")));
+    }
+
+    #[test]
+    fn test_buffer_visual_linewise() {
+        let mut buf = sample();
+        buf.move_bottom();
+        buf.set_mode(Mode::VisualLine);
+        buf.move_up();
+        assert_eq!(buf.selected_text().as_deref(), Some("baz qux\nquux"));
+
+        buf.operate_selection(Operator::Delete);
+        assert_eq!(lines(&buf), vec![ "foo bar" ]);
+
+        buf.undo();
+        assert_eq!(lines(&buf), vec![ "foo bar", "baz qux", "quux" ]);
+    }
+
+    #[test]
+    fn test_buffer_visual_blockwise() {
+        let mut buf = sample();
+        buf.move_right();
+        buf.set_mode(Mode::VisualBlock);
+        buf.move_down();
+        buf.move_down();
+        buf.move_right();
+        assert_eq!(buf.selected_text().as_deref(), Some("oo\naz\nuu"));
+
+        buf.operate_selection(Operator::Change);
+        assert_eq!(buf.mode(), Mode::Insert);
+        assert_eq!(lines(&buf), vec![ "f bar", "b qux", "qx" ]);
+        assert_eq!(buf.cursor, Cursor::new(1, 0));
+        let block = Register::blockwise("oo\naz\nuu");
+        assert_eq!(buf.take_yank().map(|yank| yank.register), Some(block.clone()));
+
+        // blocks are pasted column by column, padding rows that are followed by text
+        buf.set_mode(Mode::Normal);
+        buf.jump_to(Cursor::new(0, 1));
+        let range = buf.paste(&block, false, 1);
+        assert_eq!(lines(&buf), vec![ "f bar", "boo qux", "qazx", " uu" ]);
+        assert_eq!(range, (Cursor::new(1, 1), Cursor::new(3, 3)));
+        assert_eq!(buf.cursor, Cursor::new(1, 1));
+
+        let mut buf = sample();
+        buf.paste(&Register::blockwise("a\nbc"), true, 2);
+        assert_eq!(lines(&buf), vec![ "a a foo bar", "bcbcbaz qux", "quux" ]);
+    }
+
+    #[test]
+    fn test_buffer_visual_change_lines() {
+        let mut buf = sample();
+        buf.set_mode(Mode::VisualLine);
+        buf.move_down();
+        buf.operate_selection(Operator::Change);
+        assert_eq!(lines(&buf), vec![ "", "quux" ]);
+        assert_eq!(buf.cursor, Cursor::new(0, 0));
+    }
+
+    #[test]
+    fn test_buffer_visual_indent() {
+        let mut buf = sample();
+        buf.set_mode(Mode::Visual);
+        buf.move_down();
+        buf.operate_selection(Operator::Indent);
+        assert_eq!(lines(&buf), vec![ "    foo bar", "    baz qux", "quux" ]);
+
+        buf.set_mode(Mode::VisualLine);
+        buf.operate_selection(Operator::Dedent);
+        assert_eq!(lines(&buf), vec![ "foo bar", "    baz qux", "quux" ]);
+    }
+
+    #[test]
+    fn test_buffer_delete_lines_crlf() {
+        let mut buf = Buffer::new();
+        buf.load_text(Text::from("a\r\nb\r\nc"));
+        let dd = |buf: &mut Buffer| {
+            buf.begin_operator(Operator::Delete, None);
+            buf.begin_operator(Operator::Delete, None);
+            buf.take_yank().unwrap().register
+        };
+
+        // the last line takes the line break before it
+        buf.move_bottom();
+        assert_eq!(dd(&mut buf), Register::linewise("c"));
+        assert_eq!(lines(&buf), vec![ "a", "b" ]);
+
+        buf.move_top();
+        let register = dd(&mut buf);
+        assert_eq!(register, Register::linewise("a"));
+        buf.paste(&register, false, 1);
+        assert_eq!(lines(&buf), vec![ "b", "a" ]);
+    }
+
+    #[test]
+    fn test_buffer_paste() {
+        let mut buf = sample();
+        let range = buf.paste(&Register::linewise("x"), false, 2);
+        assert_eq!(lines(&buf), vec![ "foo bar", "x", "x", "baz qux", "quux" ]);
+        assert_eq!(range, (Cursor::new(7, 0), Cursor::new(1, 2)));
+        assert_eq!(buf.cursor, Cursor::new(0, 1));
+
+        let range = buf.paste(&Register::linewise("y"), true, 1);
+        assert_eq!(lines(&buf)[1..3], [ "y", "x" ]);
+        assert_eq!(range, (Cursor::new(0, 1), Cursor::new(0, 2)));
+
+        // charwise text is pasted after the cursor, which ends up on its last character
+        buf.move_down_many(3);
+        let range = buf.paste(&Register::charwise("1\n23"), false, 1);
+        assert_eq!(lines(&buf)[4..6], [ "b1", "23az qux" ]);
+        assert_eq!(range, (Cursor::new(1, 4), Cursor::new(2, 5)));
+        assert_eq!(buf.cursor, Cursor::new(1, 5));
+
+        buf.paste(&Register::charwise("ä"), true, 2);
+        assert_eq!(lines(&buf)[5], "2ää3az qux");
     }
 
 }
@@ -0,0 +1,257 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::env;
+
+
+
+/// The system clipboard, which `"+` and `"*` yank to and paste from.
+pub trait ClipboardProvider: fmt::Debug {
+    fn name(&self) -> &str;
+    fn get(&mut self) -> io::Result<String>;
+    fn set(&mut self, text: &str) -> io::Result<()>;
+}
+
+/// Whether yanking into or pasting from `register` goes through the system clipboard.
+#[must_use]
+pub fn is_clipboard_register(register: char) -> bool {
+    matches!(register, '+' | '*')
+}
+
+/// Picks the clipboard of the session: the tools of Wayland or X11 if installed,
+/// otherwise OSC 52 in a `terminal`, which can only be copied to.
+#[must_use]
+pub fn detect(terminal: bool) -> Box<dyn ClipboardProvider> {
+    let session = |var| env::var_os(var).is_some_and(|value| !value.is_empty());
+
+    if session("WAYLAND_DISPLAY") && in_path("wl-copy") && in_path("wl-paste") {
+        Box::new(External::wl_copy())
+    } else if session("DISPLAY") && in_path("xclip") {
+        Box::new(External::xclip())
+    } else if terminal {
+        Box::new(Osc52::stdout())
+    } else {
+        Box::new(Memory::default())
+    }
+}
+
+/// The provider called `name`, as in the `clipboard` option.
+#[must_use]
+pub fn by_name(name: &str) -> Option<Box<dyn ClipboardProvider>> {
+    Some(match name {
+        "xclip"   => Box::new(External::xclip()),
+        "wl-copy" => Box::new(External::wl_copy()),
+        "osc52"   => Box::new(Osc52::stdout()),
+        "memory"  => Box::new(Memory::default()),
+        _         => return None,
+    })
+}
+
+fn in_path(program: &str) -> bool {
+    env::var_os("PATH")
+        .is_some_and(|path| env::split_paths(&path).any(|dir| dir.join(program).is_file()))
+}
+
+/// Keeps the text within the editor, if there is no system clipboard.
+#[derive(Debug, Default)]
+pub struct Memory {
+    text: String,
+}
+
+impl ClipboardProvider for Memory {
+    fn name(&self) -> &str {
+        "memory"
+    }
+
+    fn get(&mut self) -> io::Result<String> {
+        Ok(self.text.clone())
+    }
+
+    fn set(&mut self, text: &str) -> io::Result<()> {
+        self.text = text.to_string();
+        Ok(())
+    }
+}
+
+/// Runs a command with the text on stdin to copy, and one printing it to paste.
+#[derive(Debug, Clone)]
+pub struct External {
+    name: String,
+    copy: Vec<String>,
+    paste: Vec<String>,
+}
+
+impl External {
+
+    /// `copy` and `paste` are a program followed by its arguments.
+    #[must_use]
+    pub fn new(name: &str, copy: &[&str], paste: &[&str]) -> Self {
+        let args = |cmd: &[&str]| cmd.iter().map(|arg| arg.to_string()).collect();
+        Self { name: name.to_string(), copy: args(copy), paste: args(paste) }
+    }
+
+    #[must_use]
+    pub fn xclip() -> Self {
+        Self::new(
+            "xclip",
+            &[ "xclip", "-selection", "clipboard", "-in" ],
+            &[ "xclip", "-selection", "clipboard", "-out" ],
+        )
+    }
+
+    #[must_use]
+    pub fn wl_copy() -> Self {
+        Self::new("wl-copy", &[ "wl-copy" ], &[ "wl-paste", "--no-newline" ])
+    }
+
+    fn command(args: &[String]) -> io::Result<Command> {
+        let (program, args) = args
+            .split_first()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "empty clipboard command"))?;
+        let mut cmd = Command::new(program);
+        cmd.args(args);
+        Ok(cmd)
+    }
+
+    fn check(&self, status: std::process::ExitStatus) -> io::Result<()> {
+        if status.success() {
+            Ok(())
+        } else {
+            Err(io::Error::other(format!("{} failed with {status}", self.name)))
+        }
+    }
+
+}
+
+impl ClipboardProvider for External {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn get(&mut self) -> io::Result<String> {
+        let output = Self::command(&self.paste)?.stdin(Stdio::null()).stderr(Stdio::null()).output()?;
+        self.check(output.status)?;
+        String::from_utf8(output.stdout).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    fn set(&mut self, text: &str) -> io::Result<()> {
+        // the tools keep running in the background to serve the selection,
+        // which must not hold on to our pipes
+        let mut child = Self::command(&self.copy)?
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        let written = child.stdin.take().unwrap().write_all(text.as_bytes());
+        let status = child.wait()?;
+        written?;
+        self.check(status)
+    }
+}
+
+/// Copies through the terminal with the OSC 52 escape sequence, which works over ssh.
+/// Terminals rarely allow reading the clipboard, so pasting gives what was last copied.
+pub struct Osc52 {
+    out: Box<dyn Write>,
+    text: String,
+}
+
+impl fmt::Debug for Osc52 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Osc52").field("text", &self.text).finish_non_exhaustive()
+    }
+}
+
+impl Osc52 {
+
+    #[must_use]
+    pub fn new(out: impl Write + 'static) -> Self {
+        Self { out: Box::new(out), text: String::new() }
+    }
+
+    #[must_use]
+    pub fn stdout() -> Self {
+        Self::new(io::stdout())
+    }
+
+}
+
+impl ClipboardProvider for Osc52 {
+    fn name(&self) -> &str {
+        "osc52"
+    }
+
+    fn get(&mut self) -> io::Result<String> {
+        Ok(self.text.clone())
+    }
+
+    fn set(&mut self, text: &str) -> io::Result<()> {
+        self.out.write_all(osc52(text).as_bytes())?;
+        self.out.flush()?;
+        self.text = text.to_string();
+        Ok(())
+    }
+}
+
+/// The sequence setting the clipboard to `text`
+fn osc52(text: &str) -> String {
+    format!("\x1b]52;c;{}\x07", base64(text.as_bytes()))
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clipboard_osc52() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(osc52("ä\n"), "\x1b]52;c;w6QK\x07");
+
+        let mut clipboard = Osc52::new(io::sink());
+        clipboard.set("foo").unwrap();
+        assert_eq!(clipboard.get().unwrap(), "foo");
+    }
+
+    #[test]
+    fn test_clipboard_external() {
+        let path = env::temp_dir().join(format!("ace-clipboard-{}", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        // a fake provider, which keeps the clipboard in a file
+        let mut clipboard = External::new(
+            "fake",
+            &[ "sh", "-c", "cat > \"$0\"", path ],
+            &[ "cat", path ],
+        );
+        clipboard.set("foo\nbär").unwrap();
+        assert_eq!(clipboard.get().unwrap(), "foo\nbär");
+        std::fs::remove_file(path).unwrap();
+
+        assert!(clipboard.get().is_err());
+        assert!(External::new("empty", &[], &[]).get().is_err());
+        assert!(by_name("memory").is_some() && by_name("clippy").is_none());
+    }
+
+}
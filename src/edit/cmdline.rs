@@ -0,0 +1,373 @@
+/// What the line is typed for, which it is prefixed with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Prompt {
+    #[default] Command,
+    Search,
+    SearchBackward,
+}
+
+impl Prompt {
+    #[must_use]
+    pub fn symbol(&self) -> char {
+        match self {
+            Self::Command        => ':',
+            Self::Search         => '/',
+            Self::SearchBackward => '?',
+        }
+    }
+
+    #[must_use]
+    pub fn is_search(&self) -> bool {
+        matches!(self, Self::Search | Self::SearchBackward)
+    }
+}
+
+/// The line that commands and searches are typed into in [`Mode::Command`].
+///
+/// [`Mode::Command`]: super::Mode::Command
+#[derive(Debug, Clone, Default)]
+pub struct Cmdline {
+    text: String,
+    /// byte offset into `text`
+    cursor: usize,
+    prompt: Prompt,
+    history: Vec<String>,
+    /// searches are remembered apart from commands
+    search_history: Vec<String>,
+    /// index into `history` while browsing it
+    browsing: Option<usize>,
+    /// the line that was being typed before browsing the history
+    stash: String,
+    completion: Option<Completion>,
+    /// output of the last command, shown until the next action
+    message: Option<String>,
+    /// whether the line or the message changed since they have last been drawn
+    dirty: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Completion {
+    /// byte offset of the word being completed
+    start: usize,
+    /// the word as typed, which is cycled back to after the last candidate
+    typed: String,
+    candidates: Vec<String>,
+    /// index into `candidates`, `candidates.len()` being the typed word
+    index: usize,
+}
+
+impl Cmdline {
+
+    #[must_use]
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub fn clean(&mut self) {
+        self.dirty = false;
+    }
+
+    #[must_use]
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns the cursor as a byte offset into [`Cmdline::text`].
+    #[must_use]
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    #[must_use]
+    pub fn prompt(&self) -> Prompt {
+        self.prompt
+    }
+
+    /// The history of the current prompt
+    #[must_use]
+    pub fn history(&self) -> &[String] {
+        if self.prompt.is_search() { &self.search_history } else { &self.history }
+    }
+
+    fn history_mut(&mut self) -> &mut Vec<String> {
+        if self.prompt.is_search() { &mut self.search_history } else { &mut self.history }
+    }
+
+    #[must_use]
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    pub fn set_message(&mut self, msg: impl Into<String>) {
+        self.dirty = true;
+        self.message = Some(msg.into());
+    }
+
+    pub fn clear_message(&mut self) {
+        if self.message.take().is_some() {
+            self.dirty = true;
+        }
+    }
+
+    /// Empties the line to type into it after `prompt`.
+    pub fn open(&mut self, prompt: Prompt) {
+        self.dirty = true;
+        self.prompt = prompt;
+        self.clear();
+    }
+
+    /// Empties the line, as done when entering or leaving command mode.
+    pub fn clear(&mut self) {
+        self.set_text(String::new());
+        self.browsing = None;
+        self.stash.clear();
+    }
+
+    /// Empties the line and returns it, remembering it in the history.
+    pub fn submit(&mut self) -> String {
+        let line = std::mem::take(&mut self.text);
+        self.clear();
+
+        let history = self.history_mut();
+        if !line.trim().is_empty() && history.last() != Some(&line) {
+            history.push(line.clone());
+        }
+        line
+    }
+
+    pub fn insert_char(&mut self, c: char) {
+        self.edited();
+        self.text.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+    }
+
+    /// Deletes the char before the cursor. Returns false if there is none.
+    pub fn backspace(&mut self) -> bool {
+        let Some(prev) = self.prev_boundary() else { return false };
+        self.edited();
+        self.text.replace_range(prev..self.cursor, "");
+        self.cursor = prev;
+        true
+    }
+
+    /// Deletes the char under the cursor.
+    pub fn delete(&mut self) {
+        let Some(next) = self.next_boundary() else { return };
+        self.edited();
+        self.text.replace_range(self.cursor..next, "");
+    }
+
+    /// Deletes everything before the cursor.
+    pub fn clear_before(&mut self) {
+        self.edited();
+        self.text.replace_range(..self.cursor, "");
+        self.cursor = 0;
+    }
+
+    pub fn move_left(&mut self) {
+        self.dirty = true;
+        self.completion = None;
+        if let Some(prev) = self.prev_boundary() {
+            self.cursor = prev;
+        }
+    }
+
+    pub fn move_right(&mut self) {
+        self.dirty = true;
+        self.completion = None;
+        if let Some(next) = self.next_boundary() {
+            self.cursor = next;
+        }
+    }
+
+    pub fn move_start(&mut self) {
+        self.dirty = true;
+        self.completion = None;
+        self.cursor = 0;
+    }
+
+    pub fn move_end(&mut self) {
+        self.dirty = true;
+        self.completion = None;
+        self.cursor = self.text.len();
+    }
+
+    /// Replaces the line with the previous entry of the history.
+    pub fn history_prev(&mut self) {
+        let index = self.browsing.unwrap_or(self.history().len());
+        if index == 0 {
+            return;
+        }
+
+        if self.browsing.is_none() {
+            self.stash = self.text.clone();
+        }
+        self.browsing = Some(index - 1);
+        self.set_text(self.history()[index - 1].clone());
+    }
+
+    /// Replaces the line with the next entry of the history,
+    /// or with what has been typed before browsing it.
+    pub fn history_next(&mut self) {
+        let Some(index) = self.browsing else { return };
+
+        if index + 1 < self.history().len() {
+            self.browsing = Some(index + 1);
+            self.set_text(self.history()[index + 1].clone());
+        } else {
+            self.browsing = None;
+            let stash = std::mem::take(&mut self.stash);
+            self.set_text(stash);
+        }
+    }
+
+    /// Completes the word before the cursor. `candidates` receives the line up to
+    /// the cursor and returns the replacements for its last word.
+    /// Completing again cycles through the candidates, and back to the typed word.
+    pub fn complete(&mut self, candidates: impl FnOnce(&str) -> Vec<String>) {
+
+        if let Some(completion) = &mut self.completion {
+            completion.index = (completion.index + 1) % (completion.candidates.len() + 1);
+            let word = completion.candidates.get(completion.index).unwrap_or(&completion.typed).clone();
+            let start = completion.start;
+            self.replace_word(start, &word);
+            return;
+        }
+
+        let before = &self.text[..self.cursor];
+        let start = before
+            .char_indices()
+            .rfind(|(_, c)| c.is_whitespace())
+            .map_or(0, |(i, c)| i + c.len_utf8());
+
+        let candidates = candidates(before);
+        let Some(first) = candidates.first().cloned() else { return };
+
+        self.completion = Some(Completion {
+            start,
+            typed: before[start..].to_string(),
+            candidates,
+            index: 0,
+        });
+        self.replace_word(start, &first);
+
+    }
+
+    fn replace_word(&mut self, start: usize, word: &str) {
+        self.dirty = true;
+        self.text.replace_range(start..self.cursor, word);
+        self.cursor = start + word.len();
+    }
+
+    fn set_text(&mut self, text: String) {
+        self.dirty = true;
+        self.text = text;
+        self.cursor = self.text.len();
+        self.completion = None;
+    }
+
+    /// Any edit ends the current completion.
+    fn edited(&mut self) {
+        self.dirty = true;
+        self.completion = None;
+    }
+
+    fn prev_boundary(&self) -> Option<usize> {
+        self.text[..self.cursor].char_indices().next_back().map(|(i, _)| i)
+    }
+
+    fn next_boundary(&self) -> Option<usize> {
+        self.text[self.cursor..].chars().next().map(|c| self.cursor + c.len_utf8())
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn typed(text: &str) -> Cmdline {
+        let mut cmdline = Cmdline::default();
+        text.chars().for_each(|c| cmdline.insert_char(c));
+        cmdline
+    }
+
+    #[test]
+    fn test_cmdline_editing() {
+        let mut cmdline = typed("wä");
+        assert_eq!(cmdline.cursor(), 3);
+
+        cmdline.move_left();
+        cmdline.insert_char('q');
+        assert_eq!(cmdline.text(), "wqä");
+
+        cmdline.delete();
+        assert_eq!(cmdline.text(), "wq");
+
+        cmdline.move_start();
+        assert!(!cmdline.backspace());
+        cmdline.move_end();
+        assert!(cmdline.backspace());
+        assert_eq!(cmdline.text(), "w");
+
+        cmdline.clear_before();
+        assert_eq!(cmdline.text(), "");
+    }
+
+    #[test]
+    fn test_cmdline_history() {
+        let mut cmdline = typed("w");
+        assert_eq!(cmdline.submit(), "w");
+        "q".chars().for_each(|c| cmdline.insert_char(c));
+        cmdline.submit();
+        // blank lines and repetitions are not remembered
+        cmdline.submit();
+        "q".chars().for_each(|c| cmdline.insert_char(c));
+        cmdline.submit();
+        assert_eq!(cmdline.history(), ["w", "q"]);
+
+        cmdline.insert_char('e');
+        cmdline.history_prev();
+        assert_eq!(cmdline.text(), "q");
+        cmdline.history_prev();
+        cmdline.history_prev();
+        assert_eq!(cmdline.text(), "w");
+
+        cmdline.history_next();
+        assert_eq!(cmdline.text(), "q");
+        cmdline.history_next();
+        assert_eq!(cmdline.text(), "e");
+
+        // searches have a history of their own
+        cmdline.open(Prompt::Search);
+        "foo".chars().for_each(|c| cmdline.insert_char(c));
+        cmdline.submit();
+        cmdline.history_prev();
+        assert_eq!(cmdline.text(), "foo");
+        cmdline.open(Prompt::Command);
+        assert_eq!(cmdline.history(), ["w", "q"]);
+    }
+
+    #[test]
+    fn test_cmdline_complete() {
+        let candidates = |before: &str| {
+            assert_eq!(before, "set f");
+            vec![ "fontsize".to_string(), "foo".to_string() ]
+        };
+
+        let mut cmdline = typed("set f");
+        cmdline.complete(candidates);
+        assert_eq!(cmdline.text(), "set fontsize");
+        cmdline.complete(|_| unreachable!());
+        assert_eq!(cmdline.text(), "set foo");
+        cmdline.complete(|_| unreachable!());
+        assert_eq!(cmdline.text(), "set f");
+
+        // editing starts a new completion
+        cmdline.insert_char('o');
+        cmdline.complete(|before| vec![ format!("{}o", &before[4..]) ]);
+        assert_eq!(cmdline.text(), "set foo");
+        assert_eq!(cmdline.cursor(), 7);
+    }
+
+}
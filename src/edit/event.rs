@@ -1,4 +1,5 @@
 use super::{BufferID, WindowID, Mode};
+use super::buffer::Cursor;
 
 /// This gets passed to the subscribers of the event.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -7,22 +8,69 @@ pub enum EventData {
     Deinit,
     WinNew(WindowID),
     WinDel(WindowID),
+    /// the window gained focus
+    WinEnter(WindowID),
+    /// the window lost focus
+    WinLeave(WindowID),
     BufNew(BufferID),
     BufDel(BufferID),
-    ModeChanged(Mode),
+    /// the buffer is now shown in the focused window
+    BufEnter(BufferID),
+    /// the buffer is no longer shown in the focused window
+    BufLeave(BufferID),
+    /// the buffer is about to be loaded from its file
+    BufReadPre(BufferID),
+    BufReadPost(BufferID),
+    /// the buffer is about to be written, its autocmds still run before the write
+    BufWritePre(BufferID),
+    BufWritePost(BufferID),
+    /// the lines from `start` up to `end` (exclusive) have changed,
+    /// lines after them may have been shifted
+    TextChanged { buf: BufferID, start: usize, end: usize },
+    /// the cursor of the focused buffer has moved, once per typed key or action
+    CursorMoved { buf: BufferID, cursor: Cursor },
+    ModeChanged { old: Mode, new: Mode },
 }
 
 // TODO: procmacro for this madness
 impl EventData {
+    /// The buffer the event is about, if any
+    #[must_use]
+    pub fn buffer(&self) -> Option<BufferID> {
+        match *self {
+            Self::BufNew(id)
+            | Self::BufDel(id)
+            | Self::BufEnter(id)
+            | Self::BufLeave(id)
+            | Self::BufReadPre(id)
+            | Self::BufReadPost(id)
+            | Self::BufWritePre(id)
+            | Self::BufWritePost(id)
+            | Self::TextChanged { buf: id, .. }
+            | Self::CursorMoved { buf: id, .. } => Some(id),
+            _ => None,
+        }
+    }
+
     pub fn base(&self) -> Event {
         match self {
-            Self::Init           => Event::Init,
-            Self::Deinit         => Event::Deinit,
-            Self::WinNew(_)      => Event::WinNew,
-            Self::WinDel(_)      => Event::WinDel,
-            Self::BufNew(_)      => Event::BufNew,
-            Self::BufDel(_)      => Event::BufDel,
-            Self::ModeChanged(_) => Event::ModeChanged,
+            Self::Init              => Event::Init,
+            Self::Deinit            => Event::Deinit,
+            Self::WinNew(_)         => Event::WinNew,
+            Self::WinDel(_)         => Event::WinDel,
+            Self::WinEnter(_)       => Event::WinEnter,
+            Self::WinLeave(_)       => Event::WinLeave,
+            Self::BufNew(_)         => Event::BufNew,
+            Self::BufDel(_)         => Event::BufDel,
+            Self::BufEnter(_)       => Event::BufEnter,
+            Self::BufLeave(_)       => Event::BufLeave,
+            Self::BufReadPre(_)     => Event::BufReadPre,
+            Self::BufReadPost(_)    => Event::BufReadPost,
+            Self::BufWritePre(_)    => Event::BufWritePre,
+            Self::BufWritePost(_)   => Event::BufWritePost,
+            Self::TextChanged { .. } => Event::TextChanged,
+            Self::CursorMoved { .. } => Event::CursorMoved,
+            Self::ModeChanged { .. } => Event::ModeChanged,
         }
     }
 }
@@ -34,7 +82,17 @@ pub enum Event {
     Deinit,
     WinNew,
     WinDel,
+    WinEnter,
+    WinLeave,
     BufNew,
     BufDel,
+    BufEnter,
+    BufLeave,
+    BufReadPre,
+    BufReadPost,
+    BufWritePre,
+    BufWritePost,
+    TextChanged,
+    CursorMoved,
     ModeChanged,
 }
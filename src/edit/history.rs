@@ -0,0 +1,97 @@
+use super::buffer::Cursor;
+
+
+
+/// A single reversible modification of the text of a [`Buffer`].
+/// Columns of the positions are byte offsets into the line.
+///
+/// [`Buffer`]: super::buffer::Buffer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// `text` was inserted at `pos`
+    Insert { pos: Cursor, text: String },
+    /// `text` was removed, starting at `pos`
+    Remove { pos: Cursor, text: String },
+}
+
+impl Change {
+    /// Returns the change that reverts this one.
+    #[must_use]
+    pub fn inverse(&self) -> Self {
+        match self.clone() {
+            Self::Insert { pos, text } => Self::Remove { pos, text },
+            Self::Remove { pos, text } => Self::Insert { pos, text },
+        }
+    }
+}
+
+/// A group of changes which is undone/redone as a whole.
+#[derive(Debug, Clone)]
+pub struct UndoStep {
+    pub changes: Vec<Change>,
+    /// cursor position before the first change was made
+    pub cursor_before: Cursor,
+    /// cursor position when the step was committed
+    pub cursor_after: Cursor,
+}
+
+/// Linear undo/redo history.
+///
+/// Changes are collected until [`History::commit`] is called,
+/// which turns them into a single [`UndoStep`].
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    undo: Vec<UndoStep>,
+    redo: Vec<UndoStep>,
+    pending: Vec<Change>,
+    cursor_before: Cursor,
+}
+
+impl History {
+
+    /// Records a change. `cursor` is the cursor position before the change was applied.
+    pub fn record(&mut self, change: Change, cursor: Cursor) {
+        if self.pending.is_empty() {
+            self.cursor_before = cursor;
+        }
+        self.pending.push(change);
+        // making a new change invalidates everything that has been undone
+        self.redo.clear();
+    }
+
+    /// Groups all pending changes into a single undo step.
+    pub fn commit(&mut self, cursor: Cursor) {
+        if self.pending.is_empty() { return }
+
+        self.undo.push(UndoStep {
+            changes:       std::mem::take(&mut self.pending),
+            cursor_before: self.cursor_before,
+            cursor_after:  cursor,
+        });
+    }
+
+    /// Takes the most recent step, moving it onto the redo stack.
+    pub fn undo(&mut self) -> Option<UndoStep> {
+        let step = self.undo.pop()?;
+        self.redo.push(step.clone());
+        Some(step)
+    }
+
+    /// Takes the most recently undone step, moving it back onto the undo stack.
+    pub fn redo(&mut self) -> Option<UndoStep> {
+        let step = self.redo.pop()?;
+        self.undo.push(step.clone());
+        Some(step)
+    }
+
+    #[must_use]
+    pub fn undo_count(&self) -> usize {
+        self.undo.len()
+    }
+
+    #[must_use]
+    pub fn redo_count(&self) -> usize {
+        self.redo.len()
+    }
+
+}
@@ -0,0 +1,671 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::buffer::{Buffer, Cursor};
+use super::text;
+
+
+
+/// Operations which are applied to the text covered by a [`Motion`] or [`TextObject`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operator {
+    Delete,
+    Change,
+    Yank,
+    Indent,
+    Dedent,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Motion {
+    Left,
+    Right,
+    Up,
+    Down,
+    /// `w`
+    WordForward,
+    /// `e`
+    WordEnd,
+    /// `b`
+    WordBackward,
+    /// `0`
+    LineStart,
+    /// `$`
+    LineEnd,
+    /// `gg`
+    FileStart,
+    /// `G`
+    FileEnd,
+    /// `}`
+    ParagraphForward,
+    /// `{`
+    ParagraphBackward,
+}
+
+impl Motion {
+
+    /// Linewise motions always operate on whole lines.
+    #[must_use]
+    pub fn is_linewise(&self) -> bool {
+        matches!(self, Self::Up | Self::Down | Self::FileStart | Self::FileEnd)
+    }
+
+    /// Inclusive motions include the char at their target.
+    #[must_use]
+    pub fn is_inclusive(&self) -> bool {
+        matches!(self, Self::WordEnd | Self::LineEnd)
+    }
+
+}
+
+/// Whether a [`TextObject`] includes its surroundings (`a`) or not (`i`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Scope {
+    Inner,
+    Around,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TextObject {
+    /// `iw`, `aw`
+    Word,
+    /// `ip`, `ap`
+    Paragraph,
+    /// `i(`, `a[`, ...
+    Pair(char, char),
+    /// `i"`, `a'`, ...
+    Quote(char),
+}
+
+/// A region of text. Columns are grapheme indices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextRange {
+    pub start: Cursor,
+    /// exclusive, unless the range is linewise
+    pub end: Cursor,
+    /// covers the whole lines from `start` to `end`
+    pub linewise: bool,
+}
+
+impl TextRange {
+
+    pub fn new(start: Cursor, end: Cursor) -> Self {
+        Self { start, end, linewise: false }
+    }
+
+    pub fn lines(start: isize, end: isize) -> Self {
+        Self {
+            start: Cursor::new(0, start),
+            end: Cursor::new(0, end),
+            linewise: true,
+        }
+    }
+
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+    Blank,
+    Word,
+    Punct,
+}
+
+fn class_of(grapheme: &str) -> CharClass {
+    match grapheme.chars().next() {
+        Some(c) if c.is_whitespace()               => CharClass::Blank,
+        Some(c) if c.is_alphanumeric() || c == '_' => CharClass::Word,
+        Some(_)                                    => CharClass::Punct,
+        None                                       => CharClass::Blank,
+    }
+}
+
+/// Walks over the graphemes of a buffer, caching the graphemes of the current line.
+///
+/// The position right after the last grapheme of a line stands for
+/// the newline, and is treated as whitespace.
+struct Scanner<'a> {
+    buf: &'a Buffer,
+    last: isize,
+    y: isize,
+    line: Vec<String>,
+}
+
+impl<'a> Scanner<'a> {
+
+    fn new(buf: &'a Buffer) -> Self {
+        Self {
+            buf,
+            last: buf.getlines().len() as isize - 1,
+            y: -1,
+            line: Vec::new(),
+        }
+    }
+
+    fn load(&mut self, y: isize) {
+        if y == self.y { return }
+
+        self.y = y;
+        self.line = self.buf
+            .getlines()
+            .nth(y as usize)
+            .unwrap()
+            .graphemes(true)
+            .map(str::to_string)
+            .collect();
+    }
+
+    fn width(&mut self, y: isize) -> isize {
+        self.load(y);
+        self.line.len() as isize
+    }
+
+    fn grapheme(&mut self, pos: Cursor) -> Option<&str> {
+        self.load(pos.y);
+        self.line.get(pos.x as usize).map(String::as_str)
+    }
+
+    fn class(&mut self, pos: Cursor) -> CharClass {
+        self.grapheme(pos).map_or(CharClass::Blank, class_of)
+    }
+
+    fn is_empty_line(&mut self, y: isize) -> bool {
+        self.width(y) == 0
+    }
+
+    fn next(&mut self, pos: Cursor) -> Option<Cursor> {
+        if pos.x < self.width(pos.y) {
+            Some(Cursor::new(pos.x + 1, pos.y))
+        } else if pos.y < self.last {
+            Some(Cursor::new(0, pos.y + 1))
+        } else {
+            None
+        }
+    }
+
+    fn prev(&mut self, pos: Cursor) -> Option<Cursor> {
+        if pos.x > 0 {
+            Some(Cursor::new(pos.x.min(self.width(pos.y)) - 1, pos.y))
+        } else if pos.y > 0 {
+            Some(Cursor::new(self.width(pos.y - 1), pos.y - 1))
+        } else {
+            None
+        }
+    }
+
+    fn repeat(&mut self, count: isize, start: Cursor, motion: fn(&mut Self, Cursor) -> Cursor) -> Cursor {
+        (0..count).fold(start, |pos, _| motion(self, pos))
+    }
+
+    fn word_forward(&mut self, start: Cursor) -> Cursor {
+        let mut pos = start;
+        let class = self.class(pos);
+
+        // leave the current word
+        if class != CharClass::Blank {
+            while self.class(pos) == class {
+                let Some(next) = self.next(pos) else { return pos };
+                pos = next;
+            }
+        }
+
+        // skip whitespace, but stop at empty lines
+        while self.class(pos) == CharClass::Blank {
+            if pos != start && pos.x == 0 && self.is_empty_line(pos.y) { break }
+            let Some(next) = self.next(pos) else { return pos };
+            pos = next;
+        }
+
+        pos
+    }
+
+    fn word_end(&mut self, start: Cursor) -> Cursor {
+        let Some(mut pos) = self.next(start) else { return start };
+
+        while self.class(pos) == CharClass::Blank {
+            let Some(next) = self.next(pos) else { return pos };
+            pos = next;
+        }
+
+        let class = self.class(pos);
+        while let Some(next) = self.next(pos) && self.class(next) == class {
+            pos = next;
+        }
+
+        pos
+    }
+
+    fn word_backward(&mut self, start: Cursor) -> Cursor {
+        let Some(mut pos) = self.prev(start) else { return start };
+
+        while self.class(pos) == CharClass::Blank {
+            if pos.x == 0 && self.is_empty_line(pos.y) { return pos }
+            let Some(prev) = self.prev(pos) else { return pos };
+            pos = prev;
+        }
+
+        let class = self.class(pos);
+        while let Some(prev) = self.prev(pos) && self.class(prev) == class {
+            pos = prev;
+        }
+
+        pos
+    }
+
+    fn paragraph_forward(&mut self, start: Cursor) -> Cursor {
+        let mut y = start.y;
+
+        while y < self.last && self.is_empty_line(y) { y += 1 }
+        while y < self.last && !self.is_empty_line(y) { y += 1 }
+
+        if self.is_empty_line(y) {
+            Cursor::new(0, y)
+        } else {
+            Cursor::new(self.width(y), y)
+        }
+    }
+
+    fn paragraph_backward(&mut self, start: Cursor) -> Cursor {
+        let mut y = start.y;
+
+        while y > 0 && self.is_empty_line(y) { y -= 1 }
+        while y > 0 && !self.is_empty_line(y) { y -= 1 }
+
+        Cursor::new(0, y)
+    }
+
+    /// Range of the run of graphemes of the same class around the cursor, on the current line.
+    fn word(&mut self, pos: Cursor, scope: Scope) -> TextRange {
+        let width = self.width(pos.y);
+        if width == 0 {
+            return TextRange::new(pos, pos);
+        }
+
+        let x = pos.x.min(width - 1);
+        let run = |this: &mut Self, x: isize| {
+            let class = this.class(Cursor::new(x, pos.y));
+            let mut start = x;
+            let mut end = x + 1;
+            while start > 0 && this.class(Cursor::new(start - 1, pos.y)) == class { start -= 1 }
+            while end < width && this.class(Cursor::new(end, pos.y)) == class { end += 1 }
+            (start, end, class)
+        };
+
+        let (mut start, mut end, class) = run(self, x);
+
+        if scope == Scope::Around {
+            if class == CharClass::Blank {
+                // whitespace, followed by a word
+                if end < width {
+                    end = run(self, end).1;
+                }
+            } else if end < width && self.class(Cursor::new(end, pos.y)) == CharClass::Blank {
+                // trailing whitespace
+                end = run(self, end).1;
+            } else if start > 0 && self.class(Cursor::new(start - 1, pos.y)) == CharClass::Blank {
+                // leading whitespace, if there is no trailing whitespace
+                start = run(self, start - 1).0;
+            }
+        }
+
+        TextRange::new(Cursor::new(start, pos.y), Cursor::new(end, pos.y))
+    }
+
+    fn paragraph(&mut self, pos: Cursor, scope: Scope) -> TextRange {
+        let empty = self.is_empty_line(pos.y);
+
+        let mut start = pos.y;
+        let mut end = pos.y;
+        while start > 0 && self.is_empty_line(start - 1) == empty { start -= 1 }
+        while end < self.last && self.is_empty_line(end + 1) == empty { end += 1 }
+
+        if scope == Scope::Around {
+            while end < self.last && self.is_empty_line(end + 1) != empty { end += 1 }
+        }
+
+        TextRange::lines(start, end)
+    }
+
+    fn pair(&mut self, pos: Cursor, open: char, close: char, scope: Scope) -> Option<TextRange> {
+        let is = |this: &mut Self, pos: Cursor, c: char| {
+            this.grapheme(pos).is_some_and(|g| g.starts_with(c) && g.chars().count() == 1)
+        };
+
+        // find the unmatched opening delimiter before the cursor
+        let mut depth = 0;
+        let mut start = pos;
+        loop {
+            if is(self, start, close) && start != pos {
+                depth += 1;
+            } else if is(self, start, open) {
+                if depth == 0 { break }
+                depth -= 1;
+            }
+            start = self.prev(start)?;
+        }
+
+        // and its matching closing delimiter
+        let mut depth = 0;
+        let mut end = self.next(start)?;
+        loop {
+            if is(self, end, open) {
+                depth += 1;
+            } else if is(self, end, close) {
+                if depth == 0 { break }
+                depth -= 1;
+            }
+            end = self.next(end)?;
+        }
+
+        Some(match scope {
+            Scope::Inner  => TextRange::new(self.next(start)?, end),
+            Scope::Around => TextRange::new(start, self.next(end)?),
+        })
+    }
+
+    fn quote(&mut self, pos: Cursor, quote: char, scope: Scope) -> Option<TextRange> {
+        let width = self.width(pos.y);
+
+        let mut quotes = Vec::new();
+        let mut escaped = false;
+        for x in 0..width {
+            let g = self.grapheme(Cursor::new(x, pos.y)).unwrap();
+            if !escaped && g.len() == quote.len_utf8() && g.starts_with(quote) {
+                quotes.push(x);
+            }
+            escaped = !escaped && g == "\\";
+        }
+
+        // quotes are paired up from the start of the line. if the cursor is
+        // not inside of any pair, the first pair after the cursor is used
+        let (open, close) = quotes
+            .chunks_exact(2)
+            .map(|pair| (pair[0], pair[1]))
+            .find(|(_, close)| pos.x <= *close)?;
+
+        Some(match scope {
+            Scope::Inner => TextRange::new(
+                Cursor::new(open + 1, pos.y),
+                Cursor::new(close, pos.y),
+            ),
+            Scope::Around => {
+                // include trailing whitespace
+                let mut end = close + 1;
+                while end < width && self.class(Cursor::new(end, pos.y)) == CharClass::Blank {
+                    end += 1;
+                }
+                TextRange::new(Cursor::new(open, pos.y), Cursor::new(end, pos.y))
+            }
+        })
+    }
+
+}
+
+impl Buffer {
+
+    /// Returns the position the cursor would move to, when moving `count` times.
+    /// `gg` and `G` move to the line `count` instead.
+    #[must_use]
+    pub fn motion_target(&self, motion: Motion, count: Option<usize>) -> Cursor {
+        let mut scan = Scanner::new(self);
+        let cur = self.cursor();
+        let n = count.unwrap_or(1) as isize;
+        let line = count.map(|c| (c as isize - 1).clamp(0, scan.last));
+
+        match motion {
+            Motion::Left              => Cursor::new((cur.x - n).max(0), cur.y),
+            Motion::Right             => Cursor::new((cur.x + n).min(scan.width(cur.y)), cur.y),
+            Motion::Up                => Cursor::new(cur.x, (cur.y - n).max(0)),
+            Motion::Down              => Cursor::new(cur.x, (cur.y + n).min(scan.last)),
+            Motion::WordForward       => scan.repeat(n, cur, Scanner::word_forward),
+            Motion::WordEnd           => scan.repeat(n, cur, Scanner::word_end),
+            Motion::WordBackward      => scan.repeat(n, cur, Scanner::word_backward),
+            Motion::LineStart         => Cursor::new(0, cur.y),
+            Motion::LineEnd           => {
+                let y = (cur.y + n - 1).min(scan.last);
+                Cursor::new((scan.width(y) - 1).max(0), y)
+            }
+            Motion::FileStart         => Cursor::new(0, line.unwrap_or(0)),
+            Motion::FileEnd           => Cursor::new(0, line.unwrap_or(scan.last)),
+            Motion::ParagraphForward  => scan.repeat(n, cur, Scanner::paragraph_forward),
+            Motion::ParagraphBackward => scan.repeat(n, cur, Scanner::paragraph_backward),
+        }
+    }
+
+    /// Returns the range of text an operator applies to, when combined with `motion`.
+    #[must_use]
+    pub fn motion_range(&self, motion: Motion, count: Option<usize>) -> TextRange {
+        let cur = self.cursor();
+        let target = self.motion_target(motion, count);
+
+        if motion.is_linewise() {
+            return TextRange::lines(cur.y.min(target.y), cur.y.max(target.y));
+        }
+
+        let (start, mut end) = if (target.y, target.x) < (cur.y, cur.x) {
+            (target, cur)
+        } else {
+            (cur, target)
+        };
+
+        if motion.is_inclusive() {
+            end.x += 1;
+        }
+
+        // if the last word moved over ends its line, the range ends there instead of
+        // at the first word of the next line (eg. `dw` on the last word of a line)
+        if motion == Motion::WordForward && end.y > start.y && self.only_blanks_before(end) {
+            let mut scan = Scanner::new(self);
+            end = Cursor::new(scan.width(end.y - 1), end.y - 1);
+        }
+
+        TextRange::new(start, end)
+    }
+
+    /// Returns true if the line of `pos` has nothing but whitespace before it.
+    fn only_blanks_before(&self, pos: Cursor) -> bool {
+        let line = self.line(pos.y as usize);
+        line[..text::grapheme_offset(&line, pos.x as usize)].chars().all(char::is_whitespace)
+    }
+
+    /// Returns [`None`] if there is no such object around the cursor.
+    #[must_use]
+    pub fn text_object_range(&self, object: TextObject, scope: Scope) -> Option<TextRange> {
+        let mut scan = Scanner::new(self);
+        let cur = self.cursor();
+
+        match object {
+            TextObject::Word              => Some(scan.word(cur, scope)),
+            TextObject::Paragraph         => Some(scan.paragraph(cur, scope)),
+            TextObject::Pair(open, close) => scan.pair(cur, open, close, scope),
+            TextObject::Quote(quote)      => scan.quote(cur, quote, scope),
+        }
+    }
+
+    /// Returns true if the cursor is on whitespace or an empty line.
+    #[must_use]
+    pub fn on_blank(&self) -> bool {
+        Scanner::new(self).class(self.cursor()) == CharClass::Blank
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edit::Mode;
+
+    fn buffer(lines: &[&str], cursor: (isize, isize)) -> Buffer {
+        let mut buf = Buffer::new();
+        buf.load_buffer(lines.iter().map(|l| l.to_string()).collect());
+        for _ in 0..cursor.1 { buf.move_down() }
+        for _ in 0..cursor.0 { buf.move_right() }
+        buf
+    }
+
+    fn lines(buf: &Buffer) -> Vec<String> {
+        buf.getlines().map(|l| l.into_owned()).collect()
+    }
+
+    #[test]
+    fn test_motion_words() {
+        let buf = buffer(&[ "foo.bar  baz", "", "qux" ], (0, 0));
+        assert_eq!(buf.motion_target(Motion::WordForward, None), Cursor::new(3, 0));
+        assert_eq!(buf.motion_target(Motion::WordEnd, None), Cursor::new(2, 0));
+
+        let buf = buffer(&[ "foo.bar  baz", "", "qux" ], (9, 0));
+        // stops at empty lines
+        assert_eq!(buf.motion_target(Motion::WordForward, None), Cursor::new(0, 1));
+        assert_eq!(buf.motion_target(Motion::WordEnd, None), Cursor::new(11, 0));
+        assert_eq!(buf.motion_target(Motion::WordBackward, None), Cursor::new(4, 0));
+
+        let buf = buffer(&[ "foo.bar  baz", "", "qux" ], (0, 2));
+        assert_eq!(buf.motion_target(Motion::WordBackward, None), Cursor::new(0, 1));
+    }
+
+    #[test]
+    fn test_motion_counts() {
+        let buf = buffer(&[ "foo bar baz qux", "a", "b", "c" ], (0, 0));
+        assert_eq!(buf.motion_target(Motion::WordForward, Some(3)), Cursor::new(12, 0));
+        assert_eq!(buf.motion_target(Motion::Right, Some(4)), Cursor::new(4, 0));
+        assert_eq!(buf.motion_target(Motion::Down, Some(10)), Cursor::new(0, 3));
+        assert_eq!(buf.motion_target(Motion::FileEnd, Some(2)), Cursor::new(0, 1));
+        assert_eq!(buf.motion_target(Motion::LineEnd, Some(2)), Cursor::new(0, 1));
+    }
+
+    #[test]
+    fn test_operator_counts() {
+        let mut buf = buffer(&[ "a", "b", "c", "d" ], (0, 1));
+        buf.begin_operator(Operator::Delete, Some(2));
+        buf.begin_operator(Operator::Delete, None);
+        assert_eq!(lines(&buf), vec![ "a", "d" ]);
+
+        let mut buf = buffer(&[ "one two three four five" ], (0, 0));
+        buf.begin_operator(Operator::Delete, Some(2));
+        buf.operate_motion(Motion::WordForward, Some(2));
+        assert_eq!(lines(&buf), vec![ "five" ]);
+        assert_eq!(buf.mode(), Mode::Normal);
+    }
+
+    #[test]
+    fn test_motion_paragraphs() {
+        let buf = buffer(&[ "a", "b", "", "c", "d" ], (0, 0));
+        assert_eq!(buf.motion_target(Motion::ParagraphForward, None), Cursor::new(0, 2));
+
+        let buf = buffer(&[ "a", "b", "", "c", "d" ], (0, 3));
+        assert_eq!(buf.motion_target(Motion::ParagraphForward, None), Cursor::new(1, 4));
+        assert_eq!(buf.motion_target(Motion::ParagraphBackward, None), Cursor::new(0, 2));
+    }
+
+    #[test]
+    fn test_operator_motions() {
+        let mut buf = buffer(&[ "foo bar baz" ], (4, 0));
+        buf.begin_operator(Operator::Delete, None);
+        assert_eq!(buf.mode(), Mode::OperatorPending);
+        buf.operate_motion(Motion::WordForward, None);
+        assert_eq!(lines(&buf), vec![ "foo baz" ]);
+        assert_eq!(buf.mode(), Mode::Normal);
+
+        // `cw` does not remove the trailing whitespace
+        buf.begin_operator(Operator::Change, None);
+        buf.operate_motion(Motion::WordForward, None);
+        assert_eq!(lines(&buf), vec![ "foo " ]);
+        assert_eq!(buf.mode(), Mode::Insert);
+        assert_eq!(buf.cursor(), Cursor::new(4, 0));
+
+        let mut buf = buffer(&[ "foo bar", "baz" ], (4, 0));
+        // `dw` on the last word of a line does not join lines
+        buf.begin_operator(Operator::Delete, None);
+        buf.operate_motion(Motion::WordForward, None);
+        assert_eq!(lines(&buf), vec![ "foo ", "baz" ]);
+
+        // words moved over on the next line are taken along with the line break
+        let mut buf = buffer(&[ "foo bar", "baz qux" ], (4, 0));
+        buf.begin_operator(Operator::Delete, Some(2));
+        buf.operate_motion(Motion::WordForward, None);
+        assert_eq!(lines(&buf), vec![ "foo qux" ]);
+
+        let mut buf = buffer(&[ "foo bar", "  baz" ], (4, 0));
+        buf.begin_operator(Operator::Delete, None);
+        buf.operate_motion(Motion::WordForward, None);
+        assert_eq!(lines(&buf), vec![ "foo ", "  baz" ]);
+
+        // `dyw` aborts at `y`, leaving `w` without an operator
+        let mut buf = buffer(&[ "foo bar" ], (0, 0));
+        buf.begin_operator(Operator::Delete, None);
+        buf.begin_operator(Operator::Yank, None);
+        assert_eq!(buf.mode(), Mode::Normal);
+        buf.operate_motion(Motion::WordForward, None);
+        assert_eq!(lines(&buf), vec![ "foo bar" ]);
+        assert!(buf.take_yank().is_none());
+
+        let mut buf = buffer(&[ "foo bar", "baz" ], (1, 0));
+        buf.begin_operator(Operator::Delete, None);
+        buf.operate_motion(Motion::LineEnd, None);
+        assert_eq!(lines(&buf), vec![ "f", "baz" ]);
+
+        buf.move_down();
+        buf.begin_operator(Operator::Delete, None);
+        buf.operate_motion(Motion::FileStart, None);
+        assert_eq!(lines(&buf), vec![ "" ]);
+    }
+
+    #[test]
+    fn test_operator_linewise() {
+        let mut buf = buffer(&[ "a", "b", "c" ], (0, 1));
+        buf.begin_operator(Operator::Yank, None);
+        buf.begin_operator(Operator::Yank, None);
+        assert_eq!(buf.take_yank().map(|yank| yank.register.text).as_deref(), Some("b"));
+
+        buf.begin_operator(Operator::Indent, None);
+        buf.begin_operator(Operator::Indent, None);
+        assert_eq!(lines(&buf), vec![ "a", "    b", "c" ]);
+
+        buf.begin_operator(Operator::Delete, None);
+        buf.operate_motion(Motion::Down, None);
+        assert_eq!(lines(&buf), vec![ "a" ]);
+    }
+
+    #[test]
+    fn test_text_objects() {
+        let mut buf = buffer(&[ "call(foo, \"bar baz\")  x" ], (6, 0));
+        buf.begin_operator(Operator::Yank, None);
+        buf.operate_object(TextObject::Word, Scope::Inner);
+        assert_eq!(buf.take_yank().map(|yank| yank.register.text).as_deref(), Some("foo"));
+
+        buf.begin_operator(Operator::Yank, None);
+        buf.operate_object(TextObject::Pair('(', ')'), Scope::Around);
+        assert_eq!(buf.take_yank().map(|yank| yank.register.text).as_deref(), Some("(foo, \"bar baz\")"));
+
+        let mut buf = buffer(&[ "call(foo, \"bar baz\")  x" ], (13, 0));
+        buf.begin_operator(Operator::Delete, None);
+        buf.operate_object(TextObject::Quote('"'), Scope::Inner);
+        assert_eq!(lines(&buf), vec![ "call(foo, \"\")  x" ]);
+
+        buf.begin_operator(Operator::Change, None);
+        buf.operate_object(TextObject::Pair('(', ')'), Scope::Inner);
+        assert_eq!(lines(&buf), vec![ "call()  x" ]);
+        assert_eq!(buf.cursor(), Cursor::new(5, 0));
+    }
+
+    #[test]
+    fn test_text_object_around_word() {
+        let mut buf = buffer(&[ "foo bar baz" ], (5, 0));
+        buf.begin_operator(Operator::Delete, None);
+        buf.operate_object(TextObject::Word, Scope::Around);
+        assert_eq!(lines(&buf), vec![ "foo baz" ]);
+
+        // no trailing whitespace, so leading whitespace is removed instead
+        buf.move_end_line();
+        buf.begin_operator(Operator::Delete, None);
+        buf.operate_object(TextObject::Word, Scope::Around);
+        assert_eq!(lines(&buf), vec![ "foo" ]);
+    }
+
+    #[test]
+    fn test_text_object_paragraph() {
+        let mut buf = buffer(&[ "a", "b", "", "", "c" ], (0, 1));
+        buf.begin_operator(Operator::Delete, None);
+        buf.operate_object(TextObject::Paragraph, Scope::Around);
+        assert_eq!(lines(&buf), vec![ "c" ]);
+    }
+
+}
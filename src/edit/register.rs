@@ -0,0 +1,256 @@
+use std::collections::{BTreeMap, VecDeque};
+
+
+
+/// Number of kills kept for cycling through after a paste
+const KILL_RING_SIZE: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegisterKind {
+    /// pasted into the line at the cursor
+    Charwise,
+    /// pasted as whole lines, above or below the cursor
+    Linewise,
+    /// pasted as a block, one line of text into each line starting at the cursor
+    Blockwise,
+}
+
+/// Yanked or deleted text. Linewise text does not end in a newline,
+/// and the rows of blockwise text are separated by newlines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Register {
+    pub text: String,
+    pub kind: RegisterKind,
+}
+
+impl Register {
+
+    #[must_use]
+    pub fn charwise(text: impl Into<String>) -> Self {
+        Self { text: text.into(), kind: RegisterKind::Charwise }
+    }
+
+    #[must_use]
+    pub fn linewise(text: impl Into<String>) -> Self {
+        Self { text: text.into(), kind: RegisterKind::Linewise }
+    }
+
+    #[must_use]
+    pub fn blockwise(text: impl Into<String>) -> Self {
+        Self { text: text.into(), kind: RegisterKind::Blockwise }
+    }
+
+    #[must_use]
+    pub fn is_linewise(&self) -> bool {
+        self.kind == RegisterKind::Linewise
+    }
+
+    /// Reads text from the system clipboard, which is linewise if it ends in a newline.
+    #[must_use]
+    pub fn from_clipboard(text: &str) -> Self {
+        match text.strip_suffix('\n') {
+            Some(lines) => Self::linewise(lines),
+            None        => Self::charwise(text),
+        }
+    }
+
+    /// The text for the system clipboard, where lines end in a newline.
+    /// Blocks are copied as lines, as other programs do not know about them.
+    #[must_use]
+    pub fn to_clipboard(&self) -> String {
+        match self.kind {
+            RegisterKind::Charwise => self.text.clone(),
+            RegisterKind::Linewise | RegisterKind::Blockwise => format!("{}\n", self.text),
+        }
+    }
+
+    /// Appends `other`, which starts on a new line unless both of them are charwise.
+    /// Appending to a block adds rows to it, while lines turn it into lines.
+    fn append(&mut self, other: &Register) {
+        use RegisterKind::*;
+
+        self.kind = match (self.kind, other.kind) {
+            (Charwise, Charwise)            => Charwise,
+            (Linewise, _) | (_, Linewise)   => Linewise,
+            (Blockwise, _) | (_, Blockwise) => Blockwise,
+        };
+        if self.kind != Charwise {
+            self.text.push('\n');
+        }
+        self.text.push_str(&other.text);
+    }
+
+}
+
+/// Text yanked or deleted by an operator, which the editor moves into the registers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Yank {
+    pub register: Register,
+    pub deleted: bool,
+}
+
+/// The registers shared by all buffers, like vim's:
+///
+/// - `"` is the unnamed register, holding whatever was yanked or deleted last
+/// - `a` to `z` are named, and yanking into `A` to `Z` appends to them
+/// - `0` holds the last yank and `1` to `9` the last deletions,
+///   unless the text went into a named register
+///
+/// Every yank and deletion also goes into a kill ring, whose older entries
+/// can replace the text just pasted.
+#[derive(Debug, Clone, Default)]
+pub struct Registers {
+    unnamed: Option<Register>,
+    named: BTreeMap<char, Register>,
+    yanked: Option<Register>,
+    /// newest first
+    deleted: VecDeque<Register>,
+    /// newest first
+    kills: VecDeque<Register>,
+}
+
+impl Registers {
+
+    /// Whether text can be yanked into and pasted from the register `name`.
+    /// `+` and `*` are the system clipboard, which the registers only hold a copy of.
+    #[must_use]
+    pub fn is_valid(name: char) -> bool {
+        matches!(name, '"' | '0'..='9' | 'a'..='z' | 'A'..='Z' | '+' | '*')
+    }
+
+    /// The register called `name`, or the unnamed one.
+    #[must_use]
+    pub fn get(&self, name: Option<char>) -> Option<&Register> {
+        match name {
+            None | Some('"' | '+' | '*') => self.unnamed.as_ref(),
+            Some('0')                    => self.yanked.as_ref(),
+            Some(digit @ '1'..='9')      => self.deleted.get(digit as usize - '1' as usize),
+            Some(name)                   => self.named.get(&name.to_ascii_lowercase()),
+        }
+    }
+
+    /// Stores what an operator yanked or deleted into the register `name`, or the numbered
+    /// ones if there is none. The unnamed register and the kill ring receive it either way.
+    pub fn store(&mut self, name: Option<char>, yank: Yank) {
+        let Yank { register, deleted } = yank;
+
+        let stored = match name {
+            Some(name @ 'a'..='z') => {
+                self.named.insert(name, register.clone());
+                register.clone()
+            }
+            Some(name @ 'A'..='Z') => {
+                let named = self.named
+                    .entry(name.to_ascii_lowercase())
+                    .and_modify(|named| named.append(&register))
+                    .or_insert_with(|| register.clone());
+                named.clone()
+            }
+            Some('+' | '*') => register.clone(),
+            _ if deleted => {
+                self.deleted.push_front(register.clone());
+                self.deleted.truncate(9);
+                register.clone()
+            }
+            _ => {
+                self.yanked = Some(register.clone());
+                register.clone()
+            }
+        };
+
+        self.unnamed = Some(stored);
+        self.kills.push_front(register);
+        self.kills.truncate(KILL_RING_SIZE);
+    }
+
+    /// The kill `index` steps back in the kill ring, wrapping around at its end.
+    #[must_use]
+    pub fn kill(&self, index: usize) -> Option<&Register> {
+        self.kills.get(index % self.kills.len().max(1))
+    }
+
+    /// Number of kills in the kill ring
+    #[must_use]
+    pub fn kills(&self) -> usize {
+        self.kills.len()
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn yank(text: &str) -> Yank {
+        Yank { register: Register::charwise(text), deleted: false }
+    }
+
+    fn delete(text: &str) -> Yank {
+        Yank { register: Register::linewise(text), deleted: true }
+    }
+
+    fn text(regs: &Registers, name: char) -> Option<&str> {
+        regs.get(Some(name)).map(|reg| reg.text.as_str())
+    }
+
+    #[test]
+    fn test_registers_numbered() {
+        let mut regs = Registers::default();
+        regs.store(None, yank("foo"));
+        regs.store(None, delete("bar"));
+        regs.store(None, delete("baz"));
+
+        assert_eq!(text(&regs, '"'), Some("baz"));
+        assert_eq!(text(&regs, '0'), Some("foo"));
+        assert_eq!(text(&regs, '1'), Some("baz"));
+        assert_eq!(text(&regs, '2'), Some("bar"));
+        assert_eq!(text(&regs, '3'), None);
+        assert!(regs.get(Some('1')).unwrap().is_linewise());
+
+        (0..10).for_each(|i| regs.store(None, delete(&i.to_string())));
+        assert_eq!(text(&regs, '9'), Some("1"));
+    }
+
+    #[test]
+    fn test_registers_named() {
+        let mut regs = Registers::default();
+        regs.store(Some('a'), yank("foo"));
+        regs.store(Some('A'), yank("bar"));
+        assert_eq!(text(&regs, 'a'), Some("foobar"));
+        assert_eq!(text(&regs, '"'), Some("foobar"));
+        // named registers leave the numbered ones alone
+        assert_eq!(text(&regs, '0'), None);
+
+        // appending lines starts a new line
+        regs.store(Some('A'), delete("baz"));
+        assert_eq!(regs.get(Some('a')), Some(&Register::linewise("foobar\nbaz")));
+        regs.store(Some('B'), yank("qux"));
+        assert_eq!(text(&regs, 'b'), Some("qux"));
+
+        // appending to a block adds rows
+        regs.store(Some('c'), Yank { register: Register::blockwise("a\nb"), deleted: false });
+        regs.store(Some('C'), yank("c"));
+        assert_eq!(regs.get(Some('c')), Some(&Register::blockwise("a\nb\nc")));
+    }
+
+    #[test]
+    fn test_registers_kill_ring() {
+        let mut regs = Registers::default();
+        assert_eq!(regs.kill(0), None);
+
+        regs.store(Some('a'), yank("foo"));
+        regs.store(Some('A'), yank("bar"));
+        regs.store(None, delete("baz"));
+
+        // each kill is kept on its own
+        assert_eq!(regs.kills(), 3);
+        assert_eq!(regs.kill(0).unwrap().text, "baz");
+        assert_eq!(regs.kill(1).unwrap().text, "bar");
+        assert_eq!(regs.kill(3).unwrap().text, "baz");
+
+        assert_eq!(Register::from_clipboard("a\nb\n"), Register::linewise("a\nb"));
+        assert_eq!(Register::linewise("a").to_clipboard(), "a\n");
+        assert_eq!(Register::blockwise("a\nb").to_clipboard(), "a\nb\n");
+    }
+
+}
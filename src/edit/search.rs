@@ -0,0 +1,206 @@
+use std::ops::Range;
+
+use regex::{Regex, RegexBuilder};
+use thiserror::Error;
+
+use super::buffer::{Buffer, Cursor};
+use super::text;
+
+
+
+/// A match of a [`Search`] within a single line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Match {
+    /// first grapheme of the match
+    pub start: Cursor,
+    /// grapheme after the match, on the same line as `start`
+    pub end: Cursor,
+}
+
+impl Match {
+    /// Whether `cursor` is on the match. Empty matches contain the cursor at their start.
+    #[must_use]
+    pub fn contains(&self, cursor: Cursor) -> bool {
+        cursor.y == self.start.y
+            && self.start.x <= cursor.x
+            && (cursor.x < self.end.x || cursor.x == self.start.x)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HighlightKind {
+    Match,
+    /// the match the cursor is on, or would move to
+    Current,
+}
+
+/// A range of a buffer drawn with a background, see [`super::Editor::highlights`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Highlight {
+    pub range: Match,
+    pub kind: HighlightKind,
+}
+
+#[derive(Debug, Error)]
+pub enum SearchError {
+    #[error("invalid pattern: {0}")]
+    Regex(#[from] regex::Error),
+    #[error("pattern not found: {0}")]
+    NotFound(String),
+    #[error("no previous pattern")]
+    NoPattern,
+}
+
+/// A compiled search pattern, as typed after `/` or `?`.
+#[derive(Debug, Clone)]
+pub struct Search {
+    pattern: String,
+    regex: Regex,
+    /// whether `n` searches towards the top, as after `?`
+    backward: bool,
+}
+
+impl Search {
+
+    /// Compiles `pattern` as a regex. Like vim's `smartcase`, the pattern
+    /// ignores case unless it contains uppercase characters.
+    pub fn new(pattern: &str, backward: bool) -> Result<Self, regex::Error> {
+        let regex = RegexBuilder::new(pattern)
+            .case_insensitive(!pattern.chars().any(char::is_uppercase))
+            .build()?;
+
+        Ok(Self { pattern: pattern.to_string(), regex, backward })
+    }
+
+    #[must_use]
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    #[must_use]
+    pub fn regex(&self) -> &Regex {
+        &self.regex
+    }
+
+    #[must_use]
+    pub fn is_backward(&self) -> bool {
+        self.backward
+    }
+
+    /// All matches in `buf`, in the order they appear.
+    #[must_use]
+    pub fn matches(&self, buf: &Buffer) -> Vec<Match> {
+        buf.getlines()
+            .enumerate()
+            .flat_map(|(y, line)| self.line_matches(&line, y))
+            .collect()
+    }
+
+    /// The matches within the zero-based `lines`, which may reach past the end of `buf`.
+    #[must_use]
+    pub fn matches_in(&self, buf: &Buffer, lines: Range<usize>) -> Vec<Match> {
+        let lines = lines.start..lines.end.min(buf.getlines().len());
+        lines.flat_map(|y| self.line_matches(&buf.line(y), y)).collect()
+    }
+
+    fn line_matches(&self, line: &str, y: usize) -> Vec<Match> {
+        self.regex
+            .find_iter(line)
+            .map(|m| Match {
+                start: Cursor::new(text::grapheme_index(line, m.start()) as isize, y as isize),
+                end:   Cursor::new(text::grapheme_index(line, m.end()) as isize, y as isize),
+            })
+            .collect()
+    }
+
+    /// The match after `cursor`, or before it if searching `backward`.
+    /// Wraps around the end of the buffer, which is returned as well.
+    #[must_use]
+    pub fn next(matches: &[Match], cursor: Cursor, backward: bool) -> Option<(Match, bool)> {
+        let key = |c: Cursor| (c.y, c.x);
+
+        let found = if backward {
+            matches.iter().rev().find(|m| key(m.start) < key(cursor))
+        } else {
+            matches.iter().find(|m| key(m.start) > key(cursor))
+        };
+
+        match found {
+            Some(m) => Some((*m, false)),
+            None if backward => matches.last().map(|m| (*m, true)),
+            None             => matches.first().map(|m| (*m, true)),
+        }
+    }
+
+    /// The part of the sorted `matches` within the zero-based `lines`.
+    #[must_use]
+    pub fn within<'a>(matches: &'a [Match], lines: &Range<usize>) -> &'a [Match] {
+        let start = matches.partition_point(|m| (m.start.y as usize) < lines.start);
+        let end = matches.partition_point(|m| (m.start.y as usize) < lines.end);
+        &matches[start..end]
+    }
+
+    /// The position of the match at `cursor` among `matches` and their count,
+    /// eg. `(3, 17)`. The match before the cursor counts if it is not on one.
+    #[must_use]
+    pub fn position(matches: &[Match], cursor: Cursor) -> (usize, usize) {
+        let key = |c: Cursor| (c.y, c.x);
+        let index = matches.iter().take_while(|m| key(m.start) <= key(cursor)).count();
+        (index, matches.len())
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer() -> Buffer {
+        let mut buf = Buffer::new();
+        buf.load_buffer(vec![ "foo Foo".into(), "ääfoo".into(), "bar".into() ]);
+        buf
+    }
+
+    fn starts(matches: &[Match]) -> Vec<(isize, isize)> {
+        matches.iter().map(|m| (m.start.x, m.start.y)).collect()
+    }
+
+    #[test]
+    fn test_search_smartcase() {
+        let buf = buffer();
+
+        let matches = Search::new("foo", false).unwrap().matches(&buf);
+        assert_eq!(starts(&matches), [ (0, 0), (4, 0), (2, 1) ]);
+        assert_eq!(matches[2].end, Cursor::new(5, 1));
+
+        let matches = Search::new("Fo+", false).unwrap().matches(&buf);
+        assert_eq!(starts(&matches), [ (4, 0) ]);
+
+        let matches = Search::new("foo", false).unwrap().matches_in(&buf, 1..5);
+        assert_eq!(starts(&matches), [ (2, 1) ]);
+
+        assert!(Search::new("(", false).is_err());
+    }
+
+    #[test]
+    fn test_search_next() {
+        let matches = Search::new("foo", false).unwrap().matches(&buffer());
+
+        let next = |x, y, backward| {
+            let (m, wrapped) = Search::next(&matches, Cursor::new(x, y), backward).unwrap();
+            (m.start.x, m.start.y, wrapped)
+        };
+        assert_eq!(next(0, 0, false), (4, 0, false));
+        assert_eq!(next(2, 1, false), (0, 0, true));
+        assert_eq!(next(2, 1, true), (4, 0, false));
+        assert_eq!(next(0, 0, true), (2, 1, true));
+
+        assert_eq!(Search::position(&matches, Cursor::new(4, 0)), (2, 3));
+        assert_eq!(Search::position(&matches, Cursor::new(0, 2)), (3, 3));
+        assert!(Search::next(&[], Cursor::default(), false).is_none());
+
+        assert_eq!(starts(Search::within(&matches, &(1..2))), [ (2, 1) ]);
+        assert!(Search::within(&matches, &(2..9)).is_empty());
+    }
+
+}
@@ -0,0 +1,386 @@
+use std::ops::Range;
+
+use regex::{Regex, RegexBuilder};
+use thiserror::Error;
+
+use super::buffer::{Buffer, Cursor};
+use super::search::{Match, Search};
+use super::text;
+
+
+
+#[derive(Debug, Error)]
+pub enum SubstituteError {
+    #[error("invalid pattern: {0}")]
+    Regex(#[from] regex::Error),
+    #[error("invalid delimiter: {0}")]
+    Delimiter(char),
+    #[error("unknown flag: {0}")]
+    UnknownFlag(char),
+    #[error("no previous pattern")]
+    NoPattern,
+}
+
+/// A parsed `:s/pattern/replacement/flags` command.
+#[derive(Debug, Clone)]
+pub struct Substitute {
+    pattern: String,
+    regex: Regex,
+    /// in the syntax of [`regex::Captures::expand`]
+    replacement: String,
+    /// replaces every match of a line instead of only the first
+    global: bool,
+    /// asks before replacing each match
+    confirm: bool,
+    /// only counts the matches
+    count_only: bool,
+}
+
+impl Substitute {
+
+    /// Parses the arguments following `:s`, eg. `/foo/bar/g`. Any punctuation delimits the parts,
+    /// and the flags `g` (all matches of a line), `c` (confirm), `n` (count only), `i` and `I`
+    /// (ignore case or not, instead of smart case) may follow.
+    /// An empty pattern stands for the `last` search.
+    pub fn parse(args: &str, last: Option<&Search>) -> Result<Self, SubstituteError> {
+        let mut chars = args.trim_start().chars();
+        let Some(delim) = chars.next() else { return Err(SubstituteError::NoPattern) };
+        if delim.is_alphanumeric() || delim.is_whitespace() || delim == '\\' {
+            return Err(SubstituteError::Delimiter(delim));
+        }
+
+        let rest = chars.as_str();
+        let (pattern, rest) = split_part(rest, delim);
+        let (replacement, flags) = split_part(rest.unwrap_or_default(), delim);
+
+        let pattern = match (pattern.as_str(), last) {
+            ("", Some(search)) => search.pattern().to_string(),
+            ("", None)         => return Err(SubstituteError::NoPattern),
+            _                  => pattern,
+        };
+
+        let mut self_ = Self {
+            regex: Regex::new("")?,
+            replacement: expand_replacement(&replacement),
+            global: false,
+            confirm: false,
+            count_only: false,
+            pattern,
+        };
+
+        let mut ignore_case = !self_.pattern.chars().any(char::is_uppercase);
+        for flag in flags.unwrap_or_default().trim().chars() {
+            match flag {
+                'g' => self_.global = true,
+                'c' => self_.confirm = true,
+                'n' => self_.count_only = true,
+                'i' => ignore_case = true,
+                'I' => ignore_case = false,
+                _   => return Err(SubstituteError::UnknownFlag(flag)),
+            }
+        }
+
+        self_.regex = RegexBuilder::new(&self_.pattern)
+            .case_insensitive(ignore_case)
+            .build()?;
+        Ok(self_)
+    }
+
+    #[must_use]
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    #[must_use]
+    pub fn is_confirm(&self) -> bool {
+        self.confirm
+    }
+
+    #[must_use]
+    pub fn is_count_only(&self) -> bool {
+        self.count_only
+    }
+
+}
+
+/// Splits off the text up to the next unescaped `delim`, which is unescaped in the returned part.
+/// Returns what follows the delimiter, or [`None`] if there is none.
+fn split_part(s: &str, delim: char) -> (String, Option<&str>) {
+    let mut part = String::new();
+    let mut chars = s.char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some((_, next)) if next == delim => part.push(delim),
+                Some((_, next)) => { part.push('\\'); part.push(next); }
+                None => part.push('\\'),
+            },
+            c if c == delim => return (part, Some(&s[i + c.len_utf8()..])),
+            c => part.push(c),
+        }
+    }
+    (part, None)
+}
+
+/// Converts a replacement in vim syntax, where `&` and `\0` stand for the match,
+/// `\1` to `\9` for its groups and `\r` or `\n` for a line break.
+fn expand_replacement(replacement: &str) -> String {
+    let mut expanded = String::new();
+    let mut chars = replacement.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '&' => expanded.push_str("${0}"),
+            '$' => expanded.push_str("$$"),
+            '\\' => match chars.next() {
+                Some(digit @ '0'..='9') => expanded.push_str(&format!("${{{digit}}}")),
+                Some('r' | 'n') => expanded.push('\n'),
+                Some('t') => expanded.push('\t'),
+                Some(other) => expanded.push(other),
+                None => expanded.push('\\'),
+            },
+            c => expanded.push(c),
+        }
+    }
+    expanded
+}
+
+/// A match of a substitution, along with what it is replaced with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Found {
+    /// in graphemes
+    pub range: Match,
+    /// byte offsets into the line
+    start: usize,
+    end: usize,
+    pub replacement: String,
+}
+
+/// Goes through the matches of a [`Substitute`] within some lines,
+/// replacing or skipping them one by one.
+#[derive(Debug, Clone)]
+pub struct Substitution {
+    sub: Substitute,
+    /// line and byte offset to search from
+    from: (usize, usize),
+    /// last line to search, which moves as line breaks are inserted
+    last: usize,
+    replaced: usize,
+    /// number of lines replaced in, and the last one of them
+    lines: usize,
+    last_replaced: Option<usize>,
+}
+
+impl Substitution {
+
+    /// Substitutes in the zero-based lines `first` to `last` (inclusive).
+    #[must_use]
+    pub fn new(sub: Substitute, first: usize, last: usize) -> Self {
+        Self { sub, from: (first, 0), last, replaced: 0, lines: 0, last_replaced: None }
+    }
+
+    #[must_use]
+    pub fn substitute(&self) -> &Substitute {
+        &self.sub
+    }
+
+    /// Number of matches replaced so far
+    #[must_use]
+    pub fn replaced(&self) -> usize {
+        self.replaced
+    }
+
+    /// Number of lines that matches have been replaced in so far
+    #[must_use]
+    pub fn lines(&self) -> usize {
+        self.lines
+    }
+
+    /// The line of the last replacement, after the line breaks it inserted
+    #[must_use]
+    pub fn last_line(&self) -> Option<usize> {
+        self.last_replaced
+    }
+
+    /// The next match to replace or skip
+    #[must_use]
+    pub fn current(&self, buf: &Buffer) -> Option<Found> {
+        let (first, offset) = self.from;
+        let last = self.last.min(buf.getlines().len().saturating_sub(1));
+
+        (first..=last).find_map(|y| {
+            let line = buf.line(y);
+            let start = if y == first { offset } else { 0 };
+            if start > line.len() { return None }
+
+            let caps = self.sub.regex.captures_at(&line, start)?;
+            let m = caps.get(0)?;
+            let mut replacement = String::new();
+            caps.expand(&self.sub.replacement, &mut replacement);
+
+            let y = y as isize;
+            Some(Found {
+                range: Match {
+                    start: Cursor::new(text::grapheme_index(&line, m.start()) as isize, y),
+                    end:   Cursor::new(text::grapheme_index(&line, m.end()) as isize, y),
+                },
+                start: m.start(),
+                end: m.end(),
+                replacement,
+            })
+        })
+    }
+
+    pub fn replace(&mut self, buf: &mut Buffer, found: &Found) {
+        buf.replace(found.range.start, found.range.end, &found.replacement);
+
+        let y = found.range.start.y as usize;
+        if self.last_replaced != Some(y) {
+            self.lines += 1;
+        }
+        self.replaced += 1;
+
+        // continue right after the replacement, which may have split the line
+        let breaks = found.replacement.matches('\n').count();
+        let end = match found.replacement.rsplit_once('\n') {
+            Some((_, tail)) => tail.len(),
+            None            => found.start + found.replacement.len(),
+        };
+        self.last += breaks;
+        self.last_replaced = Some(y + breaks);
+        self.advance(buf, y + breaks, end, found.start == found.end);
+    }
+
+    pub fn skip(&mut self, buf: &Buffer, found: &Found) {
+        self.advance(buf, found.range.start.y as usize, found.end, found.start == found.end);
+    }
+
+    /// Replaces all remaining matches.
+    pub fn run(&mut self, buf: &mut Buffer) {
+        while let Some(found) = self.current(buf) {
+            self.replace(buf, &found);
+        }
+    }
+
+    /// The remaining matches, without replacing them.
+    #[must_use]
+    pub fn matches(&self, buf: &Buffer) -> Vec<Match> {
+        self.matches_in(buf, 0..usize::MAX)
+    }
+
+    /// The remaining matches within the zero-based `lines`, without searching the others.
+    #[must_use]
+    pub fn matches_in(&self, buf: &Buffer, lines: Range<usize>) -> Vec<Match> {
+        if lines.is_empty() {
+            return Vec::new();
+        }
+
+        let mut rest = self.clone();
+        if lines.start > rest.from.0 {
+            rest.from = (lines.start, 0);
+        }
+        rest.last = rest.last.min(lines.end - 1);
+
+        std::iter::from_fn(|| {
+            let found = rest.current(buf)?;
+            rest.skip(buf, &found);
+            Some(found.range)
+        }).collect()
+    }
+
+    /// Moves on to the byte offset `x` of line `y`. Empty matches are stepped over,
+    /// as they would match again. Only the first match of a line is taken, unless global.
+    fn advance(&mut self, buf: &Buffer, y: usize, x: usize, empty: bool) {
+        if !self.sub.global {
+            self.from = (y + 1, 0);
+            return;
+        }
+
+        let x = if empty {
+            x + buf.line(y)[x..].chars().next().map_or(1, char::len_utf8)
+        } else {
+            x
+        };
+        self.from = (y, x);
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer() -> Buffer {
+        let mut buf = Buffer::new();
+        buf.load_buffer(vec![ "foo foo".into(), "bar".into(), "Foo = 1".into() ]);
+        buf
+    }
+
+    fn lines(buf: &Buffer) -> Vec<String> {
+        buf.getlines().map(|line| line.to_string()).collect()
+    }
+
+    fn substitute(buf: &mut Buffer, args: &str) -> usize {
+        let mut sub = Substitution::new(Substitute::parse(args, None).unwrap(), 0, 2);
+        sub.run(buf);
+        sub.replaced()
+    }
+
+    #[test]
+    fn test_substitute_parse() {
+        assert!(Substitute::parse("/a/b/gc", None).unwrap().is_confirm());
+        assert!(matches!(Substitute::parse("/a/b/x", None), Err(SubstituteError::UnknownFlag('x'))));
+        assert!(matches!(Substitute::parse("a/b/", None), Err(SubstituteError::Delimiter('a'))));
+        assert!(matches!(Substitute::parse("//b/", None), Err(SubstituteError::NoPattern)));
+
+        let last = Search::new("bar", false).unwrap();
+        assert_eq!(Substitute::parse("//b/", Some(&last)).unwrap().pattern(), "bar");
+        assert_eq!(Substitute::parse(r"#a\#b#", None).unwrap().pattern(), "a#b");
+
+        assert_eq!(split_part(r"a\/b/c", '/'), ("a/b".to_string(), Some("c")));
+        assert_eq!(expand_replacement(r"<&>\1$\r"), "<${0}>${1}$$\n");
+    }
+
+    #[test]
+    fn test_substitute_run() {
+        let mut buf = buffer();
+        assert_eq!(substitute(&mut buf, "/foo/x/"), 2);
+        assert_eq!(lines(&buf), [ "x foo", "bar", "x = 1" ]);
+
+        let mut buf = buffer();
+        assert_eq!(substitute(&mut buf, "/(fo)o/<\\1>/gI"), 2);
+        assert_eq!(lines(&buf), [ "<fo> <fo>", "bar", "Foo = 1" ]);
+
+        // line breaks extend the lines substituted in
+        let mut buf = buffer();
+        assert_eq!(substitute(&mut buf, "/ /\\r/g"), 3);
+        assert_eq!(lines(&buf), [ "foo", "foo", "bar", "Foo", "=", "1" ]);
+
+        // empty matches do not match again
+        let mut buf = buffer();
+        assert_eq!(substitute(&mut buf, "/x*/-/g"), 20);
+        assert_eq!(lines(&buf)[1], "-b-a-r-");
+    }
+
+    #[test]
+    fn test_substitute_confirm() {
+        let mut buf = buffer();
+        let mut sub = Substitution::new(Substitute::parse("/o+/0/g", None).unwrap(), 0, 2);
+        assert_eq!(sub.matches(&buf).len(), 3);
+
+        let found = sub.current(&buf).unwrap();
+        sub.skip(&buf, &found);
+        let found = sub.current(&buf).unwrap();
+        assert_eq!(found.range.start, Cursor::new(5, 0));
+        sub.replace(&mut buf, &found);
+        assert_eq!(sub.matches(&buf), [ Match { start: Cursor::new(1, 2), end: Cursor::new(3, 2) } ]);
+        assert_eq!(sub.matches_in(&buf, 2..3).len(), 1);
+        assert!(sub.matches_in(&buf, 0..2).is_empty());
+
+        sub.run(&mut buf);
+        assert_eq!(lines(&buf), [ "foo f0", "bar", "F0 = 1" ]);
+        assert_eq!((sub.replaced(), sub.lines()), (2, 2));
+    }
+
+}
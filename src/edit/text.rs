@@ -0,0 +1,216 @@
+use std::borrow::Cow;
+use std::io;
+use std::ops::Range;
+
+use ropey::{Rope, RopeSlice};
+use unicode_segmentation::UnicodeSegmentation;
+
+
+
+/// Rope-backed text storage of a buffer.
+///
+/// Positions are given as `(line, column)`, where the column is a byte
+/// offset into the line. Lines are separated by `\n`, the separator itself
+/// is never part of a line.
+#[derive(Clone, Default)]
+pub struct Text {
+    rope: Rope,
+}
+
+impl std::fmt::Debug for Text {
+    // dumping a rope of a large file is of no use to anyone
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Text")
+            .field("lines", &self.line_count())
+            .field("bytes", &self.rope.len_bytes())
+            .finish()
+    }
+}
+
+impl From<&str> for Text {
+    fn from(value: &str) -> Self {
+        Self { rope: Rope::from_str(value) }
+    }
+}
+
+impl Text {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the text from `reader`, dropping a single trailing newline, which may be `\r\n`.
+    pub fn from_reader(reader: impl io::Read) -> io::Result<Self> {
+        let mut rope = Rope::from_reader(reader)?;
+
+        let len = rope.len_chars();
+        if len > 0 && rope.char(len - 1) == '\n' {
+            let crlf = len > 1 && rope.char(len - 2) == '\r';
+            rope.remove(len - if crlf { 2 } else { 1 }..);
+        }
+
+        Ok(Self { rope })
+    }
+
+    pub fn write_to(&self, writer: impl io::Write) -> io::Result<()> {
+        self.rope.write_to(writer)
+    }
+
+    /// Always at least one, as an empty text consists of a single empty line.
+    #[must_use]
+    pub fn line_count(&self) -> usize {
+        self.rope.len_lines()
+    }
+
+    /// Returns the line at `idx`, borrowing from the rope if possible.
+    #[must_use]
+    pub fn line(&self, idx: usize) -> Cow<'_, str> {
+        strip_linebreak(self.rope.line(idx)).into()
+    }
+
+    /// Length of the line at `idx` in bytes.
+    #[must_use]
+    pub fn line_len(&self, idx: usize) -> usize {
+        strip_linebreak(self.rope.line(idx)).len_bytes()
+    }
+
+    #[must_use]
+    pub fn lines(&self) -> Lines<'_> {
+        Lines { text: self, range: 0..self.line_count() }
+    }
+
+    fn char_idx(&self, (line, col): (usize, usize)) -> usize {
+        self.rope.byte_to_char(self.rope.line_to_byte(line) + col)
+    }
+
+    pub fn insert(&mut self, pos: (usize, usize), text: &str) {
+        let idx = self.char_idx(pos);
+        self.rope.insert(idx, text);
+    }
+
+    /// Returns the text between `start` (inclusive) and `end` (exclusive).
+    #[must_use]
+    pub fn slice(&self, start: (usize, usize), end: (usize, usize)) -> String {
+        self.rope.slice(self.char_idx(start)..self.char_idx(end)).to_string()
+    }
+
+    /// Removes the text between `start` (inclusive) and `end` (exclusive).
+    /// Returns the removed text.
+    pub fn remove(&mut self, start: (usize, usize), end: (usize, usize)) -> String {
+        let range = self.char_idx(start)..self.char_idx(end);
+        let removed = self.rope.slice(range.clone()).to_string();
+        self.rope.remove(range);
+        removed
+    }
+
+}
+
+/// Number of grapheme clusters in `line`.
+#[must_use]
+pub fn grapheme_count(line: &str) -> usize {
+    line.graphemes(true).count()
+}
+
+/// Byte offset of the grapheme cluster at index `idx`.
+/// Returns the length of `line` if `idx` is out-of-bounds.
+#[must_use]
+pub fn grapheme_offset(line: &str, idx: usize) -> usize {
+    line.grapheme_indices(true)
+        .nth(idx)
+        .map_or(line.len(), |(offset, _)| offset)
+}
+
+/// Grapheme index of the cluster containing the byte at `offset`.
+#[must_use]
+pub fn grapheme_index(line: &str, offset: usize) -> usize {
+    line.grapheme_indices(true)
+        .take_while(|(i, g)| i + g.len() <= offset)
+        .count()
+}
+
+/// Strips a trailing `\n` or `\r\n` from a line.
+fn strip_linebreak(line: RopeSlice) -> RopeSlice {
+    let mut end = line.len_chars();
+
+    if end > 0 && line.char(end - 1) == '\n' {
+        end -= 1;
+        if end > 0 && line.char(end - 1) == '\r' {
+            end -= 1;
+        }
+    }
+
+    line.slice(..end)
+}
+
+/// Iterator over the lines of a [`Text`].
+///
+/// Skipping is cheap, as lines are looked up by index.
+#[derive(Debug, Clone)]
+pub struct Lines<'a> {
+    text: &'a Text,
+    range: Range<usize>,
+}
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = Cow<'a, str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let idx = self.range.next()?;
+        Some(self.text.line(idx))
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        let idx = self.range.nth(n)?;
+        Some(self.text.line(idx))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.range.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for Lines<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let idx = self.range.next_back()?;
+        Some(self.text.line(idx))
+    }
+}
+
+impl ExactSizeIterator for Lines<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_from_reader() {
+        let text = Text::from_reader("foo\r\nbar\n".as_bytes()).unwrap();
+        assert_eq!(text.line_count(), 2);
+        assert_eq!(text.lines().collect::<Vec<_>>(), vec![ "foo", "bar" ]);
+        assert_eq!(text.line_len(0), 3);
+
+        // the last line break is dropped as a whole
+        let text = Text::from_reader("a\r\nb\r\nc\r\n".as_bytes()).unwrap();
+        assert_eq!(text.lines().collect::<Vec<_>>(), vec![ "a", "b", "c" ]);
+        assert_eq!(text.line_len(2), 1);
+    }
+
+    #[test]
+    fn test_text_insert_remove() {
+        let mut text = Text::from("hello\nworld");
+        text.insert((0, 5), ",\nbig");
+        assert_eq!(text.lines().collect::<Vec<_>>(), vec![ "hello,", "big", "world" ]);
+
+        let removed = text.remove((0, 5), (2, 0));
+        assert_eq!(removed, ",\nbig\n");
+        assert_eq!(text.lines().collect::<Vec<_>>(), vec![ "helloworld" ]);
+    }
+
+    #[test]
+    fn test_text_lines_skip() {
+        let text = Text::from("a\nb\nc\nd");
+        assert_eq!(text.lines().skip(2).collect::<Vec<_>>(), vec![ "c", "d" ]);
+        assert_eq!(text.lines().skip(1).len(), 3);
+    }
+
+}
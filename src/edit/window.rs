@@ -1,6 +1,7 @@
 use std::collections::BTreeMap;
 
 use super::buffer::BufferID;
+use super::event::EventData;
 
 
 
@@ -15,18 +16,33 @@ pub struct Windows {
     pub idcount: WindowID,
     /// Using [`BTreeMap`], as the data should always be in order.
     pub windows: BTreeMap<WindowID, Window>,
+    /// whether windows have been added, deleted or focused since they have last been drawn
+    dirty: bool,
 }
 
 impl Windows {
 
     pub fn with_buffer(id: BufferID) -> Self {
+        super::emit(EventData::WinNew(0));
         Self {
             windows: BTreeMap::from([ (0, Window::new(id)) ]),
             idcount: 1,
             current: 0,
+            dirty: true,
         }
     }
 
+    /// Returns true if any window changed since they have last been drawn.
+    #[must_use]
+    pub fn is_dirty(&self) -> bool {
+        self.dirty || self.windows.values().any(Window::is_dirty)
+    }
+
+    pub fn clean(&mut self) {
+        self.dirty = false;
+        self.windows.values_mut().for_each(Window::clean);
+    }
+
     /// Retrieves the [`WindowID`] of the currently focused [`Window`]
     #[must_use]
     pub fn winid(&self) -> Option<WindowID> {
@@ -54,10 +70,12 @@ impl Windows {
             Window::default()
         };
 
+        self.dirty = true;
         let id = self.idcount;
         self.idcount += 1;
         let ret = self.windows.insert(id, win);
         assert!(ret.is_none());
+        super::emit(EventData::WinNew(id));
         id
     }
 
@@ -65,7 +83,10 @@ impl Windows {
     pub fn delete(&mut self) {
         if self.windows.is_empty() { return }
 
-        self.windows.remove(&self.current);
+        self.dirty = true;
+        if self.windows.remove(&self.current).is_some() {
+            super::emit(EventData::WinDel(self.current));
+        }
 
         let max = self.windows
             .len()
@@ -76,6 +97,7 @@ impl Windows {
     /// Cycles to the next [`Window`]
     pub fn next(&mut self, wrap: bool) {
 
+        self.dirty = true;
         self.current += 1;
         let len = self.windows.len();
 
@@ -87,6 +109,7 @@ impl Windows {
 
     /// Cycles to the previous [`Window`]
     pub fn prev(&mut self, wrap: bool) {
+        self.dirty = true;
         // BUG: switch to hashmap
         self.current = self.current
             .checked_sub(1)
@@ -103,12 +126,23 @@ impl Windows {
 #[derive(Debug, Clone, Copy, Default)]
 pub struct Window {
     buf: Option<BufferID>,
+    /// whether the window shows another buffer since it has last been drawn
+    dirty: bool,
 }
 
 impl Window {
 
     pub fn new(buf: BufferID) -> Self {
-        Self { buf: Some(buf) }
+        Self { buf: Some(buf), dirty: true }
+    }
+
+    #[must_use]
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub fn clean(&mut self) {
+        self.dirty = false;
     }
 
     #[must_use]
@@ -117,7 +151,10 @@ impl Window {
     }
 
     pub fn set_buf(&mut self, id: BufferID) {
-        self.buf = Some(id);
+        if self.buf != Some(id) {
+            self.dirty = true;
+            self.buf = Some(id);
+        }
     }
 
 }
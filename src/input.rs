@@ -0,0 +1,174 @@
+use std::time::{Duration, Instant};
+
+use macroquad::prelude::{KeyCode, MouseButton, Vec2};
+
+use crate::config::keys::{Key, KeyParseError, Modifiers};
+
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyState {
+    Press,
+    /// the key is held down long enough to be typed again
+    Repeat,
+    Release,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MouseEvent {
+    Press(MouseButton),
+    Release(MouseButton),
+    Move,
+    Scroll(Vec2),
+}
+
+/// Input produced by a backend, so that dispatching it does not depend on
+/// where it came from, be it the window, the terminal or a test.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputEvent {
+    Key {
+        /// includes the modifiers held down
+        key: Key,
+        state: KeyState,
+        /// the character typed by the key, if any
+        text: Option<char>,
+    },
+    /// text that no key stands for, eg. characters outside of the US layout
+    Text(char),
+    Mouse {
+        event: MouseEvent,
+        /// in the unit of the backend, see [`crate::render::Renderer`]
+        pos: Vec2,
+    },
+    /// the surface drawn onto changed its size
+    Resize,
+}
+
+impl InputEvent {
+
+    /// Presses `key`, typing its character unless modifiers other than shift are held.
+    #[must_use]
+    pub fn press(key: Key) -> Self {
+        let text = if (key.mods - Modifiers::Shift).is_empty() {
+            match key.key_code {
+                KeyCode::Space => Some(' '),
+                _              => key.to_char(),
+            }
+        } else {
+            None
+        };
+        Self::Key { key, state: KeyState::Press, text }
+    }
+
+    /// Presses each key of a sequence in vim notation, eg. `ofoo<Esc>`.
+    pub fn parse_sequence(s: &str) -> Result<Vec<Self>, KeyParseError> {
+        Ok(Key::parse_sequence(s)?.into_iter().map(Self::press).collect())
+    }
+
+}
+
+/// The key held down, which is repeated from `next` on
+#[derive(Debug, Clone, Copy)]
+struct Held {
+    key: Key,
+    text: Option<char>,
+    next: Instant,
+}
+
+/// Repeats the key held down for backends that only report presses and releases.
+/// Backends repeating keys themselves take precedence, see [`KeyRepeat::track`].
+#[derive(Debug, Clone, Default)]
+pub struct KeyRepeat {
+    held: Option<Held>,
+}
+
+impl KeyRepeat {
+
+    /// Follows the keys pressed and released by `ev`.
+    /// The key is not repeated before `delay` has passed, nor once the backend repeats it.
+    pub fn track(&mut self, ev: &InputEvent, now: Instant, delay: Duration) {
+        let InputEvent::Key { key, state, text } = *ev else { return };
+        if Key::is_modifier(key.key_code) { return }
+
+        match state {
+            KeyState::Press => self.held = Some(Held { key, text, next: now + delay }),
+            KeyState::Repeat | KeyState::Release => {
+                if self.held.is_some_and(|held| held.key.key_code == key.key_code) {
+                    self.held = None;
+                }
+            }
+        }
+    }
+
+    /// Time at which the key held down is repeated next
+    #[must_use]
+    pub fn next(&self) -> Option<Instant> {
+        self.held.map(|held| held.next)
+    }
+
+    /// Returns the repeats that are due at `now`, `rate` times per second.
+    pub fn poll(&mut self, now: Instant, rate: f64) -> Vec<InputEvent> {
+        let mut events = Vec::new();
+        let Some(held) = &mut self.held else { return events };
+        if rate <= 0. { return events }
+
+        let interval = Duration::from_secs_f64(1. / rate);
+        while held.next <= now {
+            events.push(InputEvent::Key { key: held.key, state: KeyState::Repeat, text: held.text });
+            held.next += interval;
+        }
+        events
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_repeat() {
+        let start = Instant::now();
+        let delay = Duration::from_millis(300);
+        let at = |ms| start + Duration::from_millis(ms);
+        let x = InputEvent::press("x".parse().unwrap());
+
+        let mut repeat = KeyRepeat::default();
+        repeat.track(&x, start, delay);
+        assert!(repeat.poll(at(299), 10.).is_empty());
+        assert_eq!(repeat.poll(at(300), 10.).len(), 1);
+        // a slow frame catches up on the repeats it missed
+        assert_eq!(repeat.poll(at(550), 10.).len(), 2);
+
+        // holding a modifier keeps repeating the key
+        repeat.track(&InputEvent::press(Key::new(KeyCode::LeftShift, Modifiers::Shift)), at(550), delay);
+        assert_eq!(repeat.poll(at(600), 10.).len(), 1);
+
+        let InputEvent::Key { key, .. } = x else { unreachable!() };
+        repeat.track(&InputEvent::Key { key, state: KeyState::Release, text: None }, at(600), delay);
+        assert!(repeat.poll(at(1000), 10.).is_empty());
+
+        // repeats of the backend replace those of the application
+        repeat.track(&x, at(1000), delay);
+        repeat.track(&InputEvent::Key { key, state: KeyState::Repeat, text: Some('x') }, at(1300), delay);
+        assert!(repeat.poll(at(2000), 10.).is_empty());
+    }
+
+    #[test]
+    fn test_input_parse_sequence() {
+        let text = |s: &str| -> Vec<Option<char>> {
+            InputEvent::parse_sequence(s)
+                .unwrap()
+                .into_iter()
+                .map(|ev| match ev {
+                    InputEvent::Key { state: KeyState::Press, text, .. } => text,
+                    _ => unreachable!(),
+                })
+                .collect()
+        };
+
+        assert_eq!(text("aB:"), [ Some('a'), Some('B'), Some(':') ]);
+        assert_eq!(text("<Space><Esc><C-u><lt>"), [ Some(' '), None, None, Some('<') ]);
+    }
+
+}
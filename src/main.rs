@@ -2,34 +2,43 @@
 
 use std::path::{PathBuf, Path};
 use std::sync::mpsc;
+use std::time::{Duration, Instant};
 
 mod render;
 mod edit;
 mod config;
+mod input;
 mod wrap;
 
-use render::GuiRenderer;
-use edit::{Editor, Mode};
+use render::{GuiRenderer, Renderer, TuiRenderer, View};
+use edit::{Editor, Focus, Mode};
 use edit::event::EventData;
+use edit::clipboard;
+use edit::register::Registers;
 use wrap::*;
 use config::{configure, Config};
+use config::file::ConfigFile;
+use config::keys::{Key, Modifiers};
+use input::{InputEvent, KeyRepeat, KeyState};
 
 use macroquad::prelude::*;
-use macroquad::miniquad::window::set_window_size;
+use macroquad::miniquad;
 
 
 
-const COLOR_BG:  Color = Color::from_rgba(40, 43, 46, 255);
-const FRAMERATE: f32 = 20.;
+const DIGITS: [KeyCode; 10] = [
+    KeyCode::Key0, KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4,
+    KeyCode::Key5, KeyCode::Key6, KeyCode::Key7, KeyCode::Key8, KeyCode::Key9,
+];
 
 
 
 
 #[derive(Debug, thiserror::Error)]
 enum AppError {
-    #[error("io error")]
+    #[error("io error: {0}")]
     Io(#[from] std::io::Error),
-    #[error("macroquad error")]
+    #[error("macroquad error: {0}")]
     Macroquad(#[from] macroquad::Error),
 }
 
@@ -40,12 +49,26 @@ struct Application {
     ed: Editor,
     config: Config,
     should_quit: bool,
-    renderer: GuiRenderer,
+    view: View,
+    /// count typed before the next action
+    count: Option<usize>,
+    /// register typed after `"` for the next action, such as `+` for the system clipboard
+    register: Option<char>,
+    /// whether `"` has been typed, and the register comes next
+    selecting_register: bool,
+    /// keys typed so far of a sequence that has not been completed yet
+    pending: Vec<Key>,
+    /// time at which the last pending key was typed
+    pending_since: Instant,
+    /// only for backends that do not repeat keys themselves
+    repeat: Option<KeyRepeat>,
+    /// whether the count, the pending keys or the view changed since the last frame
+    dirty: bool,
 }
 
 impl Application {
 
-    pub async fn new(path: Option<impl AsRef<Path>>) -> AppResult<Self> {
+    pub fn new(path: Option<impl AsRef<Path>>) -> AppResult<Self> {
 
         let ed = if let Some(path) = path {
             Editor::with_file(path)?
@@ -55,81 +78,352 @@ impl Application {
 
         let mut self_ = Self {
             should_quit:  false,
-            renderer:     GuiRenderer::new().await?,
             config:       Config::default(),
+            count:        None,
+            register:     None,
+            selecting_register: false,
+            pending:      Vec::new(),
+            pending_since: Instant::now(),
+            repeat:       None,
+            dirty:        true,
+            view:         View::default(),
             ed,
         };
 
         configure(&mut self_);
+        edit::emit(EventData::Init);
+        self_.ed.emit_focus_changes(Focus::default());
         Ok(self_)
 
     }
 
+    /// Merges the user config at `path` over the built-in one.
+    /// A missing file is only reported if `required` is set.
+    pub fn load_config(&mut self, path: &Path, required: bool) {
+
+        let errors = match ConfigFile::load(path) {
+            Ok(file) => file.apply(self),
+            Err(config::file::ConfigError::Io(err)) if !required && err.kind() == std::io::ErrorKind::NotFound => return,
+            Err(err) => vec![ err ],
+        };
+
+        for err in errors {
+            let msg = format!("{}: {err}", path.display());
+            eprintln!("{msg}");
+            self.ed.add_message(msg);
+        }
+
+    }
+
 
     pub fn quit(&mut self) {
         self.should_quit = true;
     }
 
-    fn handle_input(&mut self) {
+    /// Lets the autocmds know that the editor is about to exit.
+    fn shutdown(&mut self) {
+        edit::emit(EventData::Deinit);
+        self.handle_events();
+    }
 
-        let found_bind = self.dispatch_keybinds();
+    #[must_use]
+    pub fn count(&self) -> Option<usize> {
+        self.count
+    }
 
-        let mode = self.ed.buf().unwrap().mode();
-        if mode == Mode::Insert && !found_bind {
+    /// The register selected for the current action. Stays selected while an operator is pending.
+    #[must_use]
+    pub fn register(&self) -> Option<char> {
+        self.register
+    }
+
+    #[must_use]
+    pub fn is_selecting_register(&self) -> bool {
+        self.selecting_register
+    }
+
+    #[must_use]
+    pub fn pending_keys(&self) -> &[Key] {
+        &self.pending
+    }
 
-            if let Some(c) = get_char_pressed() {
+    /// Handles input of any backend. The mouse is not bound to anything yet.
+    fn handle_input(&mut self, ev: InputEvent) {
+        let delay = Duration::from_secs_f64(self.config.repeat_delay());
+        if let Some(repeat) = &mut self.repeat {
+            repeat.track(&ev, Instant::now(), delay);
+        }
+        self.dispatch_input(ev);
+    }
+
+    /// Types the key held down again, for backends that do not repeat keys themselves.
+    fn repeat_keys(&mut self) {
+        let Some(repeat) = &mut self.repeat else { return };
+        for ev in repeat.poll(Instant::now(), self.config.repeat_rate()) {
+            self.dispatch_input(ev);
+        }
+    }
+
+    /// Time at which the next timer runs out, ie. a pending sequence times out
+    /// or a key held down is repeated. Queued events are due immediately.
+    fn next_deadline(&self) -> Option<Instant> {
+        if edit::has_events() {
+            return Some(Instant::now());
+        }
+
+        let timeout = (!self.pending.is_empty())
+            .then(|| self.pending_since + Duration::from_secs_f64(self.config.timeout()));
+        let repeat = self.repeat.as_ref().and_then(KeyRepeat::next);
+
+        timeout.into_iter().chain(repeat).min()
+    }
+
+    #[must_use]
+    fn needs_redraw(&self) -> bool {
+        self.dirty || self.ed.is_dirty()
+    }
+
+    fn dispatch_input(&mut self, ev: InputEvent) {
+        if self.ed.confirming().is_some() {
+            self.dispatch_confirm(ev);
+            return;
+        }
+
+        match ev {
+            InputEvent::Key { key, state: KeyState::Press | KeyState::Repeat, text } => {
+                // modifiers on their own must not abort pending sequences
+                if Key::is_modifier(key.key_code) { return }
+
+                let mode = self.ed.buf().unwrap().mode();
+                let found_bind = self.dispatch_typed(mode, key);
+                self.commit();
+
+                if !found_bind && let Some(c) = text {
+                    self.insert_text(c);
+                }
+            }
+            InputEvent::Text(c) => self.insert_text(c),
+            InputEvent::Resize => self.dirty = true,
+            InputEvent::Key { state: KeyState::Release, .. } | InputEvent::Mouse { .. } => {}
+        }
+        self.ed.update_incsearch();
+        config::commands::preview(self);
+    }
+
+    /// Answers the substitution being confirmed with the typed character, or stops it on escape.
+    fn dispatch_confirm(&mut self, ev: InputEvent) {
+        let answer = match ev {
+            InputEvent::Key { key, state: KeyState::Press | KeyState::Repeat, .. } if key.key_code == KeyCode::Escape => 'q',
+            InputEvent::Key { state: KeyState::Press | KeyState::Repeat, text: Some(c), .. } | InputEvent::Text(c) => c,
+            _ => return,
+        };
+
+        let focus = self.ed.focus();
+        config::commands::confirm(self, answer);
+        self.ed.emit_focus_changes(focus);
+        self.commit();
+    }
+
+    /// Every action outside of insert mode makes up its own undo step,
+    /// whereas an insert mode session is grouped until it is left.
+    /// The replacements of a substitution that is being confirmed are grouped as well.
+    fn commit(&mut self) {
+        if self.ed.confirming().is_some() { return }
+        if self.ed.buf().is_some_and(|buf| buf.mode() != Mode::Insert) {
+            self.ed.buf_mut().unwrap().commit();
+        }
+    }
+
+    /// Inserts text that has not been handled by a keybind.
+    fn insert_text(&mut self, c: char) {
+        let focus = self.ed.focus();
+        match self.ed.buf().unwrap().mode() {
+            Mode::Insert => {
                 let buf = self.ed.buf_mut().unwrap();
                 buf.insert_char(c);
                 buf.move_right();
             }
-
-        } else {
-            clear_input_queue();
+            Mode::Command if !c.is_control() => self.ed.cmdline_mut().insert_char(c),
+            _ => {}
         }
+        self.ed.emit_focus_changes(focus);
+    }
 
+    /// Writes the focused buffer to `path`, or to the file it has been loaded from.
+    /// The autocmds of [`EventData::BufWritePre`] run right away, so they can still change the buffer.
+    /// Returns [`None`] if the buffer is not backed by any file.
+    fn write_buffer(&mut self, path: Option<&Path>) -> Option<std::io::Result<()>> {
+        let id = self.ed.bufid()?;
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None       => self.ed.buf()?.filename()?.to_path_buf(),
+        };
+
+        edit::emit(EventData::BufWritePre(id));
+        self.handle_events();
+
+        // the autocmds may have deleted the buffer
+        let buf = self.ed.buffers().get(id)?;
+        let result = buf.save_to_file(&path);
+        if result.is_ok() {
+            edit::emit(EventData::BufWritePost(id));
+        }
+        Some(result)
     }
 
+    /// Runs the autocmds of all queued events, including those emitted by the autocmds themselves.
     fn handle_events(&mut self) {
 
-        let mut ctx = edit::CONTEXT.lock().unwrap();
-        if let Some(ref ev) = ctx.event_queue.pop_front() {
-            dbg!(ev);
-            if let Some(callback) = self.config.autocmds().get(&ev.base()) {
-                callback(self, ev);
+        while let Some(ev) = edit::next_event() {
+            if let EventData::WinDel(id) = ev {
+                self.view.canvas.win.buf.forget_window(id);
+            }
+
+            let buf = ev.buffer().or_else(|| self.ed.bufid());
+            let file = buf
+                .and_then(|id| self.ed.buffers().get(id))
+                .and_then(|buf| buf.filename().map(Path::to_path_buf));
+
+            for callback in self.config.triggered(&ev, file.as_deref()) {
+                callback(self, &ev);
             }
         }
 
     }
 
+    /// Dispatches a typed key, or accumulates it into the count or the register.
     /// Returns false if no keybindings have been dispatched.
-    fn dispatch_keybinds(&mut self) -> bool {
+    fn dispatch_typed(&mut self, mode: Mode, key: Key) -> bool {
 
-        let mut found_bind = false;
-        for (bind, action) in self.config.clone().keybinds() {
+        if !matches!(mode, Mode::Insert | Mode::Command | Mode::OperatorPending) && self.pending.is_empty()
+            && self.select_register(key)
+        {
+            return true;
+        }
 
-            let mode = self.ed.buf().unwrap().mode();
-            if bind.mode != mode {
-                continue;
+        if !matches!(mode, Mode::Insert | Mode::Command) && self.pending.is_empty()
+            && key.mods == Modifiers::NoMod
+            && let Some(digit) = DIGITS.iter().position(|&key_code| key_code == key.key_code)
+            && self.push_count(digit)
+        {
+            return true;
+        }
+
+        if !self.config.next_keys(mode, &self.pending).contains(&key) {
+            if self.pending.is_empty() {
+                return false;
+            }
+            self.dirty = true;
+            self.pending.clear();
+            if !self.config.next_keys(mode, &[]).contains(&key) {
+                return true;
             }
+        }
+
+        self.dispatch_key(mode, key);
+        true
+
+    }
 
-            if bind.key.is_active() {
-                action(self);
-                found_bind = true;
+    /// An ambiguous sequence (`g` vs `gg`) resolves to the shorter binding
+    /// once no further key has been typed in time.
+    /// Returns false if there is no pending sequence that timed out.
+    fn dispatch_timeout(&mut self) -> bool {
+
+        if self.pending.is_empty() || self.pending_since.elapsed().as_secs_f64() < self.config.timeout() {
+            return false;
+        }
+
+        self.dirty = true;
+        let mode = self.ed.buf().unwrap().mode();
+        let keys = std::mem::take(&mut self.pending);
+        if let Some(action) = self.config.lookup(mode, &keys).action {
+            self.fire(action);
+        }
+        self.commit();
+        true
+
+    }
+
+    /// Appends `key` to the pending keys, and fires the binding once the sequence is complete.
+    fn dispatch_key(&mut self, mode: Mode, key: Key) {
+
+        self.dirty = true;
+        self.pending.push(key);
+        self.pending_since = Instant::now();
+
+        let lookup = self.config.lookup(mode, &self.pending);
+        if !lookup.is_prefix {
+            self.pending.clear();
+            if let Some(action) = lookup.action {
+                self.fire(action);
             }
+        }
 
+    }
+
+    /// Runs `action`, which replaces the output of the last command.
+    fn fire(&mut self, action: config::Action) {
+        self.ed.cmdline_mut().clear_message();
+        let count = self.count.take();
+        let focus = self.ed.focus();
+        self.ed.expire_paste();
+        action(self, count);
+
+        if let Err(err) = self.ed.store_yanks(self.register) {
+            self.ed.cmdline_mut().set_message(format!("clipboard: {err}"));
+        }
+        // the register applies to the whole operator, which the motion completes
+        if self.ed.buf().is_none_or(|buf| buf.mode() != Mode::OperatorPending) {
+            self.register = None;
+        }
+        self.ed.emit_focus_changes(focus);
+    }
+
+    /// `"` followed by the name of a register selects it for the next action.
+    /// Returns false if the key does not belong to a register selection.
+    fn select_register(&mut self, key: Key) -> bool {
+
+        if self.selecting_register {
+            self.dirty = true;
+            self.selecting_register = false;
+            self.register = key.to_char().filter(|&c| Registers::is_valid(c));
+            return true;
+        }
+
+        if Key::from_char('"') == Some(key) {
+            self.dirty = true;
+            self.selecting_register = true;
+            return true;
         }
 
-        found_bind
+        false
 
     }
 
-    fn render(&mut self) {
 
-        clear_background(COLOR_BG);
+    /// `0` only extends an existing count, so it can still be bound on its own.
+    /// Returns false if the digit does not belong to the count.
+    fn push_count(&mut self, digit: usize) -> bool {
+
+        if digit == 0 && self.count.is_none() {
+            return false;
+        }
+
+        self.dirty = true;
+        self.count = Some(self.count.unwrap_or(0).saturating_mul(10).saturating_add(digit));
+        true
+
+    }
+
+    fn render(&mut self, r: &mut dyn Renderer) -> AppResult<()> {
 
         let status = self.config.statusline()(self);
-        let bounds = Rect::new(0., 0., screen_width(), screen_height());
-        self.renderer.render(bounds, &self.ed, &status);
+        self.view.render(r, &self.ed, &status)?;
+        self.ed.clean();
+        self.dirty = false;
+        Ok(())
+
     }
 
 }
@@ -139,36 +433,612 @@ fn parse_args() -> clap::ArgMatches {
 
     clap::command!()
         .arg(arg!([filename] "filename"))
+        .arg(arg!(-c --config <path> "config file to use instead of ~/.config/ace/config.toml"))
+        .arg(
+            arg!(-b --backend <backend> "what to draw the editor with")
+                .value_parser([ "gui", "tui" ])
+                .default_value("gui")
+        )
         .get_matches()
 }
 
-#[macroquad::main("main")]
-async fn main() -> AppResult<()> {
+fn load_user_config(app: &mut Application, matches: &clap::ArgMatches) {
+    match matches.get_one::<String>("config") {
+        Some(config) => app.load_config(Path::new(config), true),
+        None => if let Some(config) = config::file::default_path() {
+            app.load_config(&config, false);
+        },
+    }
+}
 
-    let matches = parse_args();
+async fn run_gui(matches: clap::ArgMatches) -> AppResult<()> {
 
     let path = matches
         .get_one::<String>("filename")
         .map(PathBuf::from);
 
-    let mut app = Application::new(path).await?;
+    let mut renderer = GuiRenderer::new().await?;
+    let mut app = Application::new(path)?;
+    app.ed.set_clipboard(clipboard::detect(false));
+    // macroquad does not report the repeats of the OS
+    app.repeat = Some(KeyRepeat::default());
+    load_user_config(&mut app, &matches);
+
+    // frames only run on input, or when woken up for a timer
+    while !app.should_quit {
 
-    set_window_size(1600, 900);
+        app.handle_events();
+        for ev in renderer.poll_input() {
+            app.handle_input(ev);
+        }
+        app.repeat_keys();
+        app.dispatch_timeout();
 
-    'running: loop {
+        // every frame is shown, so it has to be drawn even if nothing changed
+        app.render(&mut renderer)?;
 
-        if app.should_quit {
-            break 'running;
+        if let Some(deadline) = app.next_deadline() {
+            renderer.wake_at(deadline);
         }
+        next_frame().await;
+
+    }
+
+    app.shutdown();
+    Ok(())
+}
+
+fn run_tui(matches: clap::ArgMatches) -> AppResult<()> {
+
+    let path = matches
+        .get_one::<String>("filename")
+        .map(PathBuf::from);
+
+    let mut renderer = TuiRenderer::new()?;
+    let mut app = Application::new(path)?;
+    app.ed.set_clipboard(clipboard::detect(true));
+    load_user_config(&mut app, &matches);
+
+    // keys are repeated by the terminal, which does not report releasing them
+    while !app.should_quit {
 
         app.handle_events();
-        app.handle_input();
-        app.render();
+        app.dispatch_timeout();
+        if app.needs_redraw() {
+            app.render(&mut renderer)?;
+        }
 
-        sleep_framerate(FRAMERATE);
-        next_frame().await;
+        let timeout = app.next_deadline().map(|deadline| deadline.saturating_duration_since(Instant::now()));
+        if let Some(ev) = render::tui::read_input(timeout)? {
+            app.handle_input(ev);
+        }
+
+    }
+
+    app.shutdown();
+    Ok(())
+}
+
+fn main() -> AppResult<()> {
+
+    let matches = parse_args();
 
+    if matches.get_one::<String>("backend").is_some_and(|backend| backend == "tui") {
+        return run_tui(matches);
     }
 
+    let conf = macroquad::conf::Conf {
+        miniquad_conf: miniquad::conf::Conf {
+            window_title: "main".to_string(),
+            window_width: 1600,
+            window_height: 900,
+            platform: miniquad::conf::Platform {
+                blocking_event_loop: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        update_on: Some(macroquad::conf::UpdateTrigger {
+            key_down: true,
+            mouse_down: true,
+            mouse_up: true,
+            mouse_wheel: true,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    macroquad::Window::from_config(conf, async {
+        if let Err(err) = run_gui(matches).await {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+    });
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use config::keys::Keybind;
+    use edit::search::Highlight;
+    use edit::register::Register;
+    use render::HeadlessRenderer;
+
+    fn feed(app: &mut Application, keys: &str) {
+        for ev in InputEvent::parse_sequence(keys).unwrap() {
+            app.handle_input(ev);
+        }
+    }
+
+    fn lines(app: &Application) -> Vec<String> {
+        app.ed.buf().unwrap().getlines().map(|line| line.to_string()).collect()
+    }
+
+    fn highlights(app: &Application) -> Vec<Highlight> {
+        app.ed.highlights(app.ed.bufid().unwrap(), 0..usize::MAX)
+    }
+
+    #[test]
+    fn test_headless_insert() {
+        let mut app = Application::new(None::<&str>).unwrap();
+        let mut screen = HeadlessRenderer::new(30, 5);
+
+        feed(&mut app, "ofoo<Esc>");
+        app.render(&mut screen).unwrap();
+
+        assert_eq!(app.ed.buf().unwrap().mode(), Mode::Normal);
+        assert_eq!(lines(&app), [ "", "foo" ]);
+
+        let frame = screen.frame().unwrap();
+        assert_eq!(frame.row(0).trim_end(), "1");
+        assert_eq!(frame.row(1).trim_end(), "2 foo");
+        assert!(frame.row(4).starts_with("normal"));
+        // normal mode moves the cursor back onto the last character
+        assert_eq!(frame.cursor(), Some((4, 1, render::CursorShape::Block)));
+    }
+
+    #[test]
+    fn test_headless_undo() {
+        let mut app = Application::new(None::<&str>).unwrap();
+
+        feed(&mut app, "ofoo<Esc>obar<Esc>");
+        assert_eq!(lines(&app), [ "", "foo", "bar" ]);
+
+        // each insert mode session is undone as a whole
+        feed(&mut app, "u");
+        assert_eq!(lines(&app), [ "", "foo" ]);
+    }
+
+    #[test]
+    fn test_headless_cmdline() {
+        let mut app = Application::new(None::<&str>).unwrap();
+        let mut screen = HeadlessRenderer::new(30, 5);
+
+        feed(&mut app, ":set linenumbers");
+        app.render(&mut screen).unwrap();
+        assert_eq!(screen.frame().unwrap().row(4).trim_end(), ":set linenumbers");
+
+        feed(&mut app, "<CR>");
+        app.render(&mut screen).unwrap();
+        assert_eq!(screen.frame().unwrap().row(4).trim_end(), "linenumbers=relative");
+
+        // actions run by name only in the modes they are meant for
+        feed(&mut app, ":insert.newline<CR>");
+        app.render(&mut screen).unwrap();
+        assert_eq!(lines(&app), [ "" ]);
+        assert!(screen.frame().unwrap().row(4).starts_with("insert.newline cannot be used"));
+
+        feed(&mut app, ":q<CR>");
+        assert!(app.should_quit);
+        assert_eq!(screen.frames().len(), 3);
+    }
+
+    #[test]
+    fn test_headless_search() {
+        let mut app = Application::new(None::<&str>).unwrap();
+        let mut screen = HeadlessRenderer::new(30, 5);
+        app.ed.buf_mut().unwrap().load_buffer(vec![ "foo".into(), "bar Foo".into(), "foo".into() ]);
+        let cursor = |app: &Application| { let c = app.ed.buf().unwrap().cursor(); (c.x, c.y) };
+
+        // the cursor follows the pattern while it is typed, and returns if it is cancelled
+        feed(&mut app, "/fo");
+        assert_eq!(cursor(&app), (4, 1));
+        app.render(&mut screen).unwrap();
+        assert_eq!(screen.frame().unwrap().row(4).trim_end(), "/fo");
+        assert_eq!(screen.frame().unwrap().get(6, 1).unwrap().bg, app.view.theme.search_current);
+        assert_eq!(screen.frame().unwrap().get(2, 2).unwrap().bg, app.view.theme.search);
+        feed(&mut app, "<Esc>");
+        assert_eq!(cursor(&app), (0, 0));
+
+        feed(&mut app, "/fo+<CR>");
+        assert_eq!(cursor(&app), (4, 1));
+
+        // matches are only searched for again once the text changes
+        let id = app.ed.bufid().unwrap();
+        let search = app.ed.search().unwrap().clone();
+        let matches = app.ed.matches(&search, id);
+        assert!(Rc::ptr_eq(&matches, &app.ed.matches(&search, id)));
+        feed(&mut app, "Ofoo<Esc>u");
+        assert!(!Rc::ptr_eq(&matches, &app.ed.matches(&search, id)));
+        assert_eq!(app.ed.matches(&search, id).len(), 3);
+        assert_eq!(cursor(&app), (4, 1));
+        feed(&mut app, "2n");
+        assert_eq!(cursor(&app), (0, 0));
+        assert_eq!(app.ed.cmdline().message(), Some("search hit BOTTOM, continuing at TOP"));
+
+        // `?` reverses the direction of `n`, and an empty pattern repeats the last one
+        feed(&mut app, "?<CR>");
+        assert_eq!(cursor(&app), (0, 2));
+        feed(&mut app, "N");
+        assert_eq!(cursor(&app), (0, 0));
+
+        feed(&mut app, "/x(<CR>");
+        assert!(app.ed.cmdline().message().unwrap().starts_with("invalid pattern"));
+        feed(&mut app, "/baz<CR>");
+        assert_eq!(app.ed.cmdline().message(), Some("pattern not found: baz"));
+        assert_eq!(cursor(&app), (0, 0));
+    }
+
+    #[test]
+    fn test_headless_hlsearch() {
+        let mut app = Application::new(None::<&str>).unwrap();
+        let mut screen = HeadlessRenderer::new(12, 5);
+        let long = format!("{}foo", "x".repeat(20));
+        app.ed.buf_mut().unwrap().load_buffer(vec![ "foo foo".into(), long ]);
+        let bg = |screen: &HeadlessRenderer, x, y| screen.frame().unwrap().get(x, y).unwrap().bg;
+
+        // every match of the last search is highlighted, the one at the cursor distinctly
+        feed(&mut app, "/foo<CR>");
+        assert_eq!(highlights(&app).len(), 3);
+        assert_eq!(app.ed.highlights(app.ed.bufid().unwrap(), 1..2).len(), 1);
+        app.render(&mut screen).unwrap();
+        assert_eq!(bg(&screen, 2, 0), app.view.theme.search);
+        assert_eq!(bg(&screen, 6, 0), app.view.theme.search_current);
+
+        // lines scrolled horizontally only highlight their visible part
+        feed(&mut app, "n");
+        app.render(&mut screen).unwrap();
+        let row = screen.frame().unwrap().row(1);
+        assert_eq!(row, "2 xxxxxxxxxf");
+        let x = row.find('f').unwrap() as u16;
+        assert_eq!(bg(&screen, x, 1), app.view.theme.search_current);
+        assert_ne!(bg(&screen, x - 1, 1), app.view.theme.search_current);
+
+        feed(&mut app, ":noh<CR>");
+        assert!(highlights(&app).is_empty());
+        app.render(&mut screen).unwrap();
+        assert_ne!(bg(&screen, 2, 0), app.view.theme.search);
+
+        // searching again brings them back
+        feed(&mut app, "n");
+        assert_eq!(highlights(&app).len(), 3);
+    }
+
+    #[test]
+    fn test_headless_clipboard() {
+        let mut app = Application::new(None::<&str>).unwrap();
+        app.ed.buf_mut().unwrap().load_buffer(vec![ "foo".into(), "bar".into() ]);
+
+        // a fake provider, which keeps the clipboard in a file
+        let path = std::env::temp_dir().join(format!("ace-headless-clipboard-{}", std::process::id()));
+        let file = path.to_str().unwrap();
+        app.ed.set_clipboard(Box::new(clipboard::External::new(
+            "fake",
+            &[ "sh", "-c", "cat > \"$0\"", file ],
+            &[ "cat", file ],
+        )));
+
+        // lines end in a newline on the clipboard
+        feed(&mut app, "\"+yy");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "foo\n");
+        assert_eq!(app.register(), None);
+
+        // the register lasts until the operator is complete, and only applies once
+        feed(&mut app, "j\"+");
+        assert_eq!(app.register(), Some('+'));
+        feed(&mut app, "yw");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "bar");
+        feed(&mut app, "kyy");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "bar");
+
+        std::fs::write(&path, "baz\n").unwrap();
+        feed(&mut app, "\"*p");
+        assert_eq!(lines(&app), [ "foo", "baz", "bar" ]);
+        feed(&mut app, "p");
+        assert_eq!(lines(&app), [ "foo", "baz", "foo", "bar" ]);
+        std::fs::write(&path, "x").unwrap();
+        feed(&mut app, "\"+P");
+        assert_eq!(lines(&app)[2], "xfoo");
+
+        std::fs::remove_file(&path).unwrap();
+        feed(&mut app, "\"+p");
+        assert!(app.ed.cmdline().message().unwrap().starts_with("clipboard: "));
+        assert_eq!(lines(&app).len(), 4);
+    }
+
+    #[test]
+    fn test_headless_registers() {
+        let mut app = Application::new(None::<&str>).unwrap();
+        app.ed.buf_mut().unwrap().load_buffer(vec![ "foo bar".into(), "baz".into() ]);
+
+        feed(&mut app, "\"ayw\"Ayyjdd");
+        assert_eq!(lines(&app), [ "foo bar" ]);
+
+        // registers are shared between buffers
+        let id = app.ed.buffers_mut().add();
+        let win = app.ed.winid().unwrap();
+        app.ed.windows_mut().get_mut(win).unwrap().set_buf(id);
+        feed(&mut app, "\"ap");
+        assert_eq!(lines(&app), [ "", "foo ", "foo bar" ]);
+        feed(&mut app, "\"1P");
+        assert_eq!(lines(&app), [ "", "baz", "foo ", "foo bar" ]);
+
+        // charwise text goes into the line
+        feed(&mut app, "ywp");
+        assert_eq!(lines(&app)[1], "bbazaz");
+
+        // pasting again replaces the text with older kills
+        feed(&mut app, "<M-y>");
+        assert_eq!(lines(&app), [ "", "baz", "baz", "foo ", "foo bar" ]);
+        feed(&mut app, "<M-y>");
+        assert_eq!(lines(&app), [ "", "baz", "foo bar", "foo ", "foo bar" ]);
+        feed(&mut app, "u");
+        assert_eq!(lines(&app), [ "", "baz", "baz", "foo ", "foo bar" ]);
+        feed(&mut app, "<M-y>");
+        assert_eq!(app.ed.cmdline().message(), Some("previous command was not a paste"));
+
+        // a block is pasted into the lines below, which cycling restores before pasting the older kill
+        feed(&mut app, "gg<C-v>jjly");
+        assert_eq!(app.ed.registers().get(None), Some(&Register::blockwise("\nba\nba")));
+        feed(&mut app, "Gp");
+        assert_eq!(lines(&app)[4..], [ "f  oo bar", " ba", " ba" ]);
+        feed(&mut app, "<M-y>");
+        assert_eq!(lines(&app)[4..], [ "fbazoo bar" ]);
+    }
+
+    #[test]
+    fn test_headless_substitute() {
+        let mut app = Application::new(None::<&str>).unwrap();
+        app.ed.buf_mut().unwrap().load_buffer(vec![ "foo".into(), "bar foo".into(), "foo foo".into() ]);
+
+        // what would be replaced is highlighted while typing
+        feed(&mut app, ":%s/o+/0/");
+        assert_eq!(highlights(&app).len(), 3);
+        feed(&mut app, "g");
+        assert_eq!(highlights(&app).len(), 4);
+        assert_eq!(app.ed.highlights(app.ed.bufid().unwrap(), 2..3).len(), 2);
+
+        // all replacements are undone at once
+        feed(&mut app, "<CR>");
+        assert_eq!(lines(&app), [ "f0", "bar f0", "f0 f0" ]);
+        assert_eq!(app.ed.cmdline().message(), Some("4 substitutions on 3 lines"));
+        assert!(highlights(&app).is_empty());
+        feed(&mut app, "u");
+        assert_eq!(lines(&app), [ "foo", "bar foo", "foo foo" ]);
+
+        feed(&mut app, ":2,3s/(f)oo/\\1\\1/<CR>");
+        assert_eq!(lines(&app), [ "foo", "bar ff", "ff foo" ]);
+        feed(&mut app, ":3,1s/x/y/<CR>");
+        assert_eq!(app.ed.cmdline().message(), Some("pattern not found: x"));
+        feed(&mut app, ":4s/x/y/<CR>");
+        assert_eq!(app.ed.cmdline().message(), Some("invalid range"));
+        feed(&mut app, "u");
+
+        // skip the first, replace the second, and the last one as well
+        feed(&mut app, ":%s/foo/x/gc<CR>");
+        assert_eq!(app.ed.cmdline().message(), Some("replace with x (y/n/a/q/l)?"));
+        feed(&mut app, "nya");
+        assert!(app.ed.confirming().is_none());
+        assert_eq!(lines(&app), [ "foo", "bar x", "x x" ]);
+        feed(&mut app, "u");
+        assert_eq!(lines(&app), [ "foo", "bar foo", "foo foo" ]);
+
+        feed(&mut app, ":%s/foo/x/gc<CR>y<Esc>");
+        assert_eq!(lines(&app), [ "x", "bar foo", "foo foo" ]);
+        assert_eq!(app.ed.cmdline().message(), Some("1 substitution on 1 line"));
+    }
+
+    #[test]
+    fn test_headless_text_input() {
+        let mut app = Application::new(None::<&str>).unwrap();
+
+        // the text of bound keys is not inserted, unlike text without a key
+        feed(&mut app, "i");
+        app.handle_input(InputEvent::Text('ä'));
+        // releasing keys and moving the mouse does nothing
+        app.handle_input(InputEvent::Key { key: "x".parse().unwrap(), state: KeyState::Release, text: None });
+        app.handle_input(InputEvent::Mouse { event: input::MouseEvent::Move, pos: Vec2::ZERO });
+        feed(&mut app, "b<Esc>");
+
+        assert_eq!(lines(&app), [ "äb" ]);
+    }
+
+    #[test]
+    fn test_headless_redraw() {
+        let mut app = Application::new(None::<&str>).unwrap();
+        let mut screen = HeadlessRenderer::new(30, 5);
+
+        assert!(app.needs_redraw());
+        app.render(&mut screen).unwrap();
+        assert!(!app.needs_redraw());
+
+        app.handle_input(InputEvent::Mouse { event: input::MouseEvent::Move, pos: Vec2::ONE });
+        assert!(!app.needs_redraw());
+
+        // the pending key is shown, and times out later on
+        feed(&mut app, "g");
+        assert!(app.needs_redraw());
+        assert!(app.next_deadline().is_some());
+        app.render(&mut screen).unwrap();
+
+        // buffers only count as changed once their text, cursor or mode does
+        feed(&mut app, "u");
+        assert!(!app.ed.buffers().is_dirty());
+
+        // and so do windows and the command line
+        let (win, id) = (app.ed.winid().unwrap(), app.ed.bufid().unwrap());
+        app.ed.windows_mut().get_mut(win).unwrap().set_buf(id);
+        app.ed.cmdline_mut().clear_message();
+        assert!(!app.ed.is_dirty());
+        app.ed.cmdline_mut().set_message("foo");
+        assert!(app.ed.is_dirty());
+        app.render(&mut screen).unwrap();
+        feed(&mut app, "ifoo");
+        assert!(app.ed.buffers().is_dirty());
+        app.render(&mut screen).unwrap();
+
+        app.handle_input(InputEvent::Resize);
+        assert!(app.needs_redraw());
+    }
+
+    #[test]
+    fn test_headless_window_scroll() {
+        let text = (0..20).map(|i| i.to_string()).collect::<Vec<_>>();
+        let render = |app: &mut Application| {
+            let mut screen = HeadlessRenderer::new(30, 5);
+            app.render(&mut screen).unwrap();
+            screen.frame().unwrap().row(0)
+        };
+
+        let mut app = Application::new(None::<&str>).unwrap();
+        app.ed.buf_mut().unwrap().load_buffer(text.clone());
+        feed(&mut app, "G");
+        render(&mut app);
+
+        // a new window taking the id of a deleted one starts out unscrolled
+        let id = app.ed.bufid().unwrap();
+        app.ed.windows_mut().delete();
+        app.ed.windows_mut().add(Some(id));
+        app.handle_events();
+        feed(&mut app, "kk");
+
+        let mut fresh = Application::new(None::<&str>).unwrap();
+        fresh.ed.buf_mut().unwrap().load_buffer(text);
+        feed(&mut fresh, "Gkk");
+        assert_eq!(render(&mut app), render(&mut fresh));
+    }
+
+    #[test]
+    fn test_headless_events() {
+        let mut app = Application::new(None::<&str>).unwrap();
+        assert!(edit::has_events());
+
+        // the default autocmds record buffers being created and deleted
+        feed(&mut app, ":bd<CR>");
+        app.handle_events();
+        assert!(!edit::has_events());
+
+        app.ed.show_messages();
+        let id = app.ed.buffers().ids().last().unwrap();
+        let messages = app.ed.buffers().get(id).unwrap().getlines().map(|line| line.to_string()).collect::<Vec<_>>();
+        assert_eq!(messages, [ "BufNew(0)", "BufDel(0)", "BufNew(1)" ]);
+
+        // a file that cannot be loaded leaves no trace of its buffer
+        app.handle_events();
+        feed(&mut app, ":e /<CR>");
+        assert!(app.ed.cmdline().message().is_some());
+        let events = std::iter::from_fn(edit::next_event).collect::<Vec<_>>();
+        assert!(!events.iter().any(|ev| matches!(ev, EventData::BufNew(_) | EventData::BufDel(_))));
+    }
+
+    #[test]
+    fn test_headless_first_buffer() {
+        let mut app = Application::new(None::<&str>).unwrap();
+
+        // the startup buffer is gone, so the first one is the buffer replacing it
+        feed(&mut app, ":bd<Enter>");
+        let id = app.ed.bufid().unwrap();
+        feed(&mut app, "U");
+        feed(&mut app, "j");
+        assert_eq!(app.ed.bufid(), Some(id));
+        assert!(app.ed.buf().is_some());
+    }
+
+    #[test]
+    fn test_headless_closures() {
+        let mut app = Application::new(None::<&str>).unwrap();
+
+        // bindings can capture what they act on
+        let snippet = String::from("fn main() {}");
+        app.config.keymap(keybind!(Normal, F1, NoMod), move |app, _| app.ed.buf_mut().unwrap().insert_string(&snippet));
+        feed(&mut app, "<F1>");
+        assert_eq!(lines(&app), [ "fn main() {}" ]);
+
+        // all autocmds of an event run, in the order they have been added
+        let order = Rc::new(RefCell::new(Vec::new()));
+        for i in 0..3 {
+            let order = order.clone();
+            app.config.autocmd(edit::event::Event::ModeChanged, move |_, _| order.borrow_mut().push(i));
+        }
+        feed(&mut app, "i<Esc>");
+        app.handle_events();
+        assert_eq!(*order.borrow(), [ 0, 1, 2, 0, 1, 2 ]);
+    }
+
+    #[test]
+    fn test_headless_focus_events() {
+        use EventData::*;
+        use edit::buffer::Cursor;
+
+        let mut app = Application::new(None::<&str>).unwrap();
+        let events = || std::iter::from_fn(edit::next_event).collect::<Vec<_>>();
+        assert_eq!(events(), [
+            BufNew(0), WinNew(0), Init, WinEnter(0), BufEnter(0),
+            CursorMoved { buf: 0, cursor: Cursor::new(0, 0) },
+        ]);
+
+        // the cursor moves once per typed key, no matter how often the action moves it
+        feed(&mut app, "ifoo");
+        assert_eq!(events(), [
+            ModeChanged { old: Mode::Normal, new: Mode::Insert },
+            TextChanged { buf: 0, start: 0, end: 1 },
+            CursorMoved { buf: 0, cursor: Cursor::new(1, 0) },
+            TextChanged { buf: 0, start: 0, end: 1 },
+            CursorMoved { buf: 0, cursor: Cursor::new(2, 0) },
+            TextChanged { buf: 0, start: 0, end: 1 },
+            CursorMoved { buf: 0, cursor: Cursor::new(3, 0) },
+        ]);
+
+        feed(&mut app, "<CR>");
+        assert_eq!(events()[0], TextChanged { buf: 0, start: 0, end: 2 });
+
+        let id = app.ed.buffers_mut().add();
+        app.ed.windows_mut().add(Some(id));
+        events();
+        let focus = app.ed.focus();
+        app.ed.windows_mut().next(true);
+        app.ed.emit_focus_changes(focus);
+        assert_eq!(events(), [
+            BufLeave(0), WinLeave(0), WinEnter(1), BufEnter(1),
+            CursorMoved { buf: 1, cursor: Cursor::new(0, 0) },
+        ]);
+    }
+
+    #[test]
+    fn test_headless_write_events() {
+        use EventData::*;
+
+        let path = std::env::temp_dir().join(format!("ace-write-events-{}", std::process::id()));
+        let mut app = Application::new(Some(&path)).unwrap();
+        assert!(std::iter::from_fn(edit::next_event).any(|ev| ev == BufReadPost(0)));
+
+        // the buffer can still be changed right before it is written
+        app.config.autocmd(edit::event::Event::BufWritePre, |app, _| app.ed.buf_mut().unwrap().insert_string("pre"));
+        feed(&mut app, ":w<CR>");
+        let written = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(written.starts_with("pre"));
+        assert!(std::iter::from_fn(edit::next_event).any(|ev| ev == BufWritePost(0)));
+
+        // a write failing only once the text is flushed is reported as well
+        feed(&mut app, ":w /dev/full<CR>");
+        assert!(app.ed.cmdline().message().is_some());
+        assert!(!std::iter::from_fn(edit::next_event).any(|ev| ev == BufWritePost(0)));
+    }
+
+}
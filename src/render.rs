@@ -1,18 +1,27 @@
-use macroquad::prelude::*;
+use std::fmt;
+use std::io;
 
-use crate::edit::{Editor, Statusline};
+use macroquad::prelude::{Color, Rect, Vec2};
+
+use crate::edit::{Editor, Mode, Statusline};
 
 const PADDING: f32 = 30.;
-const COLOR_WIDGET_AREA_SEL: Color = Color::from_rgba(70, 74, 79, 255);
-const COLOR_WIDGET_AREA:     Color = Color::from_rgba(57, 60, 64, 255);
 
 mod canvas;
 mod buffer;
 mod statusline;
 mod window;
+pub mod grid;
+pub mod gui;
+pub mod headless;
+pub mod tui;
 
 use canvas::CanvasRenderer;
 use statusline::StatuslineRenderer;
+pub use buffer::LineNumberMode;
+pub use gui::GuiRenderer;
+pub use headless::HeadlessRenderer;
+pub use tui::TuiRenderer;
 
 
 
@@ -35,23 +44,116 @@ use statusline::StatuslineRenderer;
 // |                                statusbar                            |
 // +---------------------------------------------------------------------+
 
+/// Colors used for drawing the editor
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    pub background:    Color,
+    pub text:          Color,
+    pub cursor:        Color,
+    pub cursorline:    Color,
+    pub selection:     Color,
+    /// matches of the search, and what a substitution changes
+    pub search:        Color,
+    pub search_current: Color,
+    pub statusline:    Color,
+    pub widget:        Color,
+    pub widget_active: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            background:    Color::from_rgba(40, 43, 46, 255),
+            text:          Color::from_rgba(255, 255, 255, 255),
+            cursor:        Color::from_rgba(186, 194, 204, 255),
+            cursorline:    Color::from_rgba(71, 76, 82, 255),
+            selection:     Color::from_rgba(90, 110, 140, 255),
+            search:        Color::from_rgba(110, 95, 60, 255),
+            search_current: Color::from_rgba(200, 140, 50, 255),
+            statusline:    Color::from_rgba(158, 189, 219, 255),
+            widget:        Color::from_rgba(57, 60, 64, 255),
+            widget_active: Color::from_rgba(70, 74, 79, 255),
+        }
+    }
+}
+
+/// Fonts the editor is drawn with. Backends without fonts draw both the same.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Font {
+    /// buffer contents
+    Mono,
+    /// widgets, eg. the statusline
+    Ui,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextStyle {
+    pub font: Font,
+    pub size: u16,
+    pub color: Color,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CursorShape {
+    #[default] Block,
+    Beam,
+}
+
+/// A surface the editor is drawn onto, which is what distinguishes backends.
+/// Positions and sizes are given in the unit of the backend, ie. pixels
+/// for the GUI and cells for the terminal, so that the layout can be shared.
+pub trait Renderer: fmt::Debug {
+
+    /// Size of the whole surface
+    fn size(&self) -> Vec2;
+
+    /// Converts a distance given in pixels, such as padding, into the unit of the backend.
+    fn scale_pixels(&self, pixels: f32) -> f32;
+
+    fn measure(&self, text: &str, style: TextStyle) -> f32;
+
+    fn line_height(&self, style: TextStyle) -> f32;
+
+    /// Starts a new frame.
+    fn clear(&mut self, color: Color);
+
+    fn draw_rect(&mut self, rect: Rect, color: Color);
+
+    /// Draws `text` with its top left corner at `pos`, cutting off what exceeds `max_width`.
+    fn draw_text(&mut self, text: &str, pos: Vec2, max_width: f32, style: TextStyle);
+
+    /// `rect` covers the character under the cursor.
+    fn draw_cursor(&mut self, rect: Rect, shape: CursorShape, color: Color);
+
+    /// Shows the finished frame.
+    fn present(&mut self) -> io::Result<()>;
+
+}
+
+/// Lays out the editor and holds the settings of how it is drawn,
+/// independently of the [`Renderer`] that does the drawing.
 #[derive(Debug, Clone)]
-pub struct GuiRenderer {
+pub struct View {
     pub canvas: CanvasRenderer,
     pub statusline: StatuslineRenderer,
+    pub theme: Theme,
     padding: f32,
 }
 
-impl GuiRenderer {
-
-    pub async fn new() -> Result<Self, macroquad::Error> {
-        Ok(Self {
-            canvas: CanvasRenderer::new().await?,
-            statusline: StatuslineRenderer::new().await?,
+impl Default for View {
+    fn default() -> Self {
+        Self {
+            canvas: CanvasRenderer::default(),
+            statusline: StatuslineRenderer::default(),
+            theme: Theme::default(),
             padding: PADDING,
-        })
+        }
     }
+}
 
+impl View {
+
+    /// Padding in pixels, which the terminal has no room for.
     #[must_use]
     pub fn padding(&self) -> f32 {
         self.padding
@@ -61,26 +163,45 @@ impl GuiRenderer {
         self.padding = padding;
     }
 
-    pub fn render(&mut self, bounds: Rect, ed: &Editor, statusline: &Statusline) {
+    pub fn render(&mut self, r: &mut dyn Renderer, ed: &Editor, statusline: &Statusline) -> io::Result<()> {
+
+        r.clear(self.theme.background);
 
-        let status_height = self.statusline.fontsize() as f32;
+        let size = r.size();
+        let bounds = Rect::new(0., 0., size.x, size.y);
+        let padding = r.scale_pixels(self.padding);
+        let status_height = self.statusline.height(r, &self.theme);
 
         let bounds_statusline = Rect::new(
-            bounds.x + self.padding,
-            bounds.y + bounds.h - status_height - self.padding,
-            bounds.w - self.padding * 2.,
+            bounds.x + padding,
+            bounds.y + bounds.h - status_height - padding,
+            bounds.w - padding * 2.,
             status_height,
         );
 
         let bounds_canvas = Rect::new(
-            bounds.x + self.padding,
-            bounds.y + self.padding,
-            bounds.w - self.padding * 2.,
-            bounds.h - bounds_statusline.h - self.padding * 2.,
+            bounds.x + padding,
+            bounds.y + padding,
+            bounds.w - padding * 2.,
+            bounds.h - bounds_statusline.h - padding * 2.,
         );
 
-        self.statusline.render(bounds_statusline, statusline);
-        self.canvas.render(bounds_canvas, ed);
+        self.canvas.render(r, bounds_canvas, ed, &self.theme);
+
+        // the command line takes the place of the statusline while typing,
+        // and so does the output of the last command until the next action
+        let cmdline = ed.cmdline();
+        if ed.buf().is_some_and(|buf| buf.mode() == Mode::Command) {
+            let text = format!("{}{}", cmdline.prompt().symbol(), cmdline.text());
+            self.statusline.render_line(r, bounds_statusline, &text, Some(cmdline.cursor() + 1), &self.theme);
+        } else if let Some(msg) = cmdline.message() {
+            self.statusline.render_line(r, bounds_statusline, msg, None, &self.theme);
+        } else {
+            self.statusline.render(r, bounds_statusline, statusline, &self.theme);
+        }
+
+        r.present()
+
     }
 
 }
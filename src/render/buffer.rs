@@ -1,70 +1,80 @@
-use macroquad::prelude::*;
+use std::collections::HashMap;
+use std::ops::Range;
+
+use macroquad::prelude::{Rect, Vec2, vec2};
 use crate::edit::Mode;
 use crate::edit::buffer::{Buffer, Cursor};
-use crate::wrap::{clamp_slice, draw_text_bounded, measure_char};
+use crate::edit::search::{Highlight, HighlightKind};
+use crate::edit::text::{grapheme_count, grapheme_offset};
+use crate::edit::window::WindowID;
+use super::{CursorShape, Font, Renderer, TextStyle, Theme};
 
-const CURSOR_SIZE:      f32   = 3.;
-const COLOR_CURSORLINE: Color = Color::from_rgba(71, 76, 82, 255);
-const COLOR_CURSOR:     Color = Color::from_rgba(186, 194, 204, 255);
-const COLOR_TEXT:       Color = Color::from_rgba(255, 255, 255, 255);
-const FONTPATH:         &str  = "/usr/share/fonts/TTF/JetBrainsMonoNerdFont-Regular.ttf";
 const FONTSIZE:         u16   = 30;
 
 
 
-// TODO: implement
-// different cursor shapes for different modes
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
-pub enum CursorShape {
-    #[default] Block,
-    Beam,
-}
-
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum LineNumberMode {
     #[default] Relative,
     Absolute,
 }
 
+impl std::fmt::Display for LineNumberMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            LineNumberMode::Relative => "relative",
+            LineNumberMode::Absolute => "absolute",
+        })
+    }
+}
+
 /// Information the renderer holds while rendering a single frame
 #[derive(Debug, Clone)]
 struct BufferRenderArgs<'a> {
     buf: &'a Buffer,
     mode: Mode,
+    theme: &'a Theme,
     linecount_vis: usize,
     charcount_vis: usize,
     bounds_buf: Rect,
     bounds_linenumbers: Rect,
     virt: Cursor,
-    params: TextParams<'a>,
+    style: TextStyle,
+    offset: Cursor,
 }
 
 #[derive(Debug, Clone)]
 pub struct BufferRenderer {
     /// Settings
     mode: LineNumberMode,
-    font: Font,
     fontsize: u16,
 
     /// State
-    buf_offset: Cursor,
+    /// scroll offset of each window
+    offsets: HashMap<WindowID, Cursor>,
 }
 
-impl BufferRenderer {
-
-    pub async fn new() -> Result<Self, macroquad::Error> {
-        Ok(Self {
+impl Default for BufferRenderer {
+    fn default() -> Self {
+        Self {
             mode: LineNumberMode::default(),
-            font: load_ttf_font(FONTPATH).await?,
             fontsize: FONTSIZE,
-            buf_offset: Cursor::default(),
-        })
+            offsets: HashMap::new(),
+        }
     }
+}
+
+impl BufferRenderer {
 
     pub fn set_mode(&mut self, mode: LineNumberMode) {
         self.mode = mode;
     }
 
+    #[must_use]
+    pub fn mode(&self) -> LineNumberMode {
+        self.mode
+    }
+
     pub fn set_fontsize(&mut self, fontsize: u16) {
         self.fontsize = fontsize;
     }
@@ -74,59 +84,145 @@ impl BufferRenderer {
         self.fontsize
     }
 
-    fn empty_column_width(&self) -> f32 {
-        measure_char('X', Some(&self.font), self.fontsize, 1.).width
+    /// Drops the scroll offset of a deleted window, as its id may be reused.
+    pub fn forget_window(&mut self, winid: WindowID) {
+        self.offsets.remove(&winid);
     }
 
-    fn textwidth(&self, text: impl AsRef<str>) -> f32 {
-        measure_text(text.as_ref(), Some(&self.font), self.fontsize, 1.).width
+    fn empty_column_width(r: &dyn Renderer, args: &BufferRenderArgs) -> f32 {
+        r.measure("X", args.style)
     }
 
-    fn draw_line_cursor(&self, bounds: Rect, fontsize: f32, virt_y: isize) {
+    /// Position of the top left corner of the visible line `y`, which is relative to the scroll offset
+    fn line_pos(r: &dyn Renderer, bounds: Rect, y: isize, style: TextStyle) -> Vec2 {
+        vec2(bounds.x, bounds.y + y as f32 * r.line_height(style))
+    }
 
-        draw_rectangle(
-            bounds.x,
-            bounds.y + virt_y as f32 * fontsize,
-            bounds.w,
-            fontsize,
-            COLOR_CURSORLINE,
+    fn draw_line_cursor(r: &mut dyn Renderer, args: &BufferRenderArgs) {
+
+        let pos = Self::line_pos(r, args.bounds_buf, args.virt.y, args.style);
+
+        r.draw_rect(
+            Rect::new(pos.x, pos.y, args.bounds_buf.w, r.line_height(args.style)),
+            args.theme.cursorline,
         );
 
     }
 
-    fn draw_cursor(&self, args: &BufferRenderArgs) {
+    fn draw_cursor(r: &mut dyn Renderer, args: &BufferRenderArgs) {
 
-        let fontsize = self.fontsize as f32;
+        // width of all visible chars leading up to cursor
+        let line = args.buf.getline();
+        let start = grapheme_offset(&line, args.offset.x as usize);
+        let end = grapheme_offset(&line, args.buf.cursor().x as usize);
+        let widthsum = r.measure(&line[start.min(end)..end], args.style);
 
-        self.draw_line_cursor(args.bounds_buf, fontsize, args.virt.y);
+        // width of current char
+        let c = args.buf.getchar().unwrap_or(" ".to_string());
 
-        // width of all chars leading up to cursor
-        let line = &args.buf.getlines()[args.buf.cursor().y as usize];
-        let widthsum = self.textwidth(&line[..args.virt.x as usize]);
+        let shape = match args.mode {
+            Mode::Insert => CursorShape::Beam,
+            _            => CursorShape::Block,
+        };
 
-        // width of current char
-        let c = args.buf.getchar().unwrap_or(' ');
+        let pos = Self::line_pos(r, args.bounds_buf, args.virt.y, args.style);
+
+        r.draw_cursor(
+            Rect::new(pos.x + widthsum, pos.y, r.measure(&c, args.style), r.line_height(args.style)),
+            shape,
+            args.theme.cursor,
+        );
+    }
+
+    fn draw_selection(r: &mut dyn Renderer, args: &BufferRenderArgs) {
+
+        let Some(sel) = args.buf.selection() else { return };
+        let (start, end) = sel.bounds();
+
+        let offset = args.offset;
+        let first = start.y.max(offset.y);
+        let last = end.y.min(offset.y + args.linecount_vis as isize - 1);
+
+        for y in first..=last {
+
+            let line = args.buf.getlines().nth(y as usize).unwrap();
+            let width = grapheme_count(&line) as isize;
+            let Some((from, to)) = sel.columns(y, width) else { continue };
+
+            let from = from.max(offset.x);
+            // scrolled out of view, or past the end of a non-empty line
+            if from > to || (from == to && width > 0) { continue }
+
+            let vis = grapheme_offset(&line, offset.x as usize);
+            let a = grapheme_offset(&line, from as usize);
+            let b = grapheme_offset(&line, to as usize);
 
-        let cursor = match args.mode {
-            Mode::Normal => self.textwidth(c.to_string()),
-            Mode::Insert => CURSOR_SIZE
+            // selected empty lines still take up one column
+            let w = if a == b {
+                Self::empty_column_width(r, args)
+            } else {
+                r.measure(&line[a..b], args.style)
+            };
+
+            let pos = Self::line_pos(r, args.bounds_buf, y - offset.y, args.style);
+
+            r.draw_rect(
+                Rect::new(
+                    pos.x + r.measure(&line[vis.min(a)..a], args.style),
+                    pos.y,
+                    w,
+                    r.line_height(args.style),
+                ),
+                args.theme.selection,
+            );
+
+        }
+
+    }
+
+    /// Draws the background of a match, which may be scrolled out of view.
+    fn draw_highlight(r: &mut dyn Renderer, args: &BufferRenderArgs, highlight: Highlight) {
+
+        let m = highlight.range;
+        let y = m.start.y - args.offset.y;
+        if y < 0 || y >= args.linecount_vis as isize { return }
+
+        // clip the match to the visible columns
+        let right = args.offset.x + args.charcount_vis as isize;
+        let from = m.start.x.max(args.offset.x);
+        let to = m.end.x.min(right);
+        let empty = m.start.x == m.end.x;
+        if from > to || from >= right || (from == to && !empty) { return }
+
+        let line = args.buf.line(m.start.y as usize);
+        let vis = grapheme_offset(&line, args.offset.x as usize);
+        let a = grapheme_offset(&line, from as usize);
+        let b = grapheme_offset(&line, to as usize);
+
+        // empty matches still take up one column
+        let w = if a == b {
+            Self::empty_column_width(r, args)
+        } else {
+            r.measure(&line[a..b], args.style)
         };
 
-        // char cursor
-        draw_rectangle(
-            args.bounds_buf.x + widthsum,
-            args.bounds_buf.y + args.virt.y as f32 * fontsize,
-            cursor,
-            fontsize,
-            COLOR_CURSOR,
+        let pos = Self::line_pos(r, args.bounds_buf, y, args.style);
+
+        r.draw_rect(
+            Rect::new(pos.x + r.measure(&line[vis.min(a)..a], args.style), pos.y, w, r.line_height(args.style)),
+            match highlight.kind {
+                HighlightKind::Match   => args.theme.search,
+                HighlightKind::Current => args.theme.search_current,
+            },
         );
+
     }
 
-    fn draw_gutter(&mut self, args: &BufferRenderArgs, i: usize) {
+    fn draw_gutter(&self, r: &mut dyn Renderer, args: &BufferRenderArgs, i: usize) {
 
         let bounds = args.bounds_linenumbers;
 
-        let abs = i + self.buf_offset.y as usize;
+        let abs = i + args.offset.y as usize;
         let is_current = args.buf.cursor().y as usize == abs;
 
         let linenum = match self.mode {
@@ -140,88 +236,85 @@ impl BufferRenderer {
         };
 
         let text = linenum.to_string();
+        let pos = Self::line_pos(r, bounds, i as isize, args.style);
 
-        draw_text_bounded(
+        r.draw_text(
             &text,
-            bounds.x + bounds.w - self.textwidth(&text) - self.empty_column_width(),
-            bounds.y + i as f32 * self.fontsize as f32,
-            args.params.clone(),
-            bounds.w
+            vec2(bounds.x + bounds.w - r.measure(&text, args.style) - Self::empty_column_width(r, args), pos.y),
+            bounds.w,
+            args.style,
         );
 
     }
 
 
-    fn draw_lines(&mut self, args: &BufferRenderArgs) {
+    fn draw_lines(&self, r: &mut dyn Renderer, args: &BufferRenderArgs) {
 
-        let y = self.buf_offset.y as usize;
+        let y = args.offset.y as usize;
         // edge-case: deleting lines when scrolled to the end of buffer,
         // hence clamping to document length
         let len = (y + args.linecount_vis)
             .min(args.buf.getlines().len());
 
-        let lines = &args.buf.getlines()[y..len];
+        let lines = args.buf.getlines().skip(y).take(len - y);
 
-        for (i, line) in lines.iter().enumerate() {
+        for (i, line) in lines.enumerate() {
 
-            let line = &line[(self.buf_offset.x as usize)
-                .min(line.len())..];
+            let line = &line[grapheme_offset(&line, args.offset.x as usize)..];
 
             // TODO: make char under cursor black
-            draw_text_bounded(
+            r.draw_text(
                 line,
-                args.bounds_buf.x,
-                args.bounds_buf.y + i as f32 * self.fontsize as f32,
-                args.params.clone(),
-                args.bounds_buf.w
+                Self::line_pos(r, args.bounds_buf, i as isize, args.style),
+                args.bounds_buf.w,
+                args.style,
             );
 
-            self.draw_gutter(args, i);
+            self.draw_gutter(r, args, i);
 
         }
     }
 
-    fn check_cursor_x(&mut self, args: &BufferRenderArgs) {
+    /// Scrolls by how much the cursor moved out of the visible part of the buffer.
+    fn scroll(offset: &mut Cursor, args: &BufferRenderArgs) {
 
-        // TODO:
-        let diff = args.virt.x - args.charcount_vis as isize + 1;
-
-        if diff > 0 {
-            self.buf_offset.x += diff;
+        let diff_y = args.virt.y - args.linecount_vis as isize + 1;
+        if diff_y > 0 {
+            offset.y += diff_y;
+        }
+        if args.virt.y < 0 {
+            offset.y += args.virt.y;
         }
 
+        // TODO:
+        let diff_x = args.virt.x - args.charcount_vis as isize + 1;
+        if diff_x > 0 {
+            offset.x += diff_x;
+        }
         if args.virt.x < 0 {
-            self.buf_offset.x += args.virt.x;
+            offset.x += args.virt.x;
         }
 
     }
 
-    fn check_cursor_y(&mut self, args: &BufferRenderArgs) {
-
-        // if cursor is out-of-bounds, move it back by how much it moved out-of-bounds
-        let diff = args.virt.y - args.linecount_vis as isize + 1;
-
-        if diff > 0 {
-            self.buf_offset.y += diff;
-        }
-
-        if args.virt.y < 0 {
-            self.buf_offset.y += args.virt.y;
-        }
-
+    /// Scrolls the window to the cursor, returning the lines it shows.
+    pub fn visible_lines(&mut self, r: &dyn Renderer, bounds: Rect, winid: WindowID, buf: &Buffer, theme: &Theme) -> Range<usize> {
+        let args = self.layout(r, bounds, winid, buf, buf.mode(), theme);
+        let first = args.offset.y as usize;
+        first..first + args.linecount_vis
     }
 
-    pub fn render(&mut self, bounds: Rect, buf: &Buffer, mode: Mode, active: bool) {
+    /// Lays out the window, scrolling it such that the cursor is visible.
+    fn layout<'a>(&mut self, r: &dyn Renderer, bounds: Rect, winid: WindowID, buf: &'a Buffer, mode: Mode, theme: &'a Theme) -> BufferRenderArgs<'a> {
 
-        let font = self.font.clone();
-        let params = TextParams {
-            font:      Some(&font),
-            font_size: self.fontsize,
-            color:     COLOR_TEXT,
-            ..Default::default()
+        let style = TextStyle {
+            font:  Font::Mono,
+            size:  self.fontsize,
+            color: theme.text,
         };
 
-        let column_len = self.textwidth(buf.getlines().len().to_string()) + self.empty_column_width();
+        let empty_column_width = r.measure("X", style);
+        let column_len = r.measure(&buf.getlines().len().to_string(), style) + empty_column_width;
 
         let bounds_gutter = Rect { w: column_len, ..bounds };
 
@@ -232,41 +325,63 @@ impl BufferRenderer {
         };
 
         // the amount of lines that can fit onto the screen
-        let linecount_vis = ((bounds_buf.h / self.fontsize as f32) as usize)
+        let linecount_vis = ((bounds_buf.h / r.line_height(style)) as usize)
             .min(buf.getlines().len());
 
         // the amount of chars that can fit onto the current line
         // TODO: support for non-monospace fonts
-        // let charcount_vis = clamp_slice(buf.getline(), bounds_buf.w, params.clone()).len();
-        let charcount_vis = (bounds_buf.w / self.empty_column_width()) as usize;
+        let charcount_vis = (bounds_buf.w / empty_column_width) as usize;
+
+        let offset = self.offsets.entry(winid).or_default();
 
         // absolute cursor position mapped to the
         // actual visible bounds of the buffer (virtual cursor)
-        let virt = buf.cursor() - self.buf_offset;
+        let virt = buf.cursor() - *offset;
 
         let mut args = BufferRenderArgs {
             buf,
             mode,
+            theme,
             linecount_vis,
             charcount_vis,
             bounds_buf,
             bounds_linenumbers: bounds_gutter,
             virt,
-            params,
+            style,
+            offset: *offset,
         };
 
-        self.check_cursor_y(&args);
-        self.check_cursor_x(&args);
+        Self::scroll(offset, &args);
 
         // recalculate cursor, if offset changed, otherwise there will be
         // a cursor jumping effect at the top and bottom
-        args.virt = buf.cursor() - self.buf_offset;
+        args.offset = *offset;
+        args.virt = buf.cursor() - *offset;
+        args
+
+    }
+
+    /// Draws `buf`, along with the `highlights` of the lines it shows (see [`Self::visible_lines`]).
+    #[allow(clippy::too_many_arguments)]
+    pub fn render(&mut self, r: &mut dyn Renderer, bounds: Rect, winid: WindowID, buf: &Buffer, mode: Mode, active: bool, highlights: &[Highlight], theme: &Theme) {
+
+        let args = self.layout(r, bounds, winid, buf, mode, theme);
+
+        if active {
+            Self::draw_line_cursor(r, &args);
+        }
+
+        Self::draw_selection(r, &args);
+
+        for &highlight in highlights {
+            Self::draw_highlight(r, &args, highlight);
+        }
 
         if active {
-            self.draw_cursor(&args);
+            Self::draw_cursor(r, &args);
         }
 
-        self.draw_lines(&args);
+        self.draw_lines(r, &args);
 
     }
 }
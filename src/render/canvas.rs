@@ -1,31 +1,24 @@
-use macroquad::prelude::*;
+use macroquad::prelude::Rect;
 
 use crate::edit::Editor;
+use super::{Renderer, Theme};
 use super::window::WindowRenderer;
 
 
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct CanvasRenderer {
     pub win: WindowRenderer,
 }
 
 impl CanvasRenderer {
 
-    pub async fn new() -> Result<Self, macroquad::Error> {
-        Ok(Self {
-            win: WindowRenderer::new().await?,
-        })
-    }
-
-    pub fn render(&mut self, bounds: Rect, ed: &Editor) {
+    pub fn render(&mut self, r: &mut dyn Renderer, bounds: Rect, ed: &Editor, theme: &Theme) {
 
         let len = ed.windows().count();
         let width = bounds.w / len as f32;
 
-        // TODO: refactor
-        let windows = ed.windows().windows.clone();
-        for (idx, (winid, win)) in windows.iter().enumerate() {
+        for (idx, (winid, win)) in ed.windows().windows.iter().enumerate() {
 
             let win_bounds = Rect {
                 w: width,
@@ -35,7 +28,7 @@ impl CanvasRenderer {
 
             let current = ed.windows().winid().unwrap();
             let active = *winid == current;
-            self.win.render(win_bounds, active, ed, win);
+            self.win.render(r, win_bounds, active, ed, *winid, win, theme);
         }
     }
 
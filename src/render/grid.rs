@@ -0,0 +1,246 @@
+use macroquad::prelude::{Color, Rect, Vec2};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use super::CursorShape;
+
+
+
+/// Number of cells a tab is expanded to
+const TAB_WIDTH: usize = 4;
+
+/// Shown in place of control characters, which would move the terminal's cursor
+const PLACEHOLDER: &str = "\u{fffd}";
+
+/// A single character cell
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cell {
+    /// grapheme cluster shown in the cell, which is empty if the cell is
+    /// covered by the wide grapheme to its left
+    pub symbol: String,
+    pub fg: Color,
+    pub bg: Color,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            symbol: " ".to_string(),
+            fg: Color::from_rgba(255, 255, 255, 255),
+            bg: Color::from_rgba(0, 0, 0, 255),
+        }
+    }
+}
+
+/// A screen of character cells, which is drawn into
+/// by backends without pixels. Positions are rounded to the nearest cell, so that
+/// areas sharing an edge (eg. windows splitting up the canvas) neither overlap nor leave gaps.
+#[derive(Debug, Clone, Default)]
+pub struct Grid {
+    width: u16,
+    height: u16,
+    cells: Vec<Cell>,
+    /// position and shape of the cursor
+    cursor: Option<(u16, u16, CursorShape)>,
+}
+
+impl Grid {
+
+    #[must_use]
+    pub fn new(width: u16, height: u16) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![ Cell::default(); width as usize * height as usize ],
+            cursor: None,
+        }
+    }
+
+    #[must_use]
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    #[must_use]
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    #[must_use]
+    pub fn get(&self, x: u16, y: u16) -> Option<&Cell> {
+        if x >= self.width || y >= self.height { return None }
+        self.cells.get(y as usize * self.width as usize + x as usize)
+    }
+
+    fn get_mut(&mut self, x: u16, y: u16) -> Option<&mut Cell> {
+        if x >= self.width || y >= self.height { return None }
+        self.cells.get_mut(y as usize * self.width as usize + x as usize)
+    }
+
+    #[must_use]
+    pub fn cursor(&self) -> Option<(u16, u16, CursorShape)> {
+        self.cursor
+    }
+
+    /// Places the cursor at the top left corner of `rect`.
+    pub fn set_cursor(&mut self, rect: Rect, shape: CursorShape) {
+        self.cursor = Some((cell(rect.x), cell(rect.y), shape));
+    }
+
+    /// Resets all cells to blanks of the given color, resizing the grid.
+    pub fn clear(&mut self, width: u16, height: u16, color: Color) {
+        *self = Self::new(width, height);
+        self.fill(Rect::new(0., 0., width as f32, height as f32), color);
+    }
+
+    /// Blanks the cells covered by `rect`.
+    pub fn fill(&mut self, rect: Rect, color: Color) {
+        let (x0, x1) = (cell(rect.x), cell(rect.x + rect.w));
+        let (y0, y1) = (cell(rect.y), cell(rect.y + rect.h));
+
+        for y in y0..y1 {
+            for x in x0..x1 {
+                if let Some(cell) = self.get_mut(x, y) {
+                    cell.symbol = " ".to_string();
+                    cell.bg = color;
+                }
+            }
+        }
+    }
+
+    /// Writes `text` starting at `pos`, keeping the background of the cells.
+    /// Wide graphemes take up two cells, and are left out if only one is left.
+    pub fn print(&mut self, text: &str, pos: Vec2, max_width: f32, color: Color) {
+        let (mut x, y) = (cell(pos.x) as usize, cell(pos.y));
+        let end = (cell(pos.x + max_width) as usize).min(self.width as usize);
+
+        for grapheme in text.graphemes(true) {
+            let (symbol, width) = display(grapheme);
+            if x + width > end { break }
+
+            for i in 0..width {
+                // a tab is drawn as spaces, the rest of a wide grapheme as continuation cells
+                let symbol = match i {
+                    0                  => symbol,
+                    _ if symbol == " " => " ",
+                    _                  => "",
+                };
+                self.put(x + i, y, symbol, color);
+            }
+            x += width;
+        }
+    }
+
+    /// Sets a single cell, blanking what is left of a wide grapheme it overwrites.
+    fn put(&mut self, x: usize, y: u16, symbol: &str, color: Color) {
+        let x = x as u16;
+        if self.get(x, y).is_some_and(|cell| cell.symbol.is_empty()) && x > 0 {
+            let lead = self.get_mut(x - 1, y).unwrap();
+            lead.symbol = " ".to_string();
+        }
+        if !symbol.is_empty() && self.get(x + 1, y).is_some_and(|cell| cell.symbol.is_empty()) {
+            let rest = self.get_mut(x + 1, y).unwrap();
+            rest.symbol = " ".to_string();
+        }
+        if let Some(cell) = self.get_mut(x, y) {
+            cell.symbol = symbol.to_string();
+            cell.fg = color;
+        }
+    }
+
+    /// Returns the symbols of row `y`.
+    #[must_use]
+    pub fn row(&self, y: u16) -> String {
+        (0..self.width)
+            .filter_map(|x| self.get(x, y))
+            .map(|cell| cell.symbol.as_str())
+            .collect()
+    }
+
+}
+
+/// Number of cells `text` takes up when printed
+#[must_use]
+pub fn text_width(text: &str) -> usize {
+    text.graphemes(true).map(|grapheme| display(grapheme).1).sum()
+}
+
+/// What a grapheme is drawn as, and the number of cells it takes up.
+/// Tabs are expanded to spaces and other control characters are replaced,
+/// so that they cannot move the cursor of the terminal.
+fn display(grapheme: &str) -> (&str, usize) {
+    if grapheme == "\t" {
+        (" ", TAB_WIDTH)
+    } else if grapheme.chars().any(char::is_control) {
+        (PLACEHOLDER, 1)
+    } else {
+        (grapheme, grapheme.width().clamp(1, 2))
+    }
+}
+
+/// Rounds a position onto the grid. Negative positions are clamped to 0.
+fn cell(pos: f32) -> u16 {
+    pos.round().clamp(0., u16::MAX as f32) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use macroquad::prelude::vec2;
+
+    #[test]
+    fn test_grid_draw() {
+        let red = Color::from_rgba(255, 0, 0, 255);
+        let mut grid = Grid::new(6, 2);
+
+        grid.fill(Rect::new(0., 0., 3., 1.), red);
+        grid.print("hällo wörld", vec2(1., 0.), 4., red);
+
+        assert_eq!(grid.row(0), " häll ");
+        assert_eq!(grid.get(3, 0).unwrap().bg, Cell::default().bg);
+        assert_eq!(grid.get(2, 0).unwrap().bg, red);
+        // out of bounds drawing is cut off
+        grid.print("abc", vec2(4., 1.), 10., red);
+        assert_eq!(grid.row(1), "    ab");
+    }
+
+    #[test]
+    fn test_grid_split() {
+        // three windows splitting up 8 columns
+        let mut grid = Grid::new(8, 1);
+        let width = 8. / 3.;
+        for (i, c) in ["a", "b", "c"].into_iter().enumerate() {
+            grid.fill(Rect::new(i as f32 * width, 0., width, 1.), Color::from_rgba(i as u8, 0, 0, 255));
+            grid.print(&c.repeat(3), vec2(i as f32 * width, 0.), width, Cell::default().fg);
+        }
+        assert_eq!(grid.row(0), "aaabbccc");
+    }
+
+    #[test]
+    fn test_grid_width() {
+        let fg = Cell::default().fg;
+        let mut grid = Grid::new(8, 3);
+
+        // wide graphemes are followed by a continuation cell
+        grid.print("a日本b", vec2(0., 0.), 8., fg);
+        assert_eq!(grid.row(0), "a日本b  ");
+        assert_eq!(grid.get(2, 0).unwrap().symbol, "");
+        assert_eq!(text_width("a日本b"), 6);
+
+        // overwriting half of a wide grapheme blanks the other half
+        grid.print("x", vec2(2., 0.), 1., fg);
+        assert_eq!(grid.row(0), "a x本b  ");
+        grid.print("y", vec2(3., 0.), 1., fg);
+        assert_eq!(grid.row(0), "a xy b  ");
+
+        // a wide grapheme does not fit into a single cell
+        grid.print("ab日", vec2(0., 1.), 3., fg);
+        assert_eq!(grid.row(1), "ab      ");
+
+        // tabs are expanded, control characters replaced
+        grid.print("\ta\x1bb", vec2(0., 2.), 8., fg);
+        assert_eq!(grid.row(2), "    a\u{fffd}b ");
+        assert_eq!(text_width("\ta\x1b"), 6);
+    }
+
+}
@@ -0,0 +1,200 @@
+use std::io;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Instant;
+
+use macroquad::prelude::*;
+use macroquad::miniquad::window::schedule_update;
+
+use crate::config::keys::{Key, Modifiers};
+use crate::input::{InputEvent, KeyState, MouseEvent};
+use crate::wrap::{draw_text_bounded, is_ctrl_down, is_meta_down, is_shift_down, is_super_down};
+
+use super::{CursorShape, Renderer, TextStyle};
+
+const CURSOR_SIZE:      f32   = 3.;
+const FONTPATH_MONO:    &str  = "/usr/share/fonts/TTF/JetBrainsMonoNerdFont-Regular.ttf";
+const FONTPATH_UI:      &str  = "/usr/share/fonts/TTF/Roboto-Regular.ttf";
+
+
+
+/// Draws into the macroquad window.
+#[derive(Debug, Clone)]
+pub struct GuiRenderer {
+    mono: Font,
+    ui: Font,
+    /// mouse position of the last frame
+    mouse: Vec2,
+    /// deadlines at which the event loop is woken up
+    waker: mpsc::Sender<Instant>,
+}
+
+impl GuiRenderer {
+
+    pub async fn new() -> Result<Self, macroquad::Error> {
+        Ok(Self {
+            mono: load_ttf_font(FONTPATH_MONO).await?,
+            ui:   load_ttf_font(FONTPATH_UI).await?,
+            mouse: Vec2::ZERO,
+            waker: spawn_waker(),
+        })
+    }
+
+    /// Runs another frame at `deadline` even if there is no input,
+    /// replacing the previous deadline. The event loop only wakes up for input otherwise.
+    pub fn wake_at(&self, deadline: Instant) {
+        // the waker only stops once the renderer is gone
+        let _ = self.waker.send(deadline);
+    }
+
+    /// Collects the input of the current frame, which macroquad only offers by polling.
+    /// Keys are not repeated, as macroquad does not report the repeats of the OS.
+    pub fn poll_input(&mut self) -> Vec<InputEvent> {
+
+        let mods = modifiers();
+        let mut chars: Vec<char> = std::iter::from_fn(get_char_pressed)
+            .filter(|c| !c.is_control())
+            .collect();
+
+        let mut events = Vec::new();
+
+        for key_code in get_keys_pressed() {
+            let text = take_char(&mut chars, key_code).filter(|_| (mods - Modifiers::Shift).is_empty());
+            events.push(InputEvent::Key { key: Key::new(key_code, mods), state: KeyState::Press, text });
+        }
+
+        // characters repeated by the OS for keys held down
+        chars.retain(|&c| {
+            Key::from_char(c).is_none_or(|key| !is_key_down(key.key_code))
+        });
+        events.extend(chars.into_iter().map(InputEvent::Text));
+
+        for key_code in get_keys_released() {
+            events.push(InputEvent::Key { key: Key::new(key_code, mods), state: KeyState::Release, text: None });
+        }
+
+        let pos = mouse_position().into();
+        for button in [ MouseButton::Left, MouseButton::Right, MouseButton::Middle ] {
+            if is_mouse_button_pressed(button) {
+                events.push(InputEvent::Mouse { event: MouseEvent::Press(button), pos });
+            }
+            if is_mouse_button_released(button) {
+                events.push(InputEvent::Mouse { event: MouseEvent::Release(button), pos });
+            }
+        }
+        if pos != self.mouse {
+            self.mouse = pos;
+            events.push(InputEvent::Mouse { event: MouseEvent::Move, pos });
+        }
+        let scroll: Vec2 = mouse_wheel().into();
+        if scroll != Vec2::ZERO {
+            events.push(InputEvent::Mouse { event: MouseEvent::Scroll(scroll), pos });
+        }
+
+        events
+
+    }
+
+    fn font(&self, font: super::Font) -> &Font {
+        match font {
+            super::Font::Mono => &self.mono,
+            super::Font::Ui   => &self.ui,
+        }
+    }
+
+    fn params(&self, style: TextStyle) -> TextParams<'_> {
+        TextParams {
+            font:      Some(self.font(style.font)),
+            font_size: style.size,
+            color:     style.color,
+            ..Default::default()
+        }
+    }
+
+}
+
+impl Renderer for GuiRenderer {
+
+    fn size(&self) -> Vec2 {
+        vec2(screen_width(), screen_height())
+    }
+
+    fn scale_pixels(&self, pixels: f32) -> f32 {
+        pixels
+    }
+
+    fn measure(&self, text: &str, style: TextStyle) -> f32 {
+        measure_text(text, Some(self.font(style.font)), style.size, 1.).width
+    }
+
+    fn line_height(&self, style: TextStyle) -> f32 {
+        style.size as f32
+    }
+
+    fn clear(&mut self, color: Color) {
+        clear_background(color);
+    }
+
+    fn draw_rect(&mut self, rect: Rect, color: Color) {
+        draw_rectangle(rect.x, rect.y, rect.w, rect.h, color);
+    }
+
+    fn draw_text(&mut self, text: &str, pos: Vec2, max_width: f32, style: TextStyle) {
+        draw_text_bounded(text, pos.x, pos.y, self.params(style), max_width);
+    }
+
+    fn draw_cursor(&mut self, rect: Rect, shape: CursorShape, color: Color) {
+        let w = match shape {
+            CursorShape::Block => rect.w,
+            CursorShape::Beam  => CURSOR_SIZE,
+        };
+        draw_rectangle(rect.x, rect.y, w, rect.h, color);
+    }
+
+    /// Frames are shown by macroquad at the end of the main loop.
+    fn present(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+}
+
+fn modifiers() -> Modifiers {
+    let mut mods = Modifiers::NoMod;
+    if is_shift_down() { mods |= Modifiers::Shift }
+    if is_ctrl_down()  { mods |= Modifiers::Ctrl }
+    if is_meta_down()  { mods |= Modifiers::Meta }
+    if is_super_down() { mods |= Modifiers::Super }
+    mods
+}
+
+/// Removes the character typed by `key_code` from the characters of this frame,
+/// as macroquad reports them separately from the keys.
+fn take_char(chars: &mut Vec<char>, key_code: KeyCode) -> Option<char> {
+    let idx = chars.iter().position(|&c| Key::from_char(c).is_some_and(|key| key.key_code == key_code))?;
+    Some(chars.remove(idx))
+}
+
+/// Wakes up the blocking event loop at the deadlines sent to it, from another thread.
+fn spawn_waker() -> mpsc::Sender<Instant> {
+    let (tx, rx) = mpsc::channel::<Instant>();
+
+    thread::spawn(move || {
+        let mut deadline: Option<Instant> = None;
+        loop {
+            let received = match deadline {
+                Some(deadline) => rx.recv_timeout(deadline.saturating_duration_since(Instant::now())),
+                None           => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            match received {
+                Ok(next) => deadline = Some(next),
+                Err(RecvTimeoutError::Timeout) => {
+                    deadline = None;
+                    schedule_update();
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+    });
+
+    tx
+}
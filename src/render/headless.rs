@@ -0,0 +1,92 @@
+use std::io;
+
+use macroquad::prelude::{Color, Rect, Vec2, vec2};
+
+use super::{CursorShape, Renderer, TextStyle};
+use super::grid::{self, Grid};
+
+
+
+/// Draws into memory instead of a screen, keeping every presented frame,
+/// so the editor can be run and inspected without a window or terminal.
+/// Measures like the terminal, in cells.
+#[derive(Debug, Clone)]
+pub struct HeadlessRenderer {
+    width: u16,
+    height: u16,
+    /// frame currently being drawn
+    grid: Grid,
+    frames: Vec<Grid>,
+}
+
+impl HeadlessRenderer {
+
+    #[must_use]
+    pub fn new(width: u16, height: u16) -> Self {
+        Self {
+            width,
+            height,
+            grid: Grid::new(width, height),
+            frames: Vec::new(),
+        }
+    }
+
+    /// All frames presented so far, oldest first.
+    #[must_use]
+    pub fn frames(&self) -> &[Grid] {
+        &self.frames
+    }
+
+    /// The last presented frame
+    #[must_use]
+    pub fn frame(&self) -> Option<&Grid> {
+        self.frames.last()
+    }
+
+    pub fn resize(&mut self, width: u16, height: u16) {
+        self.width = width;
+        self.height = height;
+    }
+
+}
+
+impl Renderer for HeadlessRenderer {
+
+    fn size(&self) -> Vec2 {
+        vec2(self.width as f32, self.height as f32)
+    }
+
+    fn scale_pixels(&self, _: f32) -> f32 {
+        0.
+    }
+
+    fn measure(&self, text: &str, _: TextStyle) -> f32 {
+        grid::text_width(text) as f32
+    }
+
+    fn line_height(&self, _: TextStyle) -> f32 {
+        1.
+    }
+
+    fn clear(&mut self, color: Color) {
+        self.grid.clear(self.width, self.height, color);
+    }
+
+    fn draw_rect(&mut self, rect: Rect, color: Color) {
+        self.grid.fill(rect, color);
+    }
+
+    fn draw_text(&mut self, text: &str, pos: Vec2, max_width: f32, style: TextStyle) {
+        self.grid.print(text, pos, max_width, style.color);
+    }
+
+    fn draw_cursor(&mut self, rect: Rect, shape: CursorShape, _: Color) {
+        self.grid.set_cursor(rect, shape);
+    }
+
+    fn present(&mut self) -> io::Result<()> {
+        self.frames.push(self.grid.clone());
+        Ok(())
+    }
+
+}
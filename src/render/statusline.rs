@@ -1,75 +1,90 @@
-use macroquad::prelude::*;
+use macroquad::prelude::{Rect, vec2};
 use crate::edit::Statusline;
-use crate::wrap::{draw_rectangle_rect, draw_text_bounded};
-use super::COLOR_WIDGET_AREA;
+use super::{CursorShape, Font, Renderer, TextStyle, Theme};
 
-const COLOR_STATUSLINE: Color = Color::from_rgba(158, 189, 219, 255);
-const FONTPATH:         &str  = "/usr/share/fonts/TTF/Roboto-Regular.ttf";
 const FONTSIZE:         u16   = 30;
 
 
 
 #[derive(Debug, Clone)]
 pub struct StatuslineRenderer {
-    font: Font,
     fontsize: u16,
 }
 
-impl StatuslineRenderer {
-
-    pub async fn new() -> Result<Self, macroquad::Error> {
-        Ok(Self {
-            font: load_ttf_font(FONTPATH).await?,
-            fontsize: FONTSIZE,
-        })
+impl Default for StatuslineRenderer {
+    fn default() -> Self {
+        Self { fontsize: FONTSIZE }
     }
+}
+
+impl StatuslineRenderer {
 
     pub fn fontsize(&self) -> u16 {
         self.fontsize
     }
 
-    fn textwidth(&self, text: impl AsRef<str>) -> f32 {
-        measure_text(text.as_ref(), Some(&self.font), self.fontsize, 1.).width
+    pub fn set_fontsize(&mut self, fontsize: u16) {
+        self.fontsize = fontsize;
+    }
+
+    fn style(&self, theme: &Theme) -> TextStyle {
+        TextStyle {
+            font:  Font::Ui,
+            size:  self.fontsize,
+            color: theme.statusline,
+        }
     }
 
-    pub fn render(&mut self, bounds: Rect, statusline: &Statusline) {
+    pub fn height(&self, r: &dyn Renderer, theme: &Theme) -> f32 {
+        r.line_height(self.style(theme))
+    }
 
-        draw_rectangle_rect(bounds, COLOR_WIDGET_AREA);
+    pub fn render(&mut self, r: &mut dyn Renderer, bounds: Rect, statusline: &Statusline, theme: &Theme) {
 
-        let params = TextParams {
-            font:      Some(&self.font),
-            font_size: self.fontsize,
-            color:     COLOR_STATUSLINE,
-            ..Default::default()
-        };
+        r.draw_rect(bounds, theme.widget);
 
-        draw_text_bounded(
+        let style = self.style(theme);
+
+        r.draw_text(
             &statusline.left,
-            bounds.x,
-            bounds.y,
-            params.clone(),
-            bounds.w
+            vec2(bounds.x, bounds.y),
+            bounds.w,
+            style,
         );
 
-        let width = self.textwidth(&statusline.center);
+        let width = r.measure(&statusline.center, style);
 
-        draw_text_bounded(
+        r.draw_text(
             &statusline.center,
-            bounds.x + bounds.w / 2. - width / 2.,
-            bounds.y,
-            params.clone(),
-            bounds.w
+            vec2(bounds.x + bounds.w / 2. - width / 2., bounds.y),
+            bounds.w,
+            style,
         );
 
-        let width = self.textwidth(&statusline.right);
+        let width = r.measure(&statusline.right, style);
 
-        draw_text_bounded(
+        r.draw_text(
             &statusline.right,
-            bounds.x + bounds.w - width,
-            bounds.y,
-            params,
-            bounds.w
+            vec2(bounds.x + bounds.w - width, bounds.y),
+            bounds.w,
+            style,
         );
 
     }
+
+    /// Draws a single line of text in place of the statusline, eg. the command line.
+    /// `cursor` is a byte offset into `text`.
+    pub fn render_line(&mut self, r: &mut dyn Renderer, bounds: Rect, text: &str, cursor: Option<usize>, theme: &Theme) {
+
+        r.draw_rect(bounds, theme.widget);
+
+        let style = self.style(theme);
+        r.draw_text(text, vec2(bounds.x, bounds.y), bounds.w, style);
+
+        if let Some(cursor) = cursor {
+            let x = bounds.x + r.measure(&text[..cursor], style);
+            r.draw_cursor(Rect::new(x, bounds.y, r.measure(" ", style), bounds.h), CursorShape::Beam, theme.cursor);
+        }
+
+    }
 }
@@ -0,0 +1,234 @@
+use std::io::{self, Stdout, Write};
+use std::time::Duration;
+
+use crossterm::{cursor, event, execute, queue, terminal};
+use crossterm::cursor::SetCursorStyle;
+use crossterm::event::{Event, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{self, Print, SetBackgroundColor, SetForegroundColor};
+use macroquad::prelude::{Color, KeyCode, Rect, Vec2, vec2};
+
+use crate::config::keys::{Key, Modifiers};
+use crate::input::{InputEvent, KeyState};
+
+use super::{CursorShape, Renderer, TextStyle};
+use super::grid::{self, Grid};
+
+
+
+/// Draws into the terminal, where wide graphemes take up two cells.
+/// Fonts do not apply here, and the terminal has no room for padding.
+/// The mouse is not captured, so that text can still be selected by the terminal.
+#[derive(Debug)]
+pub struct TuiRenderer {
+    out: Stdout,
+    grid: Grid,
+}
+
+impl TuiRenderer {
+
+    /// Takes over the terminal until the renderer is dropped.
+    pub fn new() -> io::Result<Self> {
+        let mut out = io::stdout();
+        terminal::enable_raw_mode()?;
+        execute!(out, terminal::EnterAlternateScreen)?;
+
+        Ok(Self {
+            out,
+            grid: Grid::default(),
+        })
+    }
+
+}
+
+impl Renderer for TuiRenderer {
+
+    fn size(&self) -> Vec2 {
+        vec2(self.grid.width() as f32, self.grid.height() as f32)
+    }
+
+    fn scale_pixels(&self, _: f32) -> f32 {
+        0.
+    }
+
+    fn measure(&self, text: &str, _: TextStyle) -> f32 {
+        grid::text_width(text) as f32
+    }
+
+    fn line_height(&self, _: TextStyle) -> f32 {
+        1.
+    }
+
+    fn clear(&mut self, color: Color) {
+        let (width, height) = terminal::size().unwrap_or((self.grid.width(), self.grid.height()));
+        self.grid.clear(width, height, color);
+    }
+
+    fn draw_rect(&mut self, rect: Rect, color: Color) {
+        self.grid.fill(rect, color);
+    }
+
+    fn draw_text(&mut self, text: &str, pos: Vec2, max_width: f32, style: TextStyle) {
+        self.grid.print(text, pos, max_width, style.color);
+    }
+
+    /// The cursor of the terminal itself is used, which takes the color of the terminal.
+    fn draw_cursor(&mut self, rect: Rect, shape: CursorShape, _: Color) {
+        self.grid.set_cursor(rect, shape);
+    }
+
+    fn present(&mut self) -> io::Result<()> {
+
+        queue!(self.out, cursor::Hide)?;
+
+        let mut colors = None;
+        for y in 0..self.grid.height() {
+            queue!(self.out, cursor::MoveTo(0, y))?;
+
+            for x in 0..self.grid.width() {
+                let cell = self.grid.get(x, y).unwrap();
+                if colors != Some((cell.fg, cell.bg)) {
+                    queue!(self.out, SetForegroundColor(rgb(cell.fg)), SetBackgroundColor(rgb(cell.bg)))?;
+                    colors = Some((cell.fg, cell.bg));
+                }
+                queue!(self.out, Print(&cell.symbol))?;
+            }
+        }
+
+        queue!(self.out, style::ResetColor)?;
+
+        if let Some((x, y, shape)) = self.grid.cursor() {
+            let shape = match shape {
+                CursorShape::Block => SetCursorStyle::SteadyBlock,
+                CursorShape::Beam  => SetCursorStyle::SteadyBar,
+            };
+            queue!(self.out, cursor::MoveTo(x, y), shape, cursor::Show)?;
+        }
+
+        self.out.flush()
+
+    }
+
+}
+
+impl Drop for TuiRenderer {
+    fn drop(&mut self) {
+        let _ = execute!(
+            self.out,
+            style::ResetColor,
+            SetCursorStyle::DefaultUserShape,
+            cursor::Show,
+            terminal::LeaveAlternateScreen,
+        );
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+fn rgb(color: Color) -> style::Color {
+    let [r, g, b, _] = color.into();
+    style::Color::Rgb { r, g, b }
+}
+
+/// Waits up to `timeout` for input, or until there is input if no timeout is given.
+pub fn read_input(timeout: Option<Duration>) -> io::Result<Option<InputEvent>> {
+
+    if let Some(timeout) = timeout && !event::poll(timeout)? {
+        return Ok(None);
+    }
+
+    Ok(match event::read()? {
+        Event::Key(ev) => translate_key(ev),
+        Event::Resize(..) => Some(InputEvent::Resize),
+        _ => None,
+    })
+
+}
+
+/// Maps a terminal key event onto the keys used by keybinds.
+/// Most terminals only report presses.
+fn translate_key(ev: KeyEvent) -> Option<InputEvent> {
+    use event::KeyCode as Term;
+
+    let state = match ev.kind {
+        KeyEventKind::Press   => KeyState::Press,
+        KeyEventKind::Repeat  => KeyState::Repeat,
+        KeyEventKind::Release => KeyState::Release,
+    };
+
+    let mut mods = Modifiers::NoMod;
+    if ev.modifiers.contains(KeyModifiers::CONTROL) { mods |= Modifiers::Ctrl }
+    if ev.modifiers.contains(KeyModifiers::ALT)     { mods |= Modifiers::Meta }
+    if ev.modifiers.contains(KeyModifiers::SUPER)   { mods |= Modifiers::Super }
+
+    // terminals report shifted characters as the character itself
+    if let Term::Char(c) = ev.code {
+        let text = (mods.is_empty() && state != KeyState::Release).then_some(c);
+        return Some(match Key::from_char(c) {
+            Some(key) => InputEvent::Key { key: Key::new(key.key_code, key.mods | mods), state, text },
+            None      => InputEvent::Text(text?),
+        });
+    }
+
+    if ev.modifiers.contains(KeyModifiers::SHIFT) { mods |= Modifiers::Shift }
+
+    let key_code = match ev.code {
+        Term::Backspace => KeyCode::Backspace,
+        Term::Enter     => KeyCode::Enter,
+        Term::Left      => KeyCode::Left,
+        Term::Right     => KeyCode::Right,
+        Term::Up        => KeyCode::Up,
+        Term::Down      => KeyCode::Down,
+        Term::Home      => KeyCode::Home,
+        Term::End       => KeyCode::End,
+        Term::PageUp    => KeyCode::PageUp,
+        Term::PageDown  => KeyCode::PageDown,
+        Term::Tab       => KeyCode::Tab,
+        Term::BackTab   => { mods |= Modifiers::Shift; KeyCode::Tab }
+        Term::Delete    => KeyCode::Delete,
+        Term::Insert    => KeyCode::Insert,
+        Term::Esc       => KeyCode::Escape,
+        Term::F(n @ 1..=12) => [
+            KeyCode::F1, KeyCode::F2, KeyCode::F3,  KeyCode::F4,  KeyCode::F5,  KeyCode::F6,
+            KeyCode::F7, KeyCode::F8, KeyCode::F9, KeyCode::F10, KeyCode::F11, KeyCode::F12,
+        ][n as usize - 1],
+        _ => return None,
+    };
+
+    Some(InputEvent::Key { key: Key::new(key_code, mods), state, text: None })
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(code: event::KeyCode, modifiers: KeyModifiers) -> (Option<Key>, Option<char>) {
+        match translate_key(KeyEvent::new(code, modifiers)).unwrap() {
+            InputEvent::Key { key, state: KeyState::Press, text } => (Some(key), text),
+            InputEvent::Text(c) => (None, Some(c)),
+            ev => panic!("unexpected event {ev:?}"),
+        }
+    }
+
+    #[test]
+    fn test_translate_key() {
+        assert_eq!(
+            press(event::KeyCode::Char('G'), KeyModifiers::SHIFT),
+            (Some(Key::new(KeyCode::G, Modifiers::Shift)), Some('G'))
+        );
+        assert_eq!(
+            press(event::KeyCode::Char(':'), KeyModifiers::NONE),
+            (Some(Key::new(KeyCode::Semicolon, Modifiers::Shift)), Some(':'))
+        );
+        assert_eq!(
+            press(event::KeyCode::Char('w'), KeyModifiers::CONTROL),
+            (Some(Key::new(KeyCode::W, Modifiers::Ctrl)), None)
+        );
+        assert_eq!(
+            press(event::KeyCode::BackTab, KeyModifiers::SHIFT),
+            (Some(Key::new(KeyCode::Tab, Modifiers::Shift)), None)
+        );
+        // characters without a key can still be typed
+        assert_eq!(press(event::KeyCode::Char('ä'), KeyModifiers::NONE), (None, Some('ä')));
+    }
+
+}
@@ -1,31 +1,26 @@
-use macroquad::prelude::*;
+use macroquad::prelude::Rect;
 
-use crate::wrap::draw_rectangle_rect;
 use crate::edit::Editor;
-use crate::edit::window::Window;
+use crate::edit::window::{Window, WindowID};
 
-use super::{buffer::BufferRenderer, COLOR_WIDGET_AREA, COLOR_WIDGET_AREA_SEL};
+use super::{buffer::BufferRenderer, Renderer, Theme};
 
 
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct WindowRenderer {
     pub buf: BufferRenderer,
 }
 
 impl WindowRenderer {
-    pub async fn new() -> Result<Self, macroquad::Error> {
-        Ok(Self {
-            buf: BufferRenderer::new().await?,
-        })
-    }
 
-    pub fn render(&mut self, bounds: Rect, active: bool, ed: &Editor, win: &Window) {
+    #[allow(clippy::too_many_arguments)]
+    pub fn render(&mut self, r: &mut dyn Renderer, bounds: Rect, active: bool, ed: &Editor, winid: WindowID, win: &Window, theme: &Theme) {
 
-        draw_rectangle_rect(bounds, if active {
-            COLOR_WIDGET_AREA_SEL
+        r.draw_rect(bounds, if active {
+            theme.widget_active
         } else {
-            COLOR_WIDGET_AREA
+            theme.widget
         });
 
         if let Some(id) = win.buf() {
@@ -34,7 +29,9 @@ impl WindowRenderer {
                 .buffers()
                 .get(id)
                 .unwrap();
-            self.buf.render(bounds, buf, ed.buf().unwrap().mode(), active);
+            let lines = self.buf.visible_lines(r, bounds, winid, buf, theme);
+            let highlights = ed.highlights(id, lines);
+            self.buf.render(r, bounds, winid, buf, ed.buf().unwrap().mode(), active, &highlights, theme);
 
         }
         // TODO: some sort of indicator for empty windows
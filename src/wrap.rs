@@ -1,37 +1,28 @@
-const KEY_REPEAT_DELAY: f64 = 5.0;
-
-use std::collections::HashMap;
-use std::time::Duration;
-
 use macroquad::prelude::*;
+use unicode_segmentation::UnicodeSegmentation;
 
-// should be called in the main loop
-// sleeps in the current iteration until the desired framerate is achieved
-pub fn sleep_framerate(framerate: f32) {
-
-    let minft = 1. / framerate;
-    let ft = get_frame_time();
-
-    if ft < minft {
-        let sleep = minft - ft;
-        std::thread::sleep(Duration::from_secs_f32(sleep));
-    }
-
-}
-
-/// Truncates a string slice to the given width
+/// Truncates a string slice to the given width, which may leave it empty
 pub fn clamp_slice<'a>(mut slice: &'a str, max_width: f32, params: TextParams) -> &'a str {
-    while measure_text(
+    // a negative width is never reached, not even by an empty slice
+    while !slice.is_empty() && measure_text(
         slice,
         params.font,
         params.font_size,
         params.font_scale,
     ).width > max_width {
-        slice = &slice[..slice.len()-1];
+        slice = pop_grapheme(slice);
     }
     slice
 }
 
+/// Removes the last grapheme cluster of a string slice
+fn pop_grapheme(slice: &str) -> &str {
+    slice
+        .grapheme_indices(true)
+        .next_back()
+        .map_or(slice, |(idx, _)| &slice[..idx])
+}
+
 pub fn measure_char(
     c:          char,
     font:       Option<&Font>,
@@ -56,8 +47,10 @@ pub fn draw_text_bounded(
     // get slice of text that is small enough to render
     let mut text_slice = text.as_ref();
 
-    while measure_text(text_slice, params.font, params.font_size, params.font_scale).width > max_width {
-        text_slice = &text_slice[..text_slice.len()-1];
+    while !text_slice.is_empty()
+        && measure_text(text_slice, params.font, params.font_size, params.font_scale).width > max_width
+    {
+        text_slice = pop_grapheme(text_slice);
     }
 
     // adding font size to y, such that the text origin is at
@@ -70,42 +63,6 @@ pub fn get_last_key_down() -> Option<KeyCode> {
     get_keys_down().into_iter().next()
 }
 
-#[allow(static_mut_refs)]
-pub fn is_key_repeated(key_code: KeyCode) -> bool {
-
-    static mut TIME_MAP: Option<HashMap<KeyCode, f64>> = None;
-
-    unsafe {
-
-        if TIME_MAP.is_none() {
-            TIME_MAP = Some(HashMap::new());
-        }
-
-        let time = TIME_MAP
-            .as_mut()
-            .unwrap();
-
-        time.entry(key_code).or_insert(0.);
-
-        let time_pressed = time
-            .get_mut(&key_code)
-            .unwrap();
-
-        if is_key_down(key_code) {
-            *time_pressed += 1.;
-        } else {
-            *time_pressed = 0.;
-        }
-
-        *time_pressed >= KEY_REPEAT_DELAY
-
-    }
-}
-
-pub fn is_key_active(key_code: KeyCode) -> bool {
-    is_key_pressed(key_code) || is_key_repeated(key_code)
-}
-
 pub fn is_shift_down() -> bool {
     is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift)
 }